@@ -1,29 +1,100 @@
-use crate::llm::{LLMClient, LLMRequest};
-use crate::manifest::{Manifest, Phase};
+use crate::approval::{ApprovalDecision, ApprovalGate, AWAITING_APPROVAL};
+use crate::attachments::Attachment;
+use crate::dry_run::{self, DryRunReport, ModelSource, PhaseDryRun};
+use crate::ensemble::{self, Candidate, Verdict, VERDICT_SUFFIX};
+use crate::llm::{
+    CacheConfig, ChatMessage, ContentBlock, GeminiConfig, Generation, LLMClient, LLMError,
+    LLMRequest, MultiTurnRequest, TokenUsage, MIN_THINKING_BUDGET,
+};
+use crate::manifest::{
+    parse_json_output, Approval, Ensemble, EnsembleStrategy, ForEach, Manifest, Phase,
+};
+use crate::metrics::PhaseMetrics;
+use crate::mock::MockConfig;
+use crate::model_catalog::{ModelCatalog, DEFAULT_MODEL};
+use crate::report::{self, ReportSection, REPORT_KEY};
+use crate::response_cache::ResponseCache;
+use crate::telemetry::{self, RunLogEntry};
+use crate::token_batch::{BatchSender, TokenBatchConfig, TokenBatcher};
 use anyhow::{anyhow, Result};
-use chrono::Local;
+use chrono::{Local, Utc};
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use tauri::{AppHandle, Emitter}; // Use AppHandle for global event emission (Tauri 2.0)
+use tracing::{Instrument, Level};
 
 // ------------------------------------------------------------------
 // Event Payloads (Sent to Frontend)
 // ------------------------------------------------------------------
 
+/// Identifies the run an event belongs to, so listeners can follow one run (or
+/// session) while others are in flight. `seq` increases across all of a run's events.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct EventScope {
+    pub run_id: String,
+    pub session_id: Option<i64>,
+    pub seq: u64,
+}
+
+/// Hands out scopes for one run's events. Scopes are taken where an event is produced
+/// (stream-token batches before they go to the delivery task), so `seq` follows emission order.
+#[derive(Debug, Clone)]
+struct EventSequence {
+    run_id: String,
+    session_id: Option<i64>,
+    next_seq: Arc<AtomicU64>,
+}
+
+impl EventSequence {
+    fn new(session_id: Option<i64>) -> Self {
+        Self {
+            run_id: new_run_id(),
+            session_id,
+            next_seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn next(&self) -> EventScope {
+        EventScope {
+            run_id: self.run_id.clone(),
+            session_id: self.session_id,
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
 #[derive(Clone, Serialize)]
 struct LogPayload {
+    #[serde(flatten)]
+    scope: EventScope,
     message: String,
 }
 
 #[derive(Clone, Serialize)]
 struct PhaseUpdatePayload {
+    #[serde(flatten)]
+    scope: EventScope,
     phase_id: String,
-    status: String, // "running", "completed", "failed"
+    status: String, // "running", "awaiting_approval", "completed", "failed"
+    progress: Option<PhaseProgress>, // Fan-out phases: items finished so far
+}
+
+/// Items of a `foreach` phase finished (completed or failed) out of the total
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct PhaseProgress {
+    pub completed: usize,
+    pub total: usize,
 }
 
 #[derive(Clone, Serialize)]
 struct StreamTokenPayload {
+    #[serde(flatten)]
+    scope: EventScope,
     token: String,
     phase_id: String,
 }
@@ -32,7 +103,8 @@ struct StreamTokenPayload {
 /// Extended for user data accessibility (IM-5001, IM-5002)
 #[derive(Clone, Serialize)]
 struct PhaseOutputPayload {
-    session_id: Option<i64>,
+    #[serde(flatten)]
+    scope: EventScope,
     phase_id: String,
     phase_name: String,
     status: String,                // "running", "completed", "failed"
@@ -40,6 +112,24 @@ struct PhaseOutputPayload {
     user_input: Option<String>,    // IM-5002: User input/manifest data sent to LLM
     output: Option<String>,
     error: Option<String>,
+    cache_hit: bool,           // Output was served from the response cache
+    reasoning: Option<String>, // Thinking/reasoning trace, kept separate from the output
+    stop_reason: Option<String>,
+    truncated: bool, // Output still ends at the token limit after any continuations
+    metrics: Option<PhaseMetrics>, // Timing for the phase (completed phases only)
+}
+
+/// A phase-output event before it is scoped to the run (see `emit_phase_output`)
+#[derive(Default)]
+struct PhaseOutput<'a> {
+    phase_id: &'a str,
+    phase_name: &'a str,
+    status: &'a str,
+    system_prompt: Option<&'a str>,
+    user_input: Option<&'a str>,
+    output: Option<&'a str>,
+    error: Option<&'a str>,
+    run: Option<&'a PhaseRun>,
 }
 
 // ------------------------------------------------------------------
@@ -50,6 +140,7 @@ struct PhaseOutputPayload {
 pub enum PhaseStatus {
     Pending,
     Running,
+    AwaitingApproval,
     Completed,
     Failed(String),
     Skipped,
@@ -60,7 +151,7 @@ pub struct AgentState {
     pub current_phase_id: Option<String>,
     pub phase_statuses: HashMap<String, PhaseStatus>,
     pub context: HashMap<String, String>,
-    pub logs: Vec<String>,
+    pub logs: Vec<RunLogEntry>, // Session run log (stored in session_run_logs)
 }
 
 impl AgentState {
@@ -78,6 +169,28 @@ impl AgentState {
 // The Agent
 // ------------------------------------------------------------------
 
+/// Context keys always seeded with the initial input (backward compatibility)
+pub const FALLBACK_INPUT_KEYS: [&str; 3] = ["initial_input", "target_company", "research_subject"];
+
+/// A completed phase: the (stitched) generation and its timing
+struct PhaseRun {
+    generation: Generation,
+    metrics: PhaseMetrics,
+    report: Option<String>, // Report section, when not the output itself (fan-out phases)
+}
+
+/// Items of a `foreach` phase run at the same time unless the phase sets `concurrency`
+pub const DEFAULT_FOREACH_CONCURRENCY: usize = 3;
+
+/// Context key holding the item input of one `foreach` run
+const FOREACH_ITEM_KEY: &str = "foreach_item";
+
+/// Continuation requests per phase when output stops at max_tokens (phase `max_continuations`)
+pub const DEFAULT_MAX_CONTINUATIONS: u32 = 2;
+
+const CONTINUE_PROMPT: &str = "Your previous response was cut off at the output limit. \
+Continue exactly where it stopped, without repeating any of it or adding a preamble.";
+
 pub struct Agent {
     manifest: Manifest,
     state: AgentState,
@@ -85,6 +198,26 @@ pub struct Agent {
     app_handle: Option<AppHandle>, // AppHandle for global event emission (Tauri 2.0)
     model_override: Option<String>, // UI-selected model override
     session_id: Option<i64>,       // Research session ID for persistence
+    dry_run: bool,                 // Render prompts only, never call providers
+    dry_run_report: Option<DryRunReport>,
+    prompt_cache: Option<CacheConfig>, // Anthropic prompt caching for phase requests
+    token_usage: TokenUsage,           // Provider-reported usage summed over phases
+    attachments: Vec<Attachment>,      // Session files phases can reference by name
+    model_catalog: ModelCatalog,       // Pricing and deprecation data for phase models
+    events: EventSequence,             // Run id and sequence carried by every emitted event
+    token_batching: TokenBatchConfig,  // Coalescing of stream-token events
+    inputs: BTreeMap<String, String>,  // Declared manifest inputs (validated by the caller)
+    forward_tokens: bool, // Emit stream-token events (off for concurrent fan-out items)
+    approvals: Option<ApprovalGate>, // Reviewer decisions for `approval: required` phases
+}
+
+/// Run identifier for agents not given one by the caller
+fn new_run_id() -> String {
+    format!(
+        "run-{}-{:08x}",
+        Local::now().format("%Y%m%d%H%M%S"),
+        rand::random::<u32>()
+    )
 }
 
 impl Agent {
@@ -104,16 +237,251 @@ impl Agent {
             app_handle,
             model_override,
             session_id,
+            dry_run: false,
+            dry_run_report: None,
+            prompt_cache: Some(CacheConfig::default()),
+            token_usage: TokenUsage::default(),
+            attachments: Vec::new(),
+            model_catalog: ModelCatalog::builtin(),
+            events: EventSequence::new(session_id),
+            token_batching: TokenBatchConfig::default(),
+            inputs: BTreeMap::new(),
+            forward_tokens: true,
+            approvals: None,
         }
     }
 
+    /// Builder: Identifier carried by this run's events (the UI picks it to subscribe
+    /// before the run starts)
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.events.run_id = run_id.into();
+        self
+    }
+
+    /// Builder: Values for the manifest's declared inputs (see `Manifest::resolve_inputs`);
+    /// each is placed in the context under its own key
+    pub fn with_inputs(mut self, inputs: BTreeMap<String, String>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Builder: Where reviewer decisions arrive for phases with `approval: required`
+    /// (without a gate such phases continue unreviewed)
+    pub fn with_approval_gate(mut self, gate: ApprovalGate) -> Self {
+        self.approvals = Some(gate);
+        self
+    }
+
+    /// Builder: How streamed text is coalesced into stream-token events
+    pub fn with_token_batching(mut self, config: TokenBatchConfig) -> Self {
+        self.token_batching = config;
+        self
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.events.run_id
+    }
+
+    /// Scope for the next emitted event
+    pub fn event_scope(&self) -> EventScope {
+        self.events.next()
+    }
+
+    /// Builder: Configure the offline mock provider used by mock* models
+    pub fn with_mock_config(mut self, config: MockConfig) -> Self {
+        self.llm_client = self.llm_client.with_mock_config(config);
+        self
+    }
+
+    /// Builder: Serve identical phase requests from the response cache
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.llm_client = self.llm_client.with_response_cache(cache);
+        self
+    }
+
+    /// Builder: Gemini generationConfig and safety settings for Gemini phases
+    pub fn with_gemini_config(mut self, config: GeminiConfig) -> Self {
+        self.llm_client = self.llm_client.with_gemini_config(config);
+        self
+    }
+
+    /// Builder: Files attached to this run; phases select them via `attachments:`
+    pub fn with_attachments(mut self, attachments: Vec<Attachment>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Builder: Model catalog used for cost estimates and model warnings
+    pub fn with_model_catalog(mut self, catalog: ModelCatalog) -> Self {
+        self.model_catalog = catalog;
+        self
+    }
+
+    /// Builder: Configure Anthropic prompt caching for phase requests (None disables it)
+    pub fn with_prompt_cache(mut self, config: Option<CacheConfig>) -> Self {
+        self.prompt_cache = config;
+        self
+    }
+
+    /// Provider-reported token usage summed over the phases run so far
+    pub fn token_usage(&self) -> &TokenUsage {
+        &self.token_usage
+    }
+
+    /// Builder: Render every phase's prompts and estimate cost instead of calling providers
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Report produced by the last dry run (None unless `with_dry_run(true)` was used)
+    pub fn dry_run_report(&self) -> Option<&DryRunReport> {
+        self.dry_run_report.as_ref()
+    }
+
     // Public accessor for context
     pub fn get_context(&self, key: &str) -> Option<String> {
         self.state.context.get(key).cloned()
     }
 
+    /// This run's log lines, in order
+    pub fn run_log(&self) -> &[RunLogEntry] {
+        &self.state.logs
+    }
+
     pub async fn run_workflow(&mut self, initial_input: &str) -> Result<()> {
-        // DYNAMIC INPUT KEY DISCOVERY
+        let span = tracing::info_span!(
+            "workflow",
+            session_id = ?self.session_id,
+            manifest = %self.manifest.manifest.id
+        );
+        self.run_phases(initial_input).instrument(span).await
+    }
+
+    async fn run_phases(&mut self, initial_input: &str) -> Result<()> {
+        self.seed_inputs(initial_input);
+
+        let phases = self.manifest.phases.clone();
+
+        if self.dry_run {
+            let report = self.build_dry_run_report(initial_input, &phases);
+            self.log(&format!(
+                "🧪 DRY RUN: {} phases, ~{} tokens, ~${:.4} estimated, {} warnings",
+                report.phases.len(),
+                report.total_estimated_tokens,
+                report.total_estimated_cost_usd,
+                report.warning_count
+            ));
+            self.dry_run_report = Some(report);
+            return Ok(());
+        }
+
+        // Phase outputs for the final report, assembled into markdown_file after the run
+        let mut sections = Vec::new();
+
+        for phase in phases {
+            self.state.current_phase_id = Some(phase.id.clone());
+            self.update_phase_status(&phase.id, PhaseStatus::Running);
+            // Note: "running" emit with prompts now happens inside execute_phase (IM-5003)
+
+            let span = tracing::info_span!(
+                "phase",
+                phase_id = %phase.id,
+                model = tracing::field::Empty,
+                latency_ms = tracing::field::Empty
+            );
+            let result = match self.run_phase(&phase).instrument(span.clone()).await {
+                Ok(run) if phase.approval == Approval::Required => {
+                    self.review_phase(&phase, run)
+                        .instrument(span.clone())
+                        .await
+                }
+                result => result,
+            };
+            match result {
+                Ok(run) => {
+                    let output = run.generation.text.clone();
+                    span.record("latency_ms", run.metrics.duration_ms);
+                    span.in_scope(|| self.log(&format!("Phase {} completed.", phase.name)));
+                    self.update_phase_status(&phase.id, PhaseStatus::Completed);
+
+                    // Emit phase-output event with completed status and output for persistence
+                    // Note: prompts already emitted with "running" status; here we just add output
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &phase.id,
+                        phase_name: &phase.name,
+                        status: "completed",
+                        output: Some(&output),
+                        run: Some(&run),
+                        ..PhaseOutput::default()
+                    });
+
+                    // Store to specific target/schema for inter-phase dependencies
+                    if let Some(target) = &phase.output_target {
+                        self.state.context.insert(target.clone(), output.clone());
+                    } else if let Some(schema) = &phase.output_schema {
+                        self.state.context.insert(schema.clone(), output.clone());
+                    }
+
+                    sections.push(ReportSection {
+                        phase_id: phase.id.clone(),
+                        name: phase.name.clone(),
+                        content: run.report.unwrap_or(output),
+                    });
+                }
+                Err(e) => {
+                    span.in_scope(|| {
+                        self.log_at(Level::ERROR, &format!("Phase {} failed: {}", phase.name, e))
+                    });
+                    self.update_phase_status(&phase.id, PhaseStatus::Failed(e.to_string()));
+
+                    // Emit phase-output event with failed status and error for persistence
+                    // Note: prompts already emitted with "running" status; here we just add error
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &phase.id,
+                        phase_name: &phase.name,
+                        status: "failed",
+                        error: Some(&e.to_string()),
+                        ..PhaseOutput::default()
+                    });
+
+                    return Err(e);
+                }
+            }
+        }
+
+        self.state.current_phase_id = None;
+
+        // The manifest's report template, else every phase output as a section
+        let final_report = match &self.manifest.report {
+            Some(template) => {
+                let assembled = report::assemble(template, &sections, &self.state.context);
+                for warning in &assembled.warnings {
+                    self.log(&format!("⚠️ REPORT: {}", warning));
+                }
+                assembled.text
+            }
+            None => report::concatenate(&sections),
+        };
+        self.state
+            .context
+            .insert(REPORT_KEY.to_string(), final_report);
+
+        if self.token_usage != TokenUsage::default() {
+            self.log(&format!(
+                "🧾 TOTAL USAGE: {} in / {} out tokens (prompt cache: {} written, {} read)",
+                self.token_usage.input_tokens,
+                self.token_usage.output_tokens,
+                self.token_usage.cache_creation_input_tokens,
+                self.token_usage.cache_read_input_tokens
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// DYNAMIC INPUT KEY DISCOVERY
+    fn seed_inputs(&mut self, initial_input: &str) {
         // Instead of hard-coding known input keys, we dynamically discover what input keys
         // each phase in the manifest expects, and populate them all with the user's initial input.
         // This ensures compatibility with ANY manifest format without code changes.
@@ -128,8 +496,7 @@ impl Agent {
         }
 
         // Step 2: Always include universal fallback keys for backward compatibility
-        let fallback_keys = ["initial_input", "target_company", "research_subject"];
-        for key in fallback_keys {
+        for key in FALLBACK_INPUT_KEYS {
             discovered_keys.insert(key.to_string());
         }
 
@@ -146,96 +513,199 @@ impl Agent {
             discovered_keys
         ));
 
-        let phases = self.manifest.phases.clone();
+        // Step 4: Declared inputs go under their own keys
+        for (key, value) in &self.inputs {
+            self.state.context.insert(key.clone(), value.clone());
+        }
+        if !self.inputs.is_empty() {
+            let keys: Vec<&String> = self.inputs.keys().collect();
+            self.log(&format!("📋 Declared inputs: {:?}", keys));
+        }
+    }
 
-        // CUMULATIVE REPORT: Aggregate all phase outputs into markdown_file
-        // This ensures the final report contains ALL phase outputs, not just the final synthesis
-        let mut cumulative_report = String::new();
+    /// Walk the phases as run_workflow would, rendering prompts with placeholder
+    /// outputs for upstream phases and estimating tokens/cost per phase
+    fn build_dry_run_report(&mut self, initial_input: &str, phases: &[Phase]) -> DryRunReport {
+        let mut report = DryRunReport::new(
+            &self.manifest.manifest.id,
+            &self.manifest.manifest.name,
+            initial_input,
+        );
+        let mut produced: std::collections::HashSet<String> = FALLBACK_INPUT_KEYS
+            .iter()
+            .map(|k| k.to_string())
+            .chain(self.inputs.keys().cloned())
+            .collect();
 
         for phase in phases {
-            self.state.current_phase_id = Some(phase.id.clone());
-            self.update_phase_status(&phase.id, PhaseStatus::Running);
-            // Note: "running" emit with prompts now happens inside execute_phase (IM-5003)
-
-            match self.execute_phase(&phase).await {
-                Ok(output) => {
-                    self.log(&format!("Phase {} completed.", phase.name));
-                    self.update_phase_status(&phase.id, PhaseStatus::Completed);
-
-                    // Emit phase-output event with completed status and output for persistence
-                    // Note: prompts already emitted with "running" status; here we just add output
-                    self.emit_phase_output(
-                        &phase.id,
-                        &phase.name,
-                        "completed",
-                        None,
-                        None,
-                        Some(&output),
-                        None,
-                    );
-
-                    // Store to specific target/schema for inter-phase dependencies
-                    if let Some(target) = &phase.output_target {
-                        self.state.context.insert(target.clone(), output.clone());
-                    } else if let Some(schema) = &phase.output_schema {
-                        self.state.context.insert(schema.clone(), output.clone());
-                    }
+            let mut warnings = Vec::new();
+            let (model, model_source) = self.resolve_model(phase);
+            let model = model.to_string();
+            let system_prompt = self.build_system_prompt(phase);
 
-                    // ALWAYS append to cumulative report with phase header
-                    // This ensures ALL phases appear in the final markdown_file output
-                    if !cumulative_report.is_empty() {
-                        cumulative_report.push_str("\n\n---\n\n");
+            if let Some(input_key) = &phase.input {
+                if !produced.contains(input_key) {
+                    warnings.push(format!(
+                        "Missing input: '{}' is not produced by any earlier phase; it will receive the initial input",
+                        input_key
+                    ));
+                }
+            }
+            // A fan-out phase without `input` is sent only its item
+            let resolved = (phase.foreach.is_none() || phase.input.is_some()).then(|| {
+                self.resolve_input(phase).unwrap_or_else(|e| {
+                    warnings.push(e.to_string());
+                    String::new()
+                })
+            });
+            let user_input = match &phase.foreach {
+                Some(foreach) => {
+                    if !produced.contains(foreach.source_key()) {
+                        warnings.push(format!(
+                            "Missing foreach list: '{}' is not produced by any earlier phase",
+                            foreach.source_key()
+                        ));
                     }
-                    cumulative_report.push_str(&format!("## {}\n\n{}", phase.name, output));
+                    warnings.push(format!(
+                        "Runs once per item of '{}'; the estimate is for one item",
+                        foreach.items
+                    ));
+                    let placeholder = format!("<one item of {}>", foreach.items);
+                    item_input(resolved.as_deref(), &serde_json::Value::String(placeholder))
                 }
-                Err(e) => {
-                    self.log(&format!("Phase {} failed: {}", phase.name, e));
-                    self.update_phase_status(&phase.id, PhaseStatus::Failed(e.to_string()));
+                None => resolved.unwrap_or_default(),
+            };
+            for name in self.attachments_for(phase).1 {
+                warnings.push(format!("Missing attachment: '{}'", name));
+            }
 
-                    // Emit phase-output event with failed status and error for persistence
-                    // Note: prompts already emitted with "running" status; here we just add error
-                    self.emit_phase_output(
-                        &phase.id,
-                        &phase.name,
-                        "failed",
-                        None,
-                        None,
-                        None,
-                        Some(&e.to_string()),
-                    );
+            let estimated_input_tokens =
+                dry_run::estimate_tokens(&system_prompt) + dry_run::estimate_tokens(&user_input);
+            // Thinking tokens are billed as output
+            let thinking_budget = phase
+                .reasoning
+                .as_ref()
+                .and_then(|r| r.thinking_budget)
+                .map_or(0, |b| b.max(MIN_THINKING_BUDGET) as usize);
+            let estimated_output_tokens = dry_run::MAX_OUTPUT_TOKENS + thinking_budget;
+            let mut estimated_cost_usd = self.model_catalog.estimate_cost(
+                &model,
+                estimated_input_tokens,
+                estimated_output_tokens,
+            );
+            if let Some(warning) = self.model_catalog.model_warning(&model) {
+                warnings.push(warning);
+            }
+            if let Some(ensemble) = &phase.ensemble {
+                // One call per ensemble model instead of the phase model
+                estimated_cost_usd = ensemble
+                    .models
+                    .iter()
+                    .map(|m| {
+                        self.model_catalog.estimate_cost(
+                            m,
+                            estimated_input_tokens,
+                            estimated_output_tokens,
+                        )
+                    })
+                    .sum();
+                warnings.extend(
+                    ensemble
+                        .models
+                        .iter()
+                        .filter_map(|m| self.model_catalog.model_warning(m)),
+                );
+                let judge = match ensemble.strategy {
+                    EnsembleStrategy::Vote => "",
+                    _ => " plus a judge call (not estimated)",
+                };
+                warnings.push(format!(
+                    "Runs on {} models ({}){}",
+                    ensemble.models.len(),
+                    ensemble.models.join(", "),
+                    judge
+                ));
+            }
 
-                    return Err(e);
-                }
+            // Placeholder output so downstream phases render realistically
+            if let Some(key) = phase
+                .output_target
+                .as_ref()
+                .or(phase.output_schema.as_ref())
+            {
+                self.state.context.insert(
+                    key.clone(),
+                    format!("<{} output from phase {} ({})>", key, phase.id, phase.name),
+                );
+                produced.insert(key.clone());
             }
+
+            report.push(PhaseDryRun {
+                phase_id: phase.id.clone(),
+                phase_name: phase.name.clone(),
+                model,
+                model_source,
+                system_prompt,
+                user_input,
+                estimated_input_tokens,
+                estimated_output_tokens,
+                estimated_cost_usd,
+                warnings,
+            });
         }
 
-        // Store the cumulative report as markdown_file for the final output
-        // This ensures get_context("markdown_file") returns ALL phase outputs
-        self.state
-            .context
-            .insert("markdown_file".to_string(), cumulative_report);
+        report
+    }
 
-        Ok(())
+    /// Resolve the model for a phase: UI override, then phase config, then default
+    fn resolve_model<'a>(&'a self, phase: &'a Phase) -> (&'a str, ModelSource) {
+        if let Some(model) = self.model_override.as_deref() {
+            (model, ModelSource::Override)
+        } else if let Some(model) = phase.model.as_deref() {
+            (model, ModelSource::Phase)
+        } else {
+            (DEFAULT_MODEL, ModelSource::Default)
+        }
+    }
+
+    fn build_system_prompt(&self, phase: &Phase) -> String {
+        system_prompt(phase, &date_line())
+    }
+
+    /// Phase input from context: the `input` key if set, otherwise the whole context as JSON
+    fn resolve_input(&self, phase: &Phase) -> Result<String> {
+        if let Some(input_key) = &phase.input {
+            Ok(self
+                .state
+                .context
+                .get(input_key)
+                .ok_or_else(|| anyhow!("Missing input: {}", input_key))?
+                .clone())
+        } else {
+            Ok(serde_json::to_string(&self.state.context)?)
+        }
     }
 
     /// Emit a phase-output event for frontend session persistence
     /// This enables the frontend to save phase outputs to SQLite in real-time
     /// IM-5003: Extended to include system_prompt and user_input for user data accessibility
-    fn emit_phase_output(
-        &self,
-        phase_id: &str,
-        phase_name: &str,
-        status: &str,
-        system_prompt: Option<&str>,
-        user_input: Option<&str>,
-        output: Option<&str>,
-        error: Option<&str>,
-    ) {
+    fn emit_phase_output(&self, event: PhaseOutput) {
+        let PhaseOutput {
+            phase_id,
+            phase_name,
+            status,
+            system_prompt,
+            user_input,
+            output,
+            error,
+            run,
+        } = event;
+        let generation = run.map(|r| &r.generation);
         if let Some(app) = &self.app_handle {
             match app.emit(
                 "phase-output",
                 PhaseOutputPayload {
-                    session_id: self.session_id,
+                    scope: self.event_scope(),
                     phase_id: phase_id.to_string(),
                     phase_name: phase_name.to_string(),
                     status: status.to_string(),
@@ -243,51 +713,161 @@ impl Agent {
                     user_input: user_input.map(|s| s.to_string()),
                     output: output.map(|s| s.to_string()),
                     error: error.map(|s| s.to_string()),
+                    cache_hit: generation.is_some_and(|g| g.cache_hit),
+                    reasoning: generation.and_then(|g| g.reasoning.clone()),
+                    stop_reason: generation
+                        .and_then(|g| g.stop_reason.as_ref())
+                        .map(|r| r.as_str().to_string()),
+                    truncated: generation.is_some_and(|g| g.is_truncated()),
+                    metrics: run.map(|r| r.metrics.clone()),
                 },
             ) {
-                Ok(_) => println!(
-                    "[AGENT-EMIT] ✓ Phase output: {} -> {} (session: {:?})",
-                    phase_id, status, self.session_id
-                ),
-                Err(e) => eprintln!("[AGENT-EMIT-ERROR] Failed to emit phase-output: {}", e),
+                Ok(_) => tracing::debug!(phase_id, status, "emitted phase-output"),
+                Err(e) => tracing::warn!("Failed to emit phase-output: {}", e),
             }
         }
     }
 
-    async fn execute_phase(&mut self, phase: &Phase) -> Result<String> {
-        // Use UI-selected model override, then phase config, then default to Claude
-        let model = self
-            .model_override
-            .as_deref()
-            .or(phase.model.as_deref())
-            .unwrap_or("claude-sonnet-4-5-20250929");
+    /// Content blocks for the attachments a phase references, plus unmatched references
+    fn attachments_for(&self, phase: &Phase) -> (Vec<ContentBlock>, Vec<String>) {
+        let mut blocks = Vec::new();
+        let mut missing = Vec::new();
+
+        for reference in &phase.attachments {
+            let matched: Vec<&Attachment> = self
+                .attachments
+                .iter()
+                .filter(|a| a.matches(reference))
+                .collect();
+            if matched.is_empty() && reference != crate::attachments::ALL_ATTACHMENTS {
+                missing.push(reference.clone());
+            }
+            for attachment in matched {
+                if let Some(block) = &attachment.block {
+                    if !blocks.contains(block) {
+                        blocks.push(block.clone());
+                    }
+                }
+            }
+        }
+
+        (blocks, missing)
+    }
 
-        self.log(&format!("📤 SENDING → {} [{}]", model, phase.name));
+    /// Run a phase once, once per item for `foreach` phases, or once per model for
+    /// `ensemble` phases
+    async fn run_phase(&mut self, phase: &Phase) -> Result<PhaseRun> {
+        match (&phase.foreach, &phase.ensemble) {
+            (Some(foreach), _) => self.execute_foreach(phase, foreach).await,
+            (None, Some(ensemble)) => self.execute_ensemble(phase, ensemble).await,
+            (None, None) => self.execute_phase(phase).await,
+        }
+    }
 
-        let input_data = if let Some(input_key) = &phase.input {
-            self.state
-                .context
-                .get(input_key)
-                .ok_or_else(|| anyhow!("Missing input: {}", input_key))?
-                .clone()
-        } else {
-            serde_json::to_string(&self.state.context)?
+    /// Hold a completed phase for review (`approval: required`) and apply the decision:
+    /// the output as approved or edited, a re-run with the reviewer's feedback, or the
+    /// end of the run
+    async fn review_phase(&mut self, phase: &Phase, run: PhaseRun) -> Result<PhaseRun> {
+        let Some(mut gate) = self.approvals.take() else {
+            self.log(&format!(
+                "⚠️ Phase {} requires approval, but no reviewer is connected; continuing",
+                phase.name
+            ));
+            return Ok(run);
         };
+        let result = self.await_decisions(&mut gate, phase, run).await;
+        self.approvals = Some(gate);
+        result
+    }
+
+    async fn await_decisions(
+        &mut self,
+        gate: &mut ApprovalGate,
+        phase: &Phase,
+        mut run: PhaseRun,
+    ) -> Result<PhaseRun> {
+        let mut phase = phase.clone();
+        loop {
+            self.update_phase_status(&phase.id, PhaseStatus::AwaitingApproval);
+            self.emit_phase_output(PhaseOutput {
+                phase_id: &phase.id,
+                phase_name: &phase.name,
+                status: AWAITING_APPROVAL,
+                output: Some(&run.generation.text),
+                run: Some(&run),
+                ..PhaseOutput::default()
+            });
+            self.log(&format!(
+                "⏸️ AWAITING APPROVAL: review the output of {} to continue",
+                phase.name
+            ));
+
+            match gate.wait(&phase.id).await? {
+                ApprovalDecision::Approve => {
+                    self.log(&format!("✅ APPROVED: {}", phase.name));
+                    return Ok(run);
+                }
+                ApprovalDecision::Edit { output } => {
+                    self.log(&format!("✏️ APPROVED WITH EDITS: {}", phase.name));
+                    run.generation.text = output;
+                    run.report = None;
+                    return Ok(run);
+                }
+                ApprovalDecision::Reject { feedback } => {
+                    self.log(&format!(
+                        "↩️ REJECTED: re-running {} with the reviewer's feedback",
+                        phase.name
+                    ));
+                    phase.instructions = format!(
+                        "{}\n\nReviewer feedback on a previous attempt (address it in this output):\n{}",
+                        phase.instructions, feedback
+                    );
+                    self.update_phase_status(&phase.id, PhaseStatus::Running);
+                    run = self.run_phase(&phase).await?;
+                }
+                ApprovalDecision::Cancel => {
+                    self.log(&format!("⏹️ CANCELLED at the review of {}", phase.name));
+                    anyhow::bail!("Run cancelled by the reviewer at phase {}", phase.id);
+                }
+            }
+        }
+    }
 
-        // Inject current date so LLMs search for up-to-date information
-        let current_date = Local::now().format("%B %d, %Y").to_string(); // e.g., "November 28, 2025"
+    async fn execute_phase(&mut self, phase: &Phase) -> Result<PhaseRun> {
+        let (model, _) = self.resolve_model(phase);
+        let model = model.to_string();
+        tracing::Span::current().record("model", model.as_str());
 
-        let system_prompt = format!(
-            "You are an autonomous research agent executing phase '{}'.\n\
-            IMPORTANT: Today's date is {}. When researching, prioritize finding the most recent and up-to-date information available, including data from {} and earlier.\n\n\
-            Instructions:\n{}",
-            phase.name, current_date, current_date, phase.instructions
-        );
+        self.log(&format!("📤 SENDING → {} [{}]", model, phase.name));
+        if let Some(warning) = self.model_catalog.model_warning(&model) {
+            self.log(&format!("⚠️ {}", warning));
+        }
+
+        let input_data = self.resolve_input(phase)?;
+        let date_line = date_line();
+        let system_prompt = system_prompt(phase, &date_line);
+
+        let (attachments, missing) = self.attachments_for(phase);
+        for name in &missing {
+            self.log(&format!(
+                "⚠️ Attachment not found for this session: {}",
+                name
+            ));
+        }
+        if !attachments.is_empty() {
+            self.log(&format!("📎 ATTACHED: {} file(s)", attachments.len()));
+        }
 
         let req = LLMRequest {
             system: system_prompt.clone(),
             user: input_data.clone(),
-            model: model.to_string(),
+            model,
+            response_schema: self.manifest.output_json_schema(phase),
+            skip_cache: phase.cache == Some(false),
+            cache_config: self.prompt_cache.clone(),
+            attachments,
+            reasoning: phase.reasoning.clone(),
+            volatile_system: Some(date_line),
         };
 
         self.log(&format!(
@@ -298,55 +878,33 @@ impl Agent {
 
         // IM-5003: Emit "running" event WITH prompts for user data accessibility
         // This captures the system prompt and user input for later viewing/resume
-        self.emit_phase_output(
-            &phase.id,
-            &phase.name,
-            "running",
-            Some(&system_prompt),
-            Some(&input_data),
-            None,
-            None,
-        );
+        self.emit_phase_output(PhaseOutput {
+            phase_id: &phase.id,
+            phase_name: &phase.name,
+            status: "running",
+            system_prompt: Some(&system_prompt),
+            user_input: Some(&input_data),
+            ..PhaseOutput::default()
+        });
 
         self.log("⏳ CONNECTING to API...");
 
-        let start = std::time::Instant::now();
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let mut ttft_ms = None;
+        let mut retries = 0;
 
         // Try streaming first, fall back to non-streaming
         let result = match self.llm_client.generate_stream(req.clone()).await {
-            Ok(mut stream) => {
-                self.log("🔗 CONNECTED - streaming response...");
-                let mut full_response = String::new();
-                let mut token_count = 0;
-
-                while let Some(chunk_result) = stream.next().await {
-                    match chunk_result {
-                        Ok(token) => {
-                            full_response.push_str(&token);
-                            token_count += 1;
-
-                            // Emit streaming token to frontend via AppHandle (global event)
-                            if let Some(app) = &self.app_handle {
-                                let _ = app.emit(
-                                    "stream-token",
-                                    StreamTokenPayload {
-                                        token: token.clone(),
-                                        phase_id: phase.id.clone(),
-                                    },
-                                );
-                            }
-
-                            // Log progress every 50 tokens
-                            if token_count % 50 == 0 {
-                                self.log(&format!("📝 ...{} tokens received...", token_count));
-                            }
-                        }
-                        Err(e) => {
-                            self.log(&format!("⚠️ Stream error: {}", e));
-                            break;
-                        }
-                    }
+            Ok(stream) => {
+                if self.llm_client.last_cache_hit() {
+                    self.log("♻️ CACHE HIT - replaying cached response (no provider call)");
+                } else {
+                    self.log("🔗 CONNECTED - streaming response...");
                 }
+                let (full_response, token_count, first_token_at) =
+                    self.collect_stream(stream, &phase.id).await;
+                ttft_ms = first_token_at.map(|at| at.duration_since(start).as_millis() as u64);
 
                 let elapsed = start.elapsed();
                 self.log(&format!(
@@ -355,7 +913,9 @@ impl Agent {
                     full_response.len(),
                     elapsed.as_secs_f64()
                 ));
-                Ok(full_response)
+                Ok(self
+                    .llm_client
+                    .last_generation(full_response, &req.model, start))
             }
             Err(stream_err) => {
                 // Fallback to non-streaming
@@ -364,15 +924,16 @@ impl Agent {
                     stream_err
                 ));
                 self.log("⏳ WAITING for response...");
+                retries += 1;
 
-                let result = self.llm_client.generate(req).await;
+                let result = self.llm_client.generate(req.clone()).await;
                 let elapsed = start.elapsed();
 
                 match &result {
-                    Ok(response) => {
+                    Ok(generation) => {
                         self.log(&format!(
                             "📥 RECEIVED: {} chars in {:.1}s",
-                            response.len(),
+                            generation.text.len(),
                             elapsed.as_secs_f64()
                         ));
                     }
@@ -388,24 +949,550 @@ impl Agent {
             }
         };
 
-        result
+        let mut generation = result?;
+        self.record_generation(&generation);
+
+        // Output cut off at max_tokens: ask the model to continue and stitch the parts
+        let max_continuations = phase.max_continuations.unwrap_or(DEFAULT_MAX_CONTINUATIONS);
+        let mut continuations = 0;
+        while generation.is_truncated() && continuations < max_continuations {
+            continuations += 1;
+            retries += 1;
+            self.log(&format!(
+                "✂️ TRUNCATED at max_tokens - requesting continuation {}/{}",
+                continuations, max_continuations
+            ));
+            let continuation = self
+                .continue_generation(&req, &generation.text, &phase.id)
+                .await?;
+            self.record_generation(&continuation);
+            generation.append(continuation);
+        }
+        if generation.is_truncated() {
+            self.log("⚠️ OUTPUT TRUNCATED at max_tokens - this phase's output is incomplete");
+        }
+
+        let metrics = PhaseMetrics::from_generation(&generation, started_at, ttft_ms, retries);
+        Ok(PhaseRun {
+            generation,
+            metrics,
+            report: None,
+        })
+    }
+
+    /// Run a fan-out phase: the phase once per item of its `foreach` list, at most
+    /// `concurrency` at a time. Each item is persisted as its own phase output
+    /// (`PHASE-03[2]`, 0-based); the phase output is the JSON array of item outputs.
+    async fn execute_foreach(&mut self, phase: &Phase, foreach: &ForEach) -> Result<PhaseRun> {
+        let key = foreach.source_key();
+        let source = self
+            .state
+            .context
+            .get(key)
+            .ok_or_else(|| anyhow!("Missing foreach list: {}", key))?;
+        let items = foreach.resolve_items(source)?;
+        let total = items.len();
+        let concurrency = foreach
+            .concurrency
+            .unwrap_or(DEFAULT_FOREACH_CONCURRENCY)
+            .max(1);
+        let (model, _) = self.resolve_model(phase);
+        let model = model.to_string();
+        tracing::Span::current().record("model", model.as_str());
+
+        let input = phase
+            .input
+            .as_ref()
+            .map(|_| self.resolve_input(phase))
+            .transpose()?;
+        self.log(&format!(
+            "🔀 FAN-OUT: {} items of {}, {} at a time",
+            total, foreach.items, concurrency
+        ));
+        self.emit_phase_output(PhaseOutput {
+            phase_id: &phase.id,
+            phase_name: &phase.name,
+            status: "running",
+            system_prompt: Some(&self.build_system_prompt(phase)),
+            user_input: input.as_deref(),
+            ..PhaseOutput::default()
+        });
+        self.emit_phase_progress(&phase.id, 0, total);
+
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let runs: Vec<_> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let item_phase = Phase {
+                    id: format!("{}[{}]", phase.id, index),
+                    name: format!("{} [{}]", phase.name, index),
+                    input: Some(FOREACH_ITEM_KEY.to_string()),
+                    foreach: None,
+                    ..phase.clone()
+                };
+                let context = HashMap::from([(
+                    FOREACH_ITEM_KEY.to_string(),
+                    item_input(input.as_deref(), item),
+                )]);
+                let mut child = self.child_agent(&item_phase.id, context);
+                let span = tracing::info_span!("item", phase_id = %item_phase.id);
+                async move {
+                    let result = child.execute_phase(&item_phase).await;
+                    (index, item_phase, child, result)
+                }
+                .instrument(span)
+            })
+            .collect();
+        let mut runs = futures::stream::iter(runs).buffer_unordered(concurrency);
+
+        let mut outputs = vec![String::new(); total];
+        let mut usage: Option<TokenUsage> = None;
+        let mut retries = 0;
+        let mut cache_hits = 0;
+        let mut failures = Vec::new();
+        let mut finished = 0;
+        while let Some((index, item_phase, child, result)) = runs.next().await {
+            finished += 1;
+            // Item log lines were emitted as they happened; keep them in the run log
+            self.state.logs.extend(child.state.logs);
+            self.token_usage.add(&child.token_usage);
+            match result {
+                Ok(run) => {
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &item_phase.id,
+                        phase_name: &item_phase.name,
+                        status: "completed",
+                        output: Some(&run.generation.text),
+                        run: Some(&run),
+                        ..PhaseOutput::default()
+                    });
+                    if let Some(item_usage) = &run.generation.usage {
+                        usage
+                            .get_or_insert_with(TokenUsage::default)
+                            .add(item_usage);
+                    }
+                    retries += run.metrics.retries;
+                    cache_hits += usize::from(run.generation.cache_hit);
+                    outputs[index] = run.generation.text;
+                }
+                Err(e) => {
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &item_phase.id,
+                        phase_name: &item_phase.name,
+                        status: "failed",
+                        error: Some(&e.to_string()),
+                        ..PhaseOutput::default()
+                    });
+                    failures.push(format!("{}: {}", item_phase.id, e));
+                }
+            }
+            self.log(&format!(
+                "🔀 {}: {}/{} items finished",
+                phase.id, finished, total
+            ));
+            self.emit_phase_progress(&phase.id, finished, total);
+        }
+
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "{} of {} items failed: {}",
+                failures.len(),
+                total,
+                failures.join("; ")
+            ));
+        }
+
+        // Structured item outputs are collected as JSON, others as text
+        let values: Vec<serde_json::Value> = outputs
+            .iter()
+            .map(|text| {
+                phase
+                    .output_schema
+                    .as_ref()
+                    .and_then(|_| parse_json_output(text))
+                    .unwrap_or_else(|| serde_json::Value::String(text.clone()))
+            })
+            .collect();
+        let report = items
+            .iter()
+            .zip(&outputs)
+            .enumerate()
+            .map(|(index, (item, output))| format!("### {}\n\n{}", item_label(item, index), output))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let generation = Generation {
+            text: serde_json::to_string_pretty(&values)?,
+            usage,
+            model,
+            latency_ms: start.elapsed().as_millis() as u64,
+            cache_hit: total > 0 && cache_hits == total,
+            ..Generation::default()
+        };
+        let metrics = PhaseMetrics::from_generation(&generation, started_at, None, retries);
+        Ok(PhaseRun {
+            generation,
+            metrics,
+            report: Some(report),
+        })
+    }
+
+    /// Run an ensemble phase: the phase on every model at once, then the candidates
+    /// combined by the ensemble strategy (see `ensemble`). Each candidate is persisted
+    /// as its own phase output (`PHASE-02@gpt-4o`), as is the verdict (`PHASE-02@verdict`).
+    async fn execute_ensemble(&mut self, phase: &Phase, ensemble: &Ensemble) -> Result<PhaseRun> {
+        let input = self.resolve_input(phase)?;
+        self.log(&format!(
+            "🗳️ ENSEMBLE: {} models ({}), strategy {}",
+            ensemble.models.len(),
+            ensemble.models.join(", "),
+            ensemble.strategy.as_str()
+        ));
+        self.emit_phase_output(PhaseOutput {
+            phase_id: &phase.id,
+            phase_name: &phase.name,
+            status: "running",
+            system_prompt: Some(&self.build_system_prompt(phase)),
+            user_input: Some(&input),
+            ..PhaseOutput::default()
+        });
+
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let runs: Vec<_> = ensemble
+            .models
+            .iter()
+            .map(|model| {
+                let candidate_phase = Phase {
+                    id: ensemble::candidate_phase_id(&phase.id, model),
+                    name: format!("{} [{}]", phase.name, model),
+                    ensemble: None,
+                    ..phase.clone()
+                };
+                let mut child = self.child_agent(&candidate_phase.id, self.state.context.clone());
+                // The ensemble's models take precedence over the UI-selected model
+                child.model_override = Some(model.clone());
+                let span = tracing::info_span!("candidate", phase_id = %candidate_phase.id);
+                async move {
+                    let result = child.execute_phase(&candidate_phase).await;
+                    (model.clone(), candidate_phase, child, result)
+                }
+                .instrument(span)
+            })
+            .collect();
+        let results = futures::future::join_all(runs).await;
+
+        let mut candidates = Vec::new();
+        let mut usage: Option<TokenUsage> = None;
+        let mut retries = 0;
+        let mut failures = Vec::new();
+        for (model, candidate_phase, child, result) in results {
+            self.state.logs.extend(child.state.logs);
+            self.token_usage.add(&child.token_usage);
+            match result {
+                Ok(run) => {
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &candidate_phase.id,
+                        phase_name: &candidate_phase.name,
+                        status: "completed",
+                        output: Some(&run.generation.text),
+                        run: Some(&run),
+                        ..PhaseOutput::default()
+                    });
+                    if let Some(candidate_usage) = &run.generation.usage {
+                        usage
+                            .get_or_insert_with(TokenUsage::default)
+                            .add(candidate_usage);
+                    }
+                    retries += run.metrics.retries;
+                    candidates.push(Candidate {
+                        model,
+                        output: run.generation.text,
+                    });
+                }
+                Err(e) => {
+                    self.emit_phase_output(PhaseOutput {
+                        phase_id: &candidate_phase.id,
+                        phase_name: &candidate_phase.name,
+                        status: "failed",
+                        error: Some(&e.to_string()),
+                        ..PhaseOutput::default()
+                    });
+                    self.log(&format!("⚠️ Ensemble model {} failed: {}", model, e));
+                    failures.push(format!("{}: {}", model, e));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return Err(anyhow!(
+                "All {} ensemble models failed: {}",
+                failures.len(),
+                failures.join("; ")
+            ));
+        }
+
+        let (verdict, judge_model) = match ensemble.strategy {
+            EnsembleStrategy::Vote => (ensemble::vote(&candidates)?, None),
+            _ if candidates.len() == 1 => {
+                let verdict = Verdict {
+                    output: candidates[0].output.clone(),
+                    rationale: format!("Only {} returned an answer", candidates[0].model),
+                    chosen: Some(0),
+                };
+                (verdict, None)
+            }
+            strategy => {
+                let (phase_model, _) = self.resolve_model(phase);
+                let judge_model = ensemble
+                    .judge_model
+                    .clone()
+                    .unwrap_or_else(|| phase_model.to_string());
+                self.log(&format!(
+                    "⚖️ JUDGING: {} candidates → {}",
+                    candidates.len(),
+                    judge_model
+                ));
+                let req = ensemble::build_judge_request(
+                    &judge_model,
+                    strategy,
+                    &phase.instructions,
+                    &input,
+                    &candidates,
+                );
+                let generation = self.llm_client.generate(req).await?;
+                self.record_generation(&generation);
+                if let Some(judge_usage) = &generation.usage {
+                    usage
+                        .get_or_insert_with(TokenUsage::default)
+                        .add(judge_usage);
+                }
+                let verdict = ensemble::parse_judge_reply(strategy, &generation.text, &candidates)?;
+                (verdict, Some(judge_model))
+            }
+        };
+        self.log(&format!(
+            "⚖️ VERDICT ({}): {}",
+            ensemble.strategy.as_str(),
+            verdict.rationale.lines().next().unwrap_or_default()
+        ));
+        self.emit_phase_output(PhaseOutput {
+            phase_id: &ensemble::candidate_phase_id(&phase.id, VERDICT_SUFFIX),
+            phase_name: &format!("{} [{}]", phase.name, VERDICT_SUFFIX),
+            status: "completed",
+            output: Some(&verdict.rationale),
+            ..PhaseOutput::default()
+        });
+
+        // The selected candidate's model, else the judge's (merge) or all of them (vote)
+        let model = match (verdict.chosen, judge_model) {
+            (Some(index), _) => candidates[index].model.clone(),
+            (None, Some(judge_model)) => judge_model,
+            (None, None) => ensemble.models.join(", "),
+        };
+        let generation = Generation {
+            text: verdict.output,
+            usage,
+            model,
+            latency_ms: start.elapsed().as_millis() as u64,
+            ..Generation::default()
+        };
+        let metrics = PhaseMetrics::from_generation(&generation, started_at, None, retries);
+        Ok(PhaseRun {
+            generation,
+            metrics,
+            report: None,
+        })
+    }
+
+    /// Agent for one run within a phase (a fan-out item or an ensemble candidate): same
+    /// configuration and event sequence, its own LLM client state, and `context`
+    fn child_agent(&self, phase_id: &str, context: HashMap<String, String>) -> Agent {
+        let mut state = AgentState::new();
+        state.current_phase_id = Some(phase_id.to_string());
+        state.context = context;
+        Agent {
+            manifest: self.manifest.clone(),
+            state,
+            llm_client: self.llm_client.fork(),
+            app_handle: self.app_handle.clone(),
+            model_override: self.model_override.clone(),
+            session_id: self.session_id,
+            dry_run: false,
+            dry_run_report: None,
+            prompt_cache: self.prompt_cache.clone(),
+            token_usage: TokenUsage::default(),
+            attachments: self.attachments.clone(),
+            model_catalog: self.model_catalog.clone(),
+            events: self.events.clone(),
+            token_batching: self.token_batching,
+            inputs: BTreeMap::new(),
+            // Concurrent runs would interleave in the live output
+            forward_tokens: false,
+            approvals: None,
+        }
+    }
+
+    /// Forward streamed tokens to the frontend in batches (see token_batch); returns
+    /// the text, provider chunk count and when the first token arrived. A stream error
+    /// ends the response early with the text received so far.
+    async fn collect_stream(
+        &mut self,
+        mut stream: Pin<Box<dyn futures::Stream<Item = Result<String, LLMError>> + Send>>,
+        phase_id: &str,
+    ) -> (String, usize, Option<Instant>) {
+        let mut full_response = String::new();
+        let mut token_count = 0;
+        let mut first_token_at = None;
+
+        // Emit batches to frontend via AppHandle (global event) from a separate task;
+        // each batch is scoped here, in order with this task's other events
+        let mut batcher = TokenBatcher::new(self.token_batching);
+        let mut sender = self
+            .app_handle
+            .clone()
+            .filter(|_| self.forward_tokens)
+            .map(|app| {
+                let phase_id = phase_id.to_string();
+                BatchSender::spawn(move |scope, token| {
+                    let _ = app.emit(
+                        "stream-token",
+                        StreamTokenPayload {
+                            scope,
+                            token,
+                            phase_id: phase_id.clone(),
+                        },
+                    );
+                })
+            });
+
+        loop {
+            // Wake up for the batch time window even when the provider goes quiet
+            let next = match batcher.deadline() {
+                Some(deadline) => tokio::select! {
+                    chunk = stream.next() => Some(chunk),
+                    _ = tokio::time::sleep_until(deadline.into()) => None,
+                },
+                None => Some(stream.next().await),
+            };
+
+            let batch = match next {
+                None => batcher.poll_due(Instant::now()),
+                Some(None) => break,
+                Some(Some(Ok(token))) => {
+                    first_token_at.get_or_insert_with(Instant::now);
+                    full_response.push_str(&token);
+                    token_count += 1;
+
+                    // Log progress every 50 tokens
+                    if token_count % 50 == 0 {
+                        self.log(&format!("📝 ...{} tokens received...", token_count));
+                    }
+                    batcher.push(&token)
+                }
+                Some(Some(Err(e))) => {
+                    self.log(&format!("⚠️ Stream error: {}", e));
+                    break;
+                }
+            };
+            if let (Some(batch), Some(sender)) = (batch, sender.as_mut()) {
+                sender.send(self.events.next(), batch);
+            }
+        }
+
+        // Final flush: everything received reaches the frontend before phase-output
+        if let Some(mut sender) = sender {
+            if let Some(batch) = batcher.flush() {
+                sender.send(self.events.next(), batch);
+            }
+            sender.finish().await;
+        }
+
+        (full_response, token_count, first_token_at)
+    }
+
+    /// Ask the model to pick up a truncated response where it stopped
+    async fn continue_generation(
+        &mut self,
+        req: &LLMRequest,
+        partial: &str,
+        phase_id: &str,
+    ) -> Result<Generation> {
+        let start = Instant::now();
+        let mut request = MultiTurnRequest::new(&req.model)
+            .with_system(&req.system)
+            .with_message(ChatMessage::user(&req.user).with_attachments(req.attachments.clone()))
+            .with_message(ChatMessage::assistant(partial))
+            .with_message(ChatMessage::user(CONTINUE_PROMPT));
+        if let Some(config) = &req.cache_config {
+            request = request.with_cache_config(config.clone());
+        }
+        if let Some(reasoning) = &req.reasoning {
+            request = request.with_reasoning(reasoning.clone());
+        }
+
+        match self
+            .llm_client
+            .generate_multi_turn_stream(request.clone())
+            .await
+        {
+            Ok(stream) => {
+                let (text, _, _) = self.collect_stream(stream, phase_id).await;
+                Ok(self.llm_client.last_generation(text, &req.model, start))
+            }
+            Err(_) => self.llm_client.generate_multi_turn(request).await,
+        }
+    }
+
+    /// Log a call's reasoning trace and usage, and add the usage to the run total
+    fn record_generation(&mut self, generation: &Generation) {
+        if let Some(reasoning) = &generation.reasoning {
+            self.log(&format!("🧠 REASONING: {} chars", reasoning.len()));
+        }
+
+        // Report provider usage, including Anthropic prompt cache writes/reads
+        if let Some(usage) = &generation.usage {
+            self.log(&format!(
+                "🧾 USAGE: {} in / {} out tokens (prompt cache: {} written, {} read)",
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.cache_creation_input_tokens,
+                usage.cache_read_input_tokens
+            ));
+            self.token_usage.add(usage);
+        }
     }
 
     // Helper to log to stdout AND emit to frontend via AppHandle (global event)
-    fn log(&self, msg: &str) {
-        println!("[AGENT] {}", msg);
+    // The level comes from the message's status emoji (see telemetry::level_for)
+    fn log(&mut self, msg: &str) {
+        self.log_at(telemetry::level_for(msg), msg);
+    }
+
+    // Tracing event (stdout + log file), session run log entry and "agent-log" event
+    fn log_at(&mut self, level: Level, msg: &str) {
+        match level {
+            Level::ERROR => tracing::error!("{}", msg),
+            Level::WARN => tracing::warn!("{}", msg),
+            _ => tracing::info!("{}", msg),
+        }
+        self.state.logs.push(RunLogEntry::new(
+            level,
+            self.state.current_phase_id.as_deref(),
+            msg,
+        ));
+
         if let Some(app) = &self.app_handle {
-            match app.emit(
+            if let Err(e) = app.emit(
                 "agent-log",
                 LogPayload {
+                    scope: self.event_scope(),
                     message: msg.to_string(),
                 },
             ) {
-                Ok(_) => println!("[AGENT-EMIT] ✓ Sent: {}", &msg[..msg.len().min(50)]),
-                Err(e) => eprintln!("[AGENT-EMIT-ERROR] Failed to emit log: {}", e),
+                tracing::warn!("Failed to emit agent-log: {}", e);
             }
-        } else {
-            eprintln!("[AGENT-EMIT-ERROR] No AppHandle available!");
         }
     }
 
@@ -417,6 +1504,7 @@ impl Agent {
 
         let status_str = match status {
             PhaseStatus::Running => "running",
+            PhaseStatus::AwaitingApproval => AWAITING_APPROVAL,
             PhaseStatus::Completed => "completed",
             PhaseStatus::Failed(_) => "failed",
             _ => "pending",
@@ -426,16 +1514,73 @@ impl Agent {
             match app.emit(
                 "phase-update",
                 PhaseUpdatePayload {
+                    scope: self.event_scope(),
                     phase_id: phase_id.to_string(),
                     status: status_str.to_string(),
+                    progress: None,
                 },
             ) {
-                Ok(_) => println!("[AGENT-EMIT] ✓ Phase {} -> {}", phase_id, status_str),
-                Err(e) => eprintln!("[AGENT-EMIT-ERROR] Failed to emit phase-update: {}", e),
+                Ok(_) => tracing::debug!(phase_id, status = status_str, "emitted phase-update"),
+                Err(e) => tracing::warn!("Failed to emit phase-update: {}", e),
             }
         }
     }
+
+    // Report fan-out progress as a "running" phase-update carrying the item counts
+    fn emit_phase_progress(&self, phase_id: &str, completed: usize, total: usize) {
+        if let Some(app) = &self.app_handle {
+            if let Err(e) = app.emit(
+                "phase-update",
+                PhaseUpdatePayload {
+                    scope: self.event_scope(),
+                    phase_id: phase_id.to_string(),
+                    status: "running".to_string(),
+                    progress: Some(PhaseProgress { completed, total }),
+                },
+            ) {
+                tracing::warn!("Failed to emit phase-update: {}", e);
+            }
+        }
+    }
+}
+
+/// Current date so LLMs search for up-to-date information (changes daily, so it is
+/// kept out of the response cache key)
+fn date_line() -> String {
+    let current_date = Local::now().format("%B %d, %Y").to_string(); // e.g., "November 28, 2025"
+    format!(
+        "IMPORTANT: Today's date is {}. When researching, prioritize finding the most recent and up-to-date information available, including data from {} and earlier.",
+        current_date, current_date
+    )
+}
+
+/// System prompt for a phase: its name, the date line and its instructions
+fn system_prompt(phase: &Phase, date_line: &str) -> String {
+    format!(
+        "You are an autonomous research agent executing phase '{}'.\n{}\n\nInstructions:\n{}",
+        phase.name, date_line, phase.instructions
+    )
 }
+
+/// User input for one item of a fan-out phase: the item, after the phase `input` if set
+fn item_input(input: Option<&str>, item: &serde_json::Value) -> String {
+    let item = match item {
+        serde_json::Value::String(text) => text.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    };
+    match input {
+        Some(input) => format!("{}\n\n## Item\n\n{}", input, item),
+        None => item,
+    }
+}
+
+/// Report heading for a fan-out item: the item itself if text, else its `name`
+fn item_label(item: &serde_json::Value, index: usize) -> String {
+    item.as_str()
+        .or_else(|| item.get("name").and_then(|name| name.as_str()))
+        .map_or_else(|| format!("Item {}", index + 1), |label| label.to_string())
+}
+
 // ============================================================================
 // UNIT TESTS - Agent Core Functionality
 // ============================================================================
@@ -477,6 +1622,24 @@ quality_gates: []
         assert!(agent.get_context("nonexistent").is_none());
     }
 
+    #[test]
+    fn test_event_scope_carries_run_and_increasing_seq() {
+        let agent = Agent::new(create_test_manifest(), String::new(), None, None, Some(7))
+            .with_run_id("run-a");
+
+        let first = agent.event_scope();
+        let second = agent.event_scope();
+
+        assert_eq!(first.run_id, "run-a");
+        assert_eq!(first.session_id, Some(7));
+        assert!(second.seq > first.seq);
+
+        // Generated run ids differ between agents
+        let a = Agent::new(create_test_manifest(), String::new(), None, None, None);
+        let b = Agent::new(create_test_manifest(), String::new(), None, None, None);
+        assert_ne!(a.run_id(), b.run_id());
+    }
+
     #[tokio::test]
     async fn test_run_workflow_empty_manifest() {
         let manifest = create_test_manifest();
@@ -508,6 +1671,621 @@ quality_gates: []
         // may not have phases with input fields, so we only verify the fallback keys
     }
 
+    #[tokio::test]
+    async fn test_declared_inputs_use_their_own_keys() {
+        let inputs = BTreeMap::from([
+            ("company".to_string(), "Acme Corp".to_string()),
+            ("region".to_string(), "EMEA".to_string()),
+        ]);
+        let mut agent =
+            Agent::new(create_test_manifest(), String::new(), None, None, None).with_inputs(inputs);
+        agent.run_workflow("Acme Corp · EMEA").await.unwrap();
+
+        assert_eq!(agent.get_context("company").unwrap(), "Acme Corp");
+        assert_eq!(agent.get_context("region").unwrap(), "EMEA");
+        // Fallback keys still receive the summary input
+        assert_eq!(
+            agent.get_context("initial_input").unwrap(),
+            "Acme Corp · EMEA"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_workflow_with_mock_model() {
+        let yaml_content = r#"
+manifest:
+  id: "TEST-MOCK-001"
+  version: "1.0.0"
+  name: "Mock Manifest"
+  description: "Offline pipeline"
+
+schemas:
+  Profile:
+    fields:
+      - name: company_name
+      - name: tier
+        enum: [SMB, ENTERPRISE]
+phases:
+  - id: "PHASE-01"
+    name: "Profile"
+    input: "target_company"
+    instructions: "Profile the company."
+    output_schema: "Profile"
+  - id: "PHASE-02"
+    name: "Brief"
+    input: "Profile"
+    instructions: "Write the brief."
+    output_target: "brief"
+quality_gates: []
+"#;
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", yaml_content).unwrap();
+        let manifest = Manifest::load_from_file(file.path()).unwrap();
+
+        let mut agent = Agent::new(
+            manifest,
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(MockConfig {
+            chunk_delay_ms: 0,
+            ..MockConfig::default()
+        });
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        let profile: serde_json::Value =
+            serde_json::from_str(&agent.get_context("Profile").unwrap()).unwrap();
+        assert_eq!(profile["tier"], "SMB");
+        assert!(agent
+            .get_context("brief")
+            .unwrap()
+            .contains("Mock Response"));
+    }
+
+    #[tokio::test]
+    async fn test_foreach_phase_runs_once_per_item() {
+        let yaml_content = r#"
+manifest:
+  id: "TEST-FOREACH-001"
+  version: "1.0.0"
+  name: "Fan-out Manifest"
+  description: "One analysis per competitor"
+
+schemas:
+  CompetitorList:
+    fields:
+      - name: competitors
+phases:
+  - id: "PHASE-01"
+    name: "Competitors"
+    input: "target_company"
+    instructions: "List the competitors."
+    output_schema: "CompetitorList"
+  - id: "PHASE-02"
+    name: "Competitor Analysis"
+    instructions: "Analyse the competitor."
+    foreach:
+      items: "CompetitorList.competitors"
+      concurrency: 2
+    output_target: "analyses"
+quality_gates: []
+"#;
+        let manifest = Manifest::parse(yaml_content, None).unwrap();
+        let analysis = "Analysis of {{input}}".to_string();
+        let mut agent = Agent::new(
+            manifest,
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            responses: vec![
+                r#"{"competitors": ["Alpha", "Beta", "Gamma"]}"#.to_string(),
+                analysis.clone(),
+                analysis.clone(),
+                analysis,
+            ],
+            ..MockConfig::default()
+        });
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        // Outputs are collected in item order, whatever order the items finished in
+        let analyses: Vec<String> =
+            serde_json::from_str(&agent.get_context("analyses").unwrap()).unwrap();
+        assert_eq!(
+            analyses,
+            ["Analysis of Alpha", "Analysis of Beta", "Analysis of Gamma"]
+        );
+        let report = agent.get_context("markdown_file").unwrap();
+        assert!(report.contains("## Competitor Analysis\n\n### Alpha\n\nAnalysis of Alpha"));
+        assert!(agent
+            .run_log()
+            .iter()
+            .any(|entry| entry.message.contains("PHASE-02: 3/3 items finished")));
+        assert!(agent
+            .run_log()
+            .iter()
+            .any(|entry| entry.phase_id.as_deref() == Some("PHASE-02[1]")));
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_phase_votes_and_judges() {
+        let yaml_content = r#"
+manifest:
+  id: "TEST-ENSEMBLE-001"
+  version: "1.0.0"
+  name: "Ensemble Manifest"
+  description: "Situation by vote, ROI by judge"
+
+schemas:
+  Situation:
+    fields:
+      - name: situation
+phases:
+  - id: "PHASE-01"
+    name: "Situation"
+    input: "target_company"
+    instructions: "Classify the situation."
+    output_schema: "Situation"
+    ensemble:
+      models: ["mock-a", "mock-b", "mock-c"]
+      strategy: vote
+  - id: "PHASE-02"
+    name: "ROI"
+    input: "Situation"
+    instructions: "Estimate the ROI."
+    output_target: "roi"
+    ensemble:
+      models: ["mock-a", "mock-b"]
+      judge_model: "mock-judge"
+quality_gates: []
+"#;
+        let manifest = Manifest::parse(yaml_content, None).unwrap();
+        let mut agent = Agent::new(
+            manifest,
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            responses: vec![
+                r#"{"situation": "GROWTH"}"#.to_string(),
+                r#"{"situation": "CRISIS"}"#.to_string(),
+                r#"{"situation": "Growth"}"#.to_string(),
+                "ROI of 3x".to_string(),
+                "ROI of 3x".to_string(),
+                r#"{"choice": 2, "rationale": "Shows the calculation"}"#.to_string(),
+            ],
+            ..MockConfig::default()
+        });
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        let situation: serde_json::Value =
+            serde_json::from_str(&agent.get_context("Situation").unwrap()).unwrap();
+        assert_eq!(situation["situation"], "GROWTH");
+        assert_eq!(agent.get_context("roi").unwrap(), "ROI of 3x");
+        let log = agent.run_log();
+        assert!(log
+            .iter()
+            .any(|entry| entry.phase_id.as_deref() == Some("PHASE-01@mock-c")));
+        assert!(log
+            .iter()
+            .any(|entry| entry.message.contains("situation: \"GROWTH\" (2/3 votes)")));
+        assert!(log
+            .iter()
+            .any(|entry| entry.message.contains("Selected answer 2 (mock-b)")));
+    }
+
+    #[tokio::test]
+    async fn test_report_template_replaces_concatenated_outputs() {
+        let yaml_content = r##"
+manifest:
+  id: "TEST-REPORT-001"
+  version: "1.0.0"
+  name: "Report Manifest"
+  description: "Brief with a cover page"
+
+schemas:
+  Situation:
+    fields:
+      - name: situation
+phases:
+  - id: "PHASE-01"
+    name: "Situation"
+    input: "target_company"
+    instructions: "Classify the situation."
+    output_schema: "Situation"
+  - id: "PHASE-02"
+    name: "Brief"
+    input: "Situation"
+    instructions: "Write the brief."
+    output_target: "markdown_file"
+quality_gates: []
+report:
+  cover: "# {{target_company}}\nSituation: {{Situation.situation}}"
+"##;
+        let manifest = Manifest::parse(yaml_content, None).unwrap();
+        let mut agent = Agent::new(
+            manifest,
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            responses: vec![
+                r#"{"situation": "GROWTH", "notes": "raw research"}"#.to_string(),
+                "The brief".to_string(),
+            ],
+            ..MockConfig::default()
+        });
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        // The drafted brief is kept, behind the cover page, without intermediate outputs
+        assert_eq!(
+            agent.get_context("markdown_file").unwrap(),
+            "# Acme Corp\nSituation: GROWTH\n\n---\n\nThe brief"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_approval_checkpoint_waits_for_reviewer() {
+        use crate::approval::ApprovalRegistry;
+
+        let yaml_content = r#"
+manifest:
+  id: "TEST-APPROVAL-001"
+  version: "1.0.0"
+  name: "Approval Manifest"
+  description: "Company match is reviewed"
+
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Company Match"
+    input: "target_company"
+    instructions: "Identify the company."
+    output_target: "company"
+    approval: required
+  - id: "PHASE-02"
+    name: "Brief"
+    input: "company"
+    instructions: "Write the brief."
+    output_target: "brief"
+quality_gates: []
+"#;
+        let registry = ApprovalRegistry::default();
+        let mut agent = Agent::new(
+            Manifest::parse(yaml_content, None).unwrap(),
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_run_id("run-approval")
+        .with_mock_config(MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            responses: vec![
+                "Acme Inc (Canada)".to_string(),
+                "Acme Corp".to_string(),
+                "Brief about {{input}}".to_string(),
+            ],
+            ..MockConfig::default()
+        });
+        let gate = registry.open(agent.run_id());
+        agent = agent.with_approval_gate(gate);
+
+        // Submit once the run is waiting
+        let decide = |decision: ApprovalDecision| {
+            let registry = &registry;
+            async move {
+                while registry.submit("run-approval", decision.clone()).is_err() {
+                    tokio::task::yield_now().await;
+                }
+            }
+        };
+        let review = async {
+            decide(ApprovalDecision::Reject {
+                feedback: "Wrong company: the US one".to_string(),
+            })
+            .await;
+            decide(ApprovalDecision::Edit {
+                output: "Acme Corp (Delaware)".to_string(),
+            })
+            .await;
+        };
+        let (result, _) = tokio::join!(agent.run_workflow("Acme"), review);
+        result.unwrap();
+
+        assert_eq!(
+            agent.get_context("company").as_deref(),
+            Some("Acme Corp (Delaware)")
+        );
+        assert_eq!(
+            agent.get_context("brief").as_deref(),
+            Some("Brief about Acme Corp (Delaware)")
+        );
+        let messages: Vec<&str> = agent.run_log().iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|m| m.starts_with("⏸️ AWAITING APPROVAL"))
+                .count(),
+            2
+        );
+        assert!(messages.iter().any(|m| m.starts_with("↩️ REJECTED")));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_at_approval_ends_the_run() {
+        use crate::approval::ApprovalRegistry;
+
+        let yaml_content = r#"
+manifest:
+  id: "TEST-CANCEL-001"
+  version: "1.0.0"
+  name: "Cancel Manifest"
+  description: "Approval checkpoint cancelled by the reviewer"
+
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Company Match"
+    input: "target_company"
+    instructions: "Identify the company."
+    output_target: "company"
+    approval: required
+  - id: "PHASE-02"
+    name: "Brief"
+    input: "company"
+    instructions: "Write the brief."
+    output_target: "brief"
+quality_gates: []
+"#;
+        let registry = ApprovalRegistry::default();
+        let mut agent = Agent::new(
+            Manifest::parse(yaml_content, None).unwrap(),
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_run_id("run-cancel")
+        .with_mock_config(MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            ..MockConfig::default()
+        });
+        let gate = registry.open(agent.run_id());
+        agent = agent.with_approval_gate(gate);
+
+        let review = async {
+            while registry
+                .submit("run-cancel", ApprovalDecision::Cancel)
+                .is_err()
+            {
+                tokio::task::yield_now().await;
+            }
+        };
+        let (result, _) = tokio::join!(agent.run_workflow("Acme"), review);
+
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        assert!(agent.get_context("brief").is_none());
+    }
+
+    fn truncation_manifest(max_continuations: u32) -> Manifest {
+        let yaml_content = format!(
+            r#"
+manifest:
+  id: "TEST-TRUNC-001"
+  version: "1.0.0"
+  name: "Truncation Manifest"
+  description: "Continuation of truncated output"
+
+schemas: {{}}
+phases:
+  - id: "PHASE-01"
+    name: "Brief"
+    input: "target_company"
+    instructions: "Write the brief."
+    output_target: "brief"
+    max_continuations: {}
+quality_gates: []
+"#,
+            max_continuations
+        );
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", yaml_content).unwrap();
+        Manifest::load_from_file(file.path()).unwrap()
+    }
+
+    fn truncating_mock() -> MockConfig {
+        MockConfig {
+            chunk_delay_ms: 0,
+            responses: vec!["Part one, ".to_string(), "part two.".to_string()],
+            truncate_on_calls: vec![1],
+            ..MockConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_output_is_continued_and_stitched() {
+        let mut agent = Agent::new(
+            truncation_manifest(DEFAULT_MAX_CONTINUATIONS),
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(truncating_mock());
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        assert_eq!(agent.get_context("brief").unwrap(), "Part one, part two.");
+        let truncation = agent
+            .run_log()
+            .iter()
+            .find(|entry| entry.message.contains("TRUNCATED"))
+            .unwrap();
+        assert_eq!(truncation.level, "warn");
+        assert_eq!(truncation.phase_id.as_deref(), Some("PHASE-01"));
+        assert_eq!(
+            agent.llm_client.last_stop_reason(),
+            Some(crate::llm::StopReason::EndTurn)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_output_kept_without_continuations() {
+        let mut agent = Agent::new(
+            truncation_manifest(0),
+            String::new(),
+            None,
+            Some("mock-fast".to_string()),
+            None,
+        )
+        .with_mock_config(truncating_mock());
+
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        assert_eq!(agent.get_context("brief").unwrap(), "Part one, ");
+        assert_eq!(
+            agent.llm_client.last_stop_reason(),
+            Some(crate::llm::StopReason::MaxTokens)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_renders_prompts_without_calling_provider() {
+        let yaml_content = r#"
+manifest:
+  id: "TEST-DRY-001"
+  version: "1.0.0"
+  name: "Dry Run Manifest"
+  description: "Prompt preview"
+
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Profile"
+    input: "target_company"
+    instructions: "Profile the company."
+    output_target: "profile"
+    model: "gpt-4o"
+  - id: "PHASE-02"
+    name: "Brief"
+    input: "profle"
+    instructions: "Write the brief."
+  - id: "PHASE-03"
+    name: "Synthesis"
+    input: "profile"
+    instructions: "Synthesize."
+quality_gates: []
+"#;
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", yaml_content).unwrap();
+        let manifest = Manifest::load_from_file(file.path()).unwrap();
+
+        // Invalid key: any provider call would fail, so success proves none was made
+        let mut agent =
+            Agent::new(manifest, "invalid".to_string(), None, None, None).with_dry_run(true);
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        let report = agent.dry_run_report().unwrap();
+        assert_eq!(report.phases.len(), 3);
+
+        let profile = &report.phases[0];
+        assert_eq!(profile.model, "gpt-4o");
+        assert_eq!(profile.model_source, ModelSource::Phase);
+        assert_eq!(profile.user_input, "Acme Corp");
+        assert!(profile.system_prompt.contains("Profile the company."));
+        assert!(profile.estimated_cost_usd.is_some());
+
+        // Typo'd input key is flagged
+        assert_eq!(report.phases[1].model, DEFAULT_MODEL);
+        assert_eq!(report.phases[1].model_source, ModelSource::Default);
+        assert!(report.phases[1].warnings[0].contains("profle"));
+
+        // Downstream phase sees the placeholder, not a real output
+        assert!(report.phases[2].warnings.is_empty());
+        assert!(report.phases[2].user_input.contains("PHASE-01"));
+        assert!(agent.get_context("markdown_file").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_workflow_serves_repeat_run_from_response_cache() {
+        let yaml_content = r#"
+manifest:
+  id: "TEST-CACHE-001"
+  version: "1.0.0"
+  name: "Cache Manifest"
+  description: "Response cache"
+
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Profile"
+    input: "target_company"
+    instructions: "Profile the company."
+    output_target: "markdown_file"
+quality_gates: []
+"#;
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", yaml_content).unwrap();
+        let manifest = Manifest::load_from_file(file.path()).unwrap();
+        let cache = ResponseCache::in_memory(crate::response_cache::DEFAULT_TTL).unwrap();
+
+        // Pre-populate the cache for the exact request the phase will send
+        let mut agent = Agent::new(manifest.clone(), String::new(), None, None, None);
+        agent.seed_inputs("Acme Corp");
+        let phase = &manifest.phases[0];
+        let req = LLMRequest {
+            system: system_prompt(phase, &date_line()),
+            user: agent.resolve_input(phase).unwrap(),
+            volatile_system: Some(date_line()),
+            model: DEFAULT_MODEL.to_string(),
+            ..Default::default()
+        };
+        cache
+            .put(
+                &ResponseCache::key_for(&req, None),
+                DEFAULT_MODEL,
+                "cached output",
+                Some("end_turn"),
+            )
+            .unwrap();
+
+        // Invalid key: only a cache hit can succeed
+        let mut agent = Agent::new(manifest, "invalid".to_string(), None, None, None)
+            .with_response_cache(cache);
+        agent.run_workflow("Acme Corp").await.unwrap();
+
+        assert!(agent.llm_client.last_cache_hit());
+        assert_eq!(
+            agent.llm_client.last_stop_reason(),
+            Some(crate::llm::StopReason::EndTurn)
+        );
+        assert!(agent
+            .get_context("markdown_file")
+            .unwrap()
+            .contains("cached output"));
+    }
+
     #[test]
     fn test_agent_state_initializes_empty() {
         let manifest = create_test_manifest();
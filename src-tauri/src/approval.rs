@@ -0,0 +1,261 @@
+//! Approval Checkpoints (Human-in-the-Loop)
+//!
+//! A phase with `approval: required` holds the workflow once its output is ready, so
+//! a reviewer can catch a wrong turn (e.g. the wrong company matched in PHASE-01)
+//! before later phases build on it. The agent waits on its run's `ApprovalGate`; the
+//! `review_phase` command delivers the decision through the `ApprovalRegistry`:
+//! - approve: continue with the output as is
+//! - edit: continue with the reviewer's corrected output
+//! - reject: re-run the phase with the reviewer's feedback, then wait again
+//! - cancel: end the run (the session is marked failed)
+//!
+//! A gate only lives as long as its run. If the app exits while a run waits, the
+//! session stays `awaiting_approval`; resuming it re-opens the review, and
+//! `StoredReview` says how a decision applies to the stored phase output instead.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Session and phase status while a phase waits for review
+pub const AWAITING_APPROVAL: &str = "awaiting_approval";
+
+/// The reviewer's decision on a phase output
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    /// Approve with the output replaced by the reviewer's edit
+    Edit {
+        output: String,
+    },
+    /// Re-run the phase with this feedback appended to its instructions
+    Reject {
+        feedback: String,
+    },
+    /// End the run without continuing
+    Cancel,
+}
+
+/// A decision on a phase held by a run that has ended: the phase output's new status,
+/// output (None keeps the stored one) and error, and the session's new status
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredReview {
+    pub phase_status: &'static str,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub session_status: &'static str,
+}
+
+impl StoredReview {
+    /// Approved or edited outputs count as completed; a rejected phase is left failed
+    /// (with the feedback) so it runs again when the session continues
+    pub fn for_decision(decision: ApprovalDecision) -> Self {
+        let (phase_status, output, error, session_status) = match decision {
+            ApprovalDecision::Approve => ("completed", None, None, "running"),
+            ApprovalDecision::Edit { output } => ("completed", Some(output), None, "running"),
+            ApprovalDecision::Reject { feedback } => (
+                "failed",
+                None,
+                Some(format!("Rejected by reviewer: {}", feedback)),
+                "running",
+            ),
+            ApprovalDecision::Cancel => (
+                "failed",
+                None,
+                Some("Cancelled by reviewer".to_string()),
+                "failed",
+            ),
+        };
+        Self {
+            phase_status,
+            output,
+            error,
+            session_status,
+        }
+    }
+}
+
+/// Called with ("awaiting_approval" | "running", phase id) as a run starts and stops
+/// waiting (e.g. to persist the session status)
+pub type StatusHook = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+/// The phase a run is waiting on, if any (shared by a gate and the registry)
+type Waiting = Arc<Mutex<Option<String>>>;
+
+/// The agent's end of a run's approval channel
+pub struct ApprovalGate {
+    decisions: mpsc::UnboundedReceiver<ApprovalDecision>,
+    waiting: Waiting,
+    on_status: Option<StatusHook>,
+}
+
+impl ApprovalGate {
+    /// Builder: Hook told when the run starts and stops waiting for a decision
+    pub fn with_status_hook(mut self, hook: StatusHook) -> Self {
+        self.on_status = Some(hook);
+        self
+    }
+
+    /// Wait for the reviewer's decision on `phase_id`
+    pub async fn wait(&mut self, phase_id: &str) -> Result<ApprovalDecision> {
+        self.set_waiting(Some(phase_id));
+        let decision = self.decisions.recv().await;
+        self.set_waiting(None);
+        if let Some(hook) = &self.on_status {
+            hook("running", phase_id);
+        }
+        decision.ok_or_else(|| anyhow!("Review of phase {} was cancelled", phase_id))
+    }
+
+    fn set_waiting(&self, phase_id: Option<&str>) {
+        if let Ok(mut waiting) = self.waiting.lock() {
+            *waiting = phase_id.map(|id| id.to_string());
+        }
+        if let (Some(hook), Some(phase_id)) = (&self.on_status, phase_id) {
+            hook(AWAITING_APPROVAL, phase_id);
+        }
+    }
+}
+
+/// Approval channels of the runs in flight, by run id
+#[derive(Default)]
+pub struct ApprovalRegistry {
+    runs: Mutex<HashMap<String, (mpsc::UnboundedSender<ApprovalDecision>, Waiting)>>,
+}
+
+impl ApprovalRegistry {
+    /// Open the approval channel for a run; the gate goes to its agent
+    pub fn open(&self, run_id: &str) -> ApprovalGate {
+        let (sender, decisions) = mpsc::unbounded_channel();
+        let waiting = Waiting::default();
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.insert(run_id.to_string(), (sender, waiting.clone()));
+        }
+        ApprovalGate {
+            decisions,
+            waiting,
+            on_status: None,
+        }
+    }
+
+    /// Forget a finished run
+    pub fn close(&self, run_id: &str) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.remove(run_id);
+        }
+    }
+
+    /// Deliver a decision to a run waiting for review; returns the phase it was waiting on
+    pub fn submit(&self, run_id: &str, decision: ApprovalDecision) -> Result<String> {
+        let runs = self
+            .runs
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock approvals"))?;
+        let (sender, waiting) = runs
+            .get(run_id)
+            .ok_or_else(|| anyhow!("Run {} is not in progress", run_id))?;
+        // Taken here so a second submission cannot queue up for the next checkpoint
+        let phase_id = waiting
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock approvals"))?
+            .take()
+            .ok_or_else(|| anyhow!("Run {} is not waiting for approval", run_id))?;
+        sender
+            .send(decision)
+            .map_err(|_| anyhow!("Run {} has finished", run_id))?;
+        Ok(phase_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_decision_reaches_waiting_run_only() {
+        let registry = ApprovalRegistry::default();
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let recorded = statuses.clone();
+        let mut gate =
+            registry
+                .open("run-1")
+                .with_status_hook(Box::new(move |status, phase_id| {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push(format!("{} {}", status, phase_id));
+                }));
+
+        // Nothing is waiting yet
+        assert!(registry
+            .submit("run-1", ApprovalDecision::Approve)
+            .unwrap_err()
+            .to_string()
+            .contains("not waiting"));
+        assert!(registry.submit("run-2", ApprovalDecision::Approve).is_err());
+
+        let waiter = tokio::spawn(async move {
+            let decision = gate.wait("PHASE-01").await.unwrap();
+            (gate, decision)
+        });
+        let phase_id = loop {
+            match registry.submit(
+                "run-1",
+                ApprovalDecision::Reject {
+                    feedback: "Wrong company".to_string(),
+                },
+            ) {
+                Ok(phase_id) => break phase_id,
+                Err(_) => tokio::task::yield_now().await,
+            }
+        };
+        assert_eq!(phase_id, "PHASE-01");
+
+        let (_gate, decision) = waiter.await.unwrap();
+        assert_eq!(
+            decision,
+            ApprovalDecision::Reject {
+                feedback: "Wrong company".to_string()
+            }
+        );
+        assert_eq!(
+            *statuses.lock().unwrap(),
+            ["awaiting_approval PHASE-01", "running PHASE-01"]
+        );
+
+        let json: ApprovalDecision =
+            serde_json::from_str(r#"{"action": "edit", "output": "Acme Corp (US)"}"#).unwrap();
+        assert_eq!(
+            json,
+            ApprovalDecision::Edit {
+                output: "Acme Corp (US)".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stored_review_outcomes() {
+        let edited = StoredReview::for_decision(ApprovalDecision::Edit {
+            output: "Acme Corp (US)".to_string(),
+        });
+        assert_eq!(edited.phase_status, "completed");
+        assert_eq!(edited.output.as_deref(), Some("Acme Corp (US)"));
+        assert_eq!(edited.session_status, "running");
+
+        let rejected = StoredReview::for_decision(ApprovalDecision::Reject {
+            feedback: "Wrong company".to_string(),
+        });
+        assert_eq!(rejected.phase_status, "failed");
+        assert!(rejected.error.unwrap().contains("Wrong company"));
+
+        let cancel: ApprovalDecision = serde_json::from_str(r#"{"action": "cancel"}"#).unwrap();
+        let cancelled = StoredReview::for_decision(cancel);
+        assert_eq!(
+            (cancelled.phase_status, cancelled.session_status),
+            ("failed", "failed")
+        );
+    }
+}
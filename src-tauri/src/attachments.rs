@@ -0,0 +1,152 @@
+//! Session Attachments (Local Files as Phase Inputs)
+//!
+//! Loads local files attached to a research run and converts them to `ContentBlock`s:
+//! - Images (png, jpg/jpeg, gif, webp) → image blocks
+//! - PDFs → document blocks
+//! - Spreadsheet exports and text (csv, tsv, txt, md, json) → text blocks
+//!
+//! Manifest phases opt in by name via `attachments: ["annual_report.pdf"]`, or `["*"]`
+//! for every file attached to the session.
+
+use crate::llm::ContentBlock;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Largest file accepted as an attachment (providers reject bigger inline payloads)
+pub const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Wildcard phase reference meaning "every attachment"
+pub const ALL_ATTACHMENTS: &str = "*";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String, // File name, used by phases to reference the attachment
+    pub path: PathBuf,
+    pub media_type: String,
+    pub size_bytes: u64,
+    #[serde(skip)]
+    pub block: Option<ContentBlock>, // Loaded content (not persisted)
+}
+
+impl Attachment {
+    /// Read a local file and encode it for the providers
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Invalid attachment path: {:?}", path))?
+            .to_string();
+
+        let size_bytes = fs::metadata(path)
+            .with_context(|| format!("Failed to read attachment: {:?}", path))?
+            .len();
+        if size_bytes > MAX_ATTACHMENT_BYTES {
+            return Err(anyhow!(
+                "Attachment {} is {} MB; the limit is {} MB",
+                name,
+                size_bytes / (1024 * 1024),
+                MAX_ATTACHMENT_BYTES / (1024 * 1024)
+            ));
+        }
+
+        let media_type = media_type_for(path).ok_or_else(|| {
+            anyhow!(
+                "Unsupported attachment type: {} (use PDF, PNG/JPEG/GIF/WebP, or CSV/TXT/MD/JSON; export spreadsheets as CSV)",
+                name
+            )
+        })?;
+
+        let block = if media_type.starts_with("image/") {
+            ContentBlock::image(media_type, &fs::read(path)?)
+        } else if media_type == "application/pdf" {
+            ContentBlock::document(&name, media_type, &fs::read(path)?)
+        } else {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Attachment {} is not valid UTF-8 text", name))?;
+            ContentBlock::text(format!("Attachment: {}\n\n{}", name, text))
+        };
+
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            media_type: media_type.to_string(),
+            size_bytes,
+            block: Some(block),
+        })
+    }
+
+    /// Whether a phase reference (`name` or `*`) selects this attachment
+    pub fn matches(&self, reference: &str) -> bool {
+        reference == ALL_ATTACHMENTS || reference == self.name
+    }
+}
+
+/// Media type by file extension (None for unsupported types)
+pub fn media_type_for(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let media_type = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        _ => return None,
+    };
+    Some(media_type)
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::Builder;
+
+    #[test]
+    fn test_csv_attachment_becomes_text_block() {
+        let mut file = Builder::new().suffix(".csv").tempfile().unwrap();
+        write!(file, "name,title\nJane,CFO\n").unwrap();
+
+        let attachment = Attachment::from_file(file.path()).unwrap();
+
+        assert_eq!(attachment.media_type, "text/csv");
+        match attachment.block.unwrap() {
+            ContentBlock::Text { text } => assert!(text.contains("Jane,CFO")),
+            other => panic!("expected text block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pdf_attachment_becomes_document_block() {
+        let mut file = Builder::new().suffix(".pdf").tempfile().unwrap();
+        file.write_all(b"%PDF-1.4 test").unwrap();
+
+        let attachment = Attachment::from_file(file.path()).unwrap();
+
+        assert!(matches!(
+            attachment.block,
+            Some(ContentBlock::Document { ref media_type, .. }) if media_type == "application/pdf"
+        ));
+        assert!(attachment.matches(ALL_ATTACHMENTS));
+        assert!(attachment.matches(&attachment.name.clone()));
+        assert!(!attachment.matches("other.pdf"));
+    }
+
+    #[test]
+    fn test_unsupported_attachment_type_is_rejected() {
+        let file = Builder::new().suffix(".xlsx").tempfile().unwrap();
+        let err = Attachment::from_file(file.path()).unwrap_err();
+        assert!(err.to_string().contains("export spreadsheets as CSV"));
+    }
+}
@@ -5,6 +5,9 @@
 //! - AES-256-GCM encrypted API key storage per provider
 //! - SQLite-based local user database
 
+use crate::manifest::history::ManifestSnapshot;
+use crate::metrics::{self, LatencyStats, PhaseMetrics};
+use crate::telemetry::RunLogEntry;
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
     Aes256Gcm, Nonce,
@@ -144,6 +147,8 @@ pub struct ResearchSession {
     pub manifest_name: Option<String>,
     pub status: String, // "running", "completed", "failed"
     pub current_phase_id: Option<String>,
+    /// Manifest library version the session ran (None for sessions before the library)
+    pub manifest_version_id: Option<i64>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -181,6 +186,33 @@ pub struct PhaseOutput {
     pub error: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub cache_hit: bool,             // Output was served from the response cache
+    pub reasoning: Option<String>,   // Thinking/reasoning trace behind the output
+    pub stop_reason: Option<String>, // Why generation stopped ("end_turn", "max_tokens", ...)
+    pub truncated: bool,             // Output ended at the token limit (incomplete)
+    pub metrics: Option<PhaseMetrics>, // Timing, model used and throughput (when recorded)
+}
+
+/// Run metadata recorded alongside a phase output (fields left as None are unchanged)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseOutputMeta {
+    pub cache_hit: Option<bool>,
+    pub reasoning: Option<String>,
+    pub stop_reason: Option<String>,
+    pub truncated: Option<bool>,
+    pub metrics: Option<PhaseMetrics>,
+}
+
+/// Local file attached to a research session (content stays on disk)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAttachment {
+    pub id: i64,
+    pub session_id: i64,
+    pub name: String,
+    pub path: String,
+    pub media_type: String,
+    pub size_bytes: i64,
+    pub created_at: String,
 }
 
 /// Session conversation message for user data accessibility (IM-5030, IM-5031, IM-5032)
@@ -201,6 +233,15 @@ pub struct ResumeSessionResult {
     pub session: ResearchSession,
     pub next_phase_id: String,
     pub context: SessionContext,
+    pub pending_review: Option<PendingReview>, // Phase held for review when the run ended
+}
+
+/// A phase output that was awaiting approval when its run ended (the review re-opens on resume)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingReview {
+    pub phase_id: String,
+    pub phase_name: String,
+    pub output: String,
 }
 
 /// Session context for resume (IM-5020)
@@ -243,6 +284,49 @@ pub struct ProjectSummary {
     pub updated_at: String,
 }
 
+/// Manifest in the user's library (one per `manifest.id`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSummary {
+    pub id: i64,
+    pub manifest_key: String,
+    pub name: String,
+    pub path: Option<String>,
+    pub latest_version: Option<String>,
+    pub revision_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Manifest version for listing (without content)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestVersionSummary {
+    pub id: i64,
+    pub manifest_id: i64,
+    /// Save counter within the manifest (1, 2, ...)
+    pub revision: i64,
+    /// Semantic version from the manifest header at the time of the save
+    pub version: String,
+    pub content_hash: String,
+    pub author: String,
+    pub created_at: String,
+}
+
+/// Full manifest version, as saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestVersion {
+    pub id: i64,
+    pub manifest_id: i64,
+    pub manifest_key: String,
+    pub revision: i64,
+    pub version: String,
+    pub content: String,
+    /// Composed manifest for versions that use imports/extends
+    pub resolved_content: Option<String>,
+    pub content_hash: String,
+    pub author: String,
+    pub created_at: String,
+}
+
 /// Supported AI providers
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Provider {
@@ -314,6 +398,15 @@ impl AuthManager {
         // Migration: Add archived column to projects and research_sessions tables
         self.migrate_archive_columns()?;
 
+        // Migration: Add run metadata columns to phase_outputs (response cache hits)
+        self.migrate_phase_outputs_meta_fields()?;
+
+        // Migration: Link research sessions to the manifest version they ran
+        self.migrate_session_manifest_version()?;
+
+        // Migration: Allow the 'awaiting_approval' status (approval checkpoints)
+        self.migrate_awaiting_approval_status()?;
+
         self.conn.execute_batch(
             r#"
             CREATE TABLE IF NOT EXISTS users (
@@ -397,8 +490,9 @@ impl AuthManager {
                 company TEXT NOT NULL,
                 model TEXT NOT NULL,
                 manifest_name TEXT,
-                status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed', 'awaiting_approval')),
                 current_phase_id TEXT,
+                manifest_version_id INTEGER,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
@@ -416,9 +510,24 @@ impl AuthManager {
                 session_id INTEGER NOT NULL,
                 phase_id TEXT NOT NULL,
                 phase_name TEXT NOT NULL,
-                status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed', 'awaiting_approval')),
+                system_prompt TEXT,
+                user_input TEXT,
                 output TEXT,
                 error TEXT,
+                cache_hit INTEGER NOT NULL DEFAULT 0,
+                reasoning TEXT,
+                stop_reason TEXT,
+                truncated INTEGER NOT NULL DEFAULT 0,
+                provider TEXT,
+                model TEXT,
+                started_at TEXT,
+                finished_at TEXT,
+                ttft_ms INTEGER,
+                duration_ms INTEGER,
+                retries INTEGER,
+                output_tokens INTEGER,
+                tokens_per_sec REAL,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (session_id) REFERENCES research_sessions(id) ON DELETE CASCADE,
@@ -443,6 +552,36 @@ impl AuthManager {
             CREATE INDEX IF NOT EXISTS idx_session_conversations_session_id
             ON session_conversations(session_id);
 
+            -- Session attachments table: local files given to phases as inputs
+            CREATE TABLE IF NOT EXISTS session_attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                path TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY (session_id) REFERENCES research_sessions(id) ON DELETE CASCADE,
+                UNIQUE(session_id, name)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_session_attachments_session_id
+            ON session_attachments(session_id);
+
+            -- Session run logs table: agent log lines for each research run
+            CREATE TABLE IF NOT EXISTS session_run_logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id INTEGER NOT NULL,
+                logged_at TEXT NOT NULL,
+                level TEXT NOT NULL,
+                phase_id TEXT,
+                message TEXT NOT NULL,
+                FOREIGN KEY (session_id) REFERENCES research_sessions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_session_run_logs_session_id
+            ON session_run_logs(session_id);
+
             -- Projects table: groups related research sessions
             CREATE TABLE IF NOT EXISTS projects (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -472,6 +611,37 @@ impl AuthManager {
 
             CREATE INDEX IF NOT EXISTS idx_project_sessions_session_id
             ON project_sessions(session_id);
+
+            -- Manifest library: one row per manifest id, with its saved versions
+            CREATE TABLE IF NOT EXISTS manifests (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                manifest_key TEXT NOT NULL,
+                name TEXT NOT NULL,
+                path TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+                UNIQUE(user_id, manifest_key)
+            );
+
+            -- Manifest versions: immutable content snapshots (sessions reference these)
+            CREATE TABLE IF NOT EXISTS manifest_versions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                manifest_id INTEGER NOT NULL,
+                revision INTEGER NOT NULL,
+                version TEXT NOT NULL,
+                content TEXT NOT NULL,
+                resolved_content TEXT,
+                content_hash TEXT NOT NULL,
+                author TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY (manifest_id) REFERENCES manifests(id) ON DELETE CASCADE,
+                UNIQUE(manifest_id, revision)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_manifest_versions_manifest_id
+            ON manifest_versions(manifest_id);
             "#
         )?;
 
@@ -610,6 +780,100 @@ impl AuthManager {
         Ok(())
     }
 
+    /// Migrate phase_outputs table to add run metadata columns (response cache hits, reasoning,
+    /// stop reason / truncation, timing metrics)
+    /// Uses idempotent ALTER TABLE ADD COLUMN pattern (safe to run multiple times)
+    fn migrate_phase_outputs_meta_fields(&mut self) -> Result<(), AuthError> {
+        let table_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name='phase_outputs')",
+            [],
+            |row| row.get(0),
+        ).unwrap_or(false);
+
+        if !table_exists {
+            // Table doesn't exist yet, no migration needed (init_database will create it)
+            return Ok(());
+        }
+
+        let has_cache_hit =
+            self.conn
+                .query_row("SELECT cache_hit FROM phase_outputs LIMIT 1", [], |_row| {
+                    Ok(())
+                });
+
+        if has_cache_hit.is_err() {
+            tracing::info!("Migrating phase_outputs table: adding cache_hit column");
+            let _ = self.conn.execute(
+                "ALTER TABLE phase_outputs ADD COLUMN cache_hit INTEGER NOT NULL DEFAULT 0",
+                [],
+            );
+            tracing::info!("Migration complete: added cache_hit column to phase_outputs");
+        }
+
+        let has_reasoning =
+            self.conn
+                .query_row("SELECT reasoning FROM phase_outputs LIMIT 1", [], |_row| {
+                    Ok(())
+                });
+
+        if has_reasoning.is_err() {
+            tracing::info!("Migrating phase_outputs table: adding reasoning column");
+            let _ = self
+                .conn
+                .execute("ALTER TABLE phase_outputs ADD COLUMN reasoning TEXT", []);
+            tracing::info!("Migration complete: added reasoning column to phase_outputs");
+        }
+
+        let has_stop_reason = self.conn.query_row(
+            "SELECT stop_reason FROM phase_outputs LIMIT 1",
+            [],
+            |_row| Ok(()),
+        );
+
+        if has_stop_reason.is_err() {
+            tracing::info!("Migrating phase_outputs table: adding stop_reason/truncated columns");
+            let _ = self
+                .conn
+                .execute("ALTER TABLE phase_outputs ADD COLUMN stop_reason TEXT", []);
+            let _ = self.conn.execute(
+                "ALTER TABLE phase_outputs ADD COLUMN truncated INTEGER NOT NULL DEFAULT 0",
+                [],
+            );
+            tracing::info!(
+                "Migration complete: added stop_reason/truncated columns to phase_outputs"
+            );
+        }
+
+        let has_metrics = self.conn.query_row(
+            "SELECT duration_ms FROM phase_outputs LIMIT 1",
+            [],
+            |_row| Ok(()),
+        );
+
+        if has_metrics.is_err() {
+            tracing::info!("Migrating phase_outputs table: adding timing metrics columns");
+            for column in [
+                "provider TEXT",
+                "model TEXT",
+                "started_at TEXT",
+                "finished_at TEXT",
+                "ttft_ms INTEGER",
+                "duration_ms INTEGER",
+                "retries INTEGER",
+                "output_tokens INTEGER",
+                "tokens_per_sec REAL",
+            ] {
+                let _ = self.conn.execute(
+                    &format!("ALTER TABLE phase_outputs ADD COLUMN {}", column),
+                    [],
+                );
+            }
+            tracing::info!("Migration complete: added timing metrics columns to phase_outputs");
+        }
+
+        Ok(())
+    }
+
     /// Migrate projects and research_sessions tables to add archived column
     /// Uses idempotent ALTER TABLE ADD COLUMN pattern (safe to run multiple times)
     fn migrate_archive_columns(&mut self) -> Result<(), AuthError> {
@@ -667,6 +931,89 @@ impl AuthManager {
         Ok(())
     }
 
+    /// Migrate research_sessions table to add the manifest_version_id column
+    /// Uses idempotent ALTER TABLE ADD COLUMN pattern (safe to run multiple times)
+    fn migrate_session_manifest_version(&mut self) -> Result<(), AuthError> {
+        let table_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name='research_sessions')",
+            [],
+            |row| row.get(0),
+        ).unwrap_or(false);
+
+        if !table_exists {
+            // Table doesn't exist yet, no migration needed (init_database will create it)
+            return Ok(());
+        }
+
+        let has_manifest_version = self.conn.query_row(
+            "SELECT manifest_version_id FROM research_sessions LIMIT 1",
+            [],
+            |_row| Ok(()),
+        );
+
+        if has_manifest_version.is_err() {
+            tracing::info!("Migrating research_sessions table: adding manifest_version_id column");
+            let _ = self.conn.execute(
+                "ALTER TABLE research_sessions ADD COLUMN manifest_version_id INTEGER",
+                [],
+            );
+            tracing::info!(
+                "Migration complete: added manifest_version_id column to research_sessions"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Migrate research_sessions and phase_outputs to accept the 'awaiting_approval' status
+    /// SQLite can't change a CHECK constraint in place, so each table is rebuilt from its own
+    /// definition (columns added by earlier migrations included) and its rows copied over
+    fn migrate_awaiting_approval_status(&mut self) -> Result<(), AuthError> {
+        // Dropping the old research_sessions table would otherwise cascade to phase_outputs
+        self.conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        let migrated = self.rebuild_for_awaiting_approval();
+        self.conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        migrated
+    }
+
+    fn rebuild_for_awaiting_approval(&mut self) -> Result<(), AuthError> {
+        for table in ["research_sessions", "phase_outputs"] {
+            let sql: String = self
+                .conn
+                .query_row(
+                    "SELECT sql FROM sqlite_master WHERE type='table' AND name=?1",
+                    params![table],
+                    |row| row.get(0),
+                )
+                .unwrap_or_default();
+
+            // Table doesn't exist yet (init_database will create it) or is already migrated
+            if sql.is_empty() || sql.contains("awaiting_approval") {
+                continue;
+            }
+
+            tracing::info!(
+                "Migrating {} table: adding 'awaiting_approval' status",
+                table
+            );
+            let rebuilt = sql
+                .replacen(table, &format!("{}_new", table), 1)
+                .replace("'failed')", "'failed', 'awaiting_approval')");
+            // Indexes go with the old table; init_database recreates them
+            self.conn.execute_batch(&format!(
+                "BEGIN;
+                 {rebuilt};
+                 INSERT INTO {table}_new SELECT * FROM {table};
+                 DROP TABLE {table};
+                 ALTER TABLE {table}_new RENAME TO {table};
+                 COMMIT;"
+            ))?;
+            tracing::info!("Migration complete: {} accepts 'awaiting_approval'", table);
+        }
+
+        Ok(())
+    }
+
     // ------------------------------------------------------------------
     // User Management
     // ------------------------------------------------------------------
@@ -1402,7 +1749,8 @@ impl AuthManager {
 
         let result = self.conn.query_row(
             r#"
-            SELECT id, user_id, company, model, manifest_name, status, current_phase_id, created_at, updated_at
+            SELECT id, user_id, company, model, manifest_name, status, current_phase_id,
+                   manifest_version_id, created_at, updated_at
             FROM research_sessions
             WHERE id = ?1 AND user_id = ?2
             "#,
@@ -1416,8 +1764,9 @@ impl AuthManager {
                     manifest_name: row.get(4)?,
                     status: row.get(5)?,
                     current_phase_id: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
+                    manifest_version_id: row.get(7)?,
+                    created_at: row.get(8)?,
+                    updated_at: row.get(9)?,
                 })
             },
         );
@@ -1457,6 +1806,213 @@ impl AuthManager {
         Ok(())
     }
 
+    /// Record the manifest version a research session runs
+    pub fn set_session_manifest_version(
+        &self,
+        session_id: i64,
+        manifest_version_id: i64,
+    ) -> Result<(), AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        self.conn.execute(
+            "UPDATE research_sessions SET manifest_version_id = ?1 WHERE id = ?2 AND user_id = ?3",
+            params![manifest_version_id, session_id, user.id],
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------
+    // Manifest Library
+    // ------------------------------------------------------------------
+
+    /// Record a manifest save as a new version (authored by the current user).
+    /// Unchanged content returns the latest version instead of adding a duplicate.
+    pub fn record_manifest_version(
+        &self,
+        snapshot: &ManifestSnapshot,
+    ) -> Result<ManifestVersionSummary, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO manifests (user_id, manifest_key, name, path)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(user_id, manifest_key) DO UPDATE SET
+                name = excluded.name,
+                path = COALESCE(excluded.path, manifests.path),
+                updated_at = datetime('now')
+            "#,
+            params![user.id, snapshot.manifest_key, snapshot.name, snapshot.path],
+        )?;
+        let manifest_id: i64 = tx.query_row(
+            "SELECT id FROM manifests WHERE user_id = ?1 AND manifest_key = ?2",
+            params![user.id, snapshot.manifest_key],
+            |row| row.get(0),
+        )?;
+
+        let latest = tx.query_row(
+            r#"
+            SELECT id, manifest_id, revision, version, content_hash, author, created_at
+            FROM manifest_versions
+            WHERE manifest_id = ?1
+            ORDER BY revision DESC
+            LIMIT 1
+            "#,
+            params![manifest_id],
+            manifest_version_summary_from_row,
+        );
+        let latest = match latest {
+            Ok(version) => Some(version),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(AuthError::Database(e)),
+        };
+
+        if let Some(latest) = &latest {
+            if latest.content_hash == snapshot.content_hash {
+                tx.commit()?;
+                return Ok(latest.clone());
+            }
+        }
+
+        let revision = latest.map_or(1, |v| v.revision + 1);
+        tx.execute(
+            r#"
+            INSERT INTO manifest_versions
+                (manifest_id, revision, version, content, resolved_content, content_hash, author)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+            params![
+                manifest_id,
+                revision,
+                snapshot.version,
+                snapshot.content,
+                snapshot.resolved_content,
+                snapshot.content_hash,
+                user.username
+            ],
+        )?;
+        let version = tx.query_row(
+            r#"
+            SELECT id, manifest_id, revision, version, content_hash, author, created_at
+            FROM manifest_versions
+            WHERE id = ?1
+            "#,
+            params![tx.last_insert_rowid()],
+            manifest_version_summary_from_row,
+        )?;
+        tx.commit()?;
+
+        Ok(version)
+    }
+
+    /// List the manifests in the current user's library (most recently saved first)
+    pub fn list_manifests(&self) -> Result<Vec<ManifestSummary>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT
+                m.id,
+                m.manifest_key,
+                m.name,
+                m.path,
+                (SELECT version FROM manifest_versions
+                 WHERE manifest_id = m.id ORDER BY revision DESC LIMIT 1) as latest_version,
+                (SELECT COUNT(*) FROM manifest_versions WHERE manifest_id = m.id) as revision_count,
+                m.created_at,
+                m.updated_at
+            FROM manifests m
+            WHERE m.user_id = ?1
+            ORDER BY m.updated_at DESC
+            "#,
+        )?;
+
+        let manifests = stmt
+            .query_map(params![user.id], |row| {
+                Ok(ManifestSummary {
+                    id: row.get(0)?,
+                    manifest_key: row.get(1)?,
+                    name: row.get(2)?,
+                    path: row.get(3)?,
+                    latest_version: row.get(4)?,
+                    revision_count: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(manifests)
+    }
+
+    /// List the saved versions of a manifest by its `manifest.id` (newest first)
+    pub fn list_manifest_versions(
+        &self,
+        manifest_key: &str,
+    ) -> Result<Vec<ManifestVersionSummary>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT v.id, v.manifest_id, v.revision, v.version, v.content_hash, v.author, v.created_at
+            FROM manifest_versions v
+            JOIN manifests m ON v.manifest_id = m.id
+            WHERE m.user_id = ?1 AND m.manifest_key = ?2
+            ORDER BY v.revision DESC
+            "#,
+        )?;
+
+        let versions = stmt
+            .query_map(
+                params![user.id, manifest_key],
+                manifest_version_summary_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(versions)
+    }
+
+    /// Get a manifest version with its content
+    pub fn get_manifest_version(
+        &self,
+        version_id: i64,
+    ) -> Result<Option<ManifestVersion>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        let result = self.conn.query_row(
+            r#"
+            SELECT v.id, v.manifest_id, m.manifest_key, v.revision, v.version, v.content,
+                   v.resolved_content, v.content_hash, v.author, v.created_at
+            FROM manifest_versions v
+            JOIN manifests m ON v.manifest_id = m.id
+            WHERE v.id = ?1 AND m.user_id = ?2
+            "#,
+            params![version_id, user.id],
+            |row| {
+                Ok(ManifestVersion {
+                    id: row.get(0)?,
+                    manifest_id: row.get(1)?,
+                    manifest_key: row.get(2)?,
+                    revision: row.get(3)?,
+                    version: row.get(4)?,
+                    content: row.get(5)?,
+                    resolved_content: row.get(6)?,
+                    content_hash: row.get(7)?,
+                    author: row.get(8)?,
+                    created_at: row.get(9)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(version) => Ok(Some(version)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(AuthError::Database(e)),
+        }
+    }
+
     // ------------------------------------------------------------------
     // Phase Output Management
     // ------------------------------------------------------------------
@@ -1526,6 +2082,65 @@ impl AuthManager {
         Ok(id)
     }
 
+    /// Record run metadata for an existing phase output
+    pub fn update_phase_output_meta(
+        &self,
+        session_id: i64,
+        phase_id: &str,
+        meta: &PhaseOutputMeta,
+    ) -> Result<(), AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        if let Some(m) = &meta.metrics {
+            self.conn.execute(
+                r#"
+                UPDATE phase_outputs
+                SET provider = ?1, model = ?2, started_at = ?3, finished_at = ?4, ttft_ms = ?5,
+                    duration_ms = ?6, retries = ?7, output_tokens = ?8, tokens_per_sec = ?9
+                WHERE session_id = ?10 AND phase_id = ?11
+                  AND session_id IN (SELECT id FROM research_sessions WHERE user_id = ?12)
+                "#,
+                params![
+                    m.provider,
+                    m.model,
+                    m.started_at,
+                    m.finished_at,
+                    m.ttft_ms.map(|v| v as i64),
+                    m.duration_ms as i64,
+                    m.retries,
+                    m.output_tokens.map(|v| v as i64),
+                    m.tokens_per_sec,
+                    session_id,
+                    phase_id,
+                    user.id
+                ],
+            )?;
+        }
+
+        self.conn.execute(
+            r#"
+            UPDATE phase_outputs
+            SET cache_hit = COALESCE(?1, cache_hit),
+                reasoning = COALESCE(?2, reasoning),
+                stop_reason = COALESCE(?3, stop_reason),
+                truncated = COALESCE(?4, truncated)
+            WHERE session_id = ?5 AND phase_id = ?6
+              AND session_id IN (SELECT id FROM research_sessions WHERE user_id = ?7)
+            "#,
+            params![
+                meta.cache_hit,
+                meta.reasoning,
+                meta.stop_reason,
+                meta.truncated,
+                session_id,
+                phase_id,
+                user.id
+            ],
+        )?;
+
+        Ok(())
+    }
+
     /// Get all phase outputs for a research session (in order)
     pub fn get_phase_outputs(&self, session_id: i64) -> Result<Vec<PhaseOutput>, AuthError> {
         let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
@@ -1544,7 +2159,8 @@ impl AuthManager {
         // Extended for user data accessibility (IM-5011): includes system_prompt and user_input
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, session_id, phase_id, phase_name, status, system_prompt, user_input, output, error, created_at, updated_at
+            SELECT id, session_id, phase_id, phase_name, status, system_prompt, user_input, output, error, created_at, updated_at, cache_hit, reasoning, stop_reason, truncated,
+                   provider, model, started_at, finished_at, ttft_ms, duration_ms, retries, output_tokens, tokens_per_sec
             FROM phase_outputs
             WHERE session_id = ?1
             ORDER BY created_at ASC
@@ -1565,6 +2181,11 @@ impl AuthManager {
                     error: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    cache_hit: row.get(11)?,
+                    reasoning: row.get(12)?,
+                    stop_reason: row.get(13)?,
+                    truncated: row.get(14)?,
+                    metrics: phase_metrics_from_row(row, 15)?,
                 })
             })?
             .filter_map(|r| r.ok())
@@ -1573,6 +2194,39 @@ impl AuthManager {
         Ok(outputs)
     }
 
+    /// Latency percentiles per provider/model over the current user's completed phases.
+    /// `from`/`to` are inclusive `YYYY-MM-DD` dates (UTC) matched against the phase start.
+    pub fn get_latency_stats(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<LatencyStats>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT po.provider, po.model, po.started_at, po.finished_at, po.ttft_ms, po.duration_ms,
+                   po.retries, po.output_tokens, po.tokens_per_sec
+            FROM phase_outputs po
+            JOIN research_sessions rs ON rs.id = po.session_id
+            WHERE rs.user_id = ?1
+              AND po.status = 'completed'
+              AND po.duration_ms IS NOT NULL
+              AND (?2 IS NULL OR substr(po.started_at, 1, 10) >= ?2)
+              AND (?3 IS NULL OR substr(po.started_at, 1, 10) <= ?3)
+            "#,
+        )?;
+
+        let samples: Vec<PhaseMetrics> = stmt
+            .query_map(params![user.id, from, to], |row| {
+                phase_metrics_from_row(row, 0)
+            })?
+            .filter_map(|r| r.ok().flatten())
+            .collect();
+
+        Ok(metrics::summarize(&samples))
+    }
+
     /// Get the last completed phase output for a session (for resumption)
     pub fn get_last_completed_phase(
         &self,
@@ -1594,7 +2248,8 @@ impl AuthManager {
         // Extended for user data accessibility (IM-5011): includes system_prompt and user_input
         let result = self.conn.query_row(
             r#"
-            SELECT id, session_id, phase_id, phase_name, status, system_prompt, user_input, output, error, created_at, updated_at
+            SELECT id, session_id, phase_id, phase_name, status, system_prompt, user_input, output, error, created_at, updated_at, cache_hit, reasoning, stop_reason, truncated,
+                   provider, model, started_at, finished_at, ttft_ms, duration_ms, retries, output_tokens, tokens_per_sec
             FROM phase_outputs
             WHERE session_id = ?1 AND status = 'completed'
             ORDER BY created_at DESC
@@ -1614,6 +2269,11 @@ impl AuthManager {
                     error: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    cache_hit: row.get(11)?,
+                    reasoning: row.get(12)?,
+                    stop_reason: row.get(13)?,
+                    truncated: row.get(14)?,
+                    metrics: phase_metrics_from_row(row, 15)?,
                 })
             },
         );
@@ -1625,6 +2285,183 @@ impl AuthManager {
         }
     }
 
+    // ------------------------------------------------------------------
+    // Session Attachment Management
+    // ------------------------------------------------------------------
+
+    /// Record a local file attached to a research session (upserts on session_id + name)
+    pub fn add_session_attachment(
+        &self,
+        session_id: i64,
+        name: &str,
+        path: &str,
+        media_type: &str,
+        size_bytes: i64,
+    ) -> Result<i64, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        // Verify the session belongs to the current user
+        let session_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM research_sessions WHERE id = ?1 AND user_id = ?2)",
+            params![session_id, user.id],
+            |row| row.get(0),
+        )?;
+
+        if !session_exists {
+            return Err(AuthError::Database(rusqlite::Error::QueryReturnedNoRows));
+        }
+
+        self.conn.execute(
+            r#"
+            INSERT INTO session_attachments (session_id, name, path, media_type, size_bytes)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(session_id, name) DO UPDATE SET
+                path = excluded.path,
+                media_type = excluded.media_type,
+                size_bytes = excluded.size_bytes
+            "#,
+            params![session_id, name, path, media_type, size_bytes],
+        )?;
+
+        let id: i64 = self.conn.query_row(
+            "SELECT id FROM session_attachments WHERE session_id = ?1 AND name = ?2",
+            params![session_id, name],
+            |row| row.get(0),
+        )?;
+
+        Ok(id)
+    }
+
+    /// Get all attachments for a research session (in attach order)
+    pub fn get_session_attachments(
+        &self,
+        session_id: i64,
+    ) -> Result<Vec<SessionAttachment>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        // Verify the session belongs to the current user
+        let session_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM research_sessions WHERE id = ?1 AND user_id = ?2)",
+            params![session_id, user.id],
+            |row| row.get(0),
+        )?;
+
+        if !session_exists {
+            return Err(AuthError::Database(rusqlite::Error::QueryReturnedNoRows));
+        }
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, session_id, name, path, media_type, size_bytes, created_at
+            FROM session_attachments
+            WHERE session_id = ?1
+            ORDER BY id ASC
+            "#,
+        )?;
+
+        let attachments = stmt
+            .query_map(params![session_id], |row| {
+                Ok(SessionAttachment {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    name: row.get(2)?,
+                    path: row.get(3)?,
+                    media_type: row.get(4)?,
+                    size_bytes: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(attachments)
+    }
+
+    // ------------------------------------------------------------------
+    // Session Run Logs
+    // ------------------------------------------------------------------
+
+    /// Append a run's log lines to a research session (resumed runs add to the same log)
+    pub fn append_run_log(
+        &self,
+        session_id: i64,
+        entries: &[RunLogEntry],
+    ) -> Result<(), AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        // Verify the session belongs to the current user
+        let session_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM research_sessions WHERE id = ?1 AND user_id = ?2)",
+            params![session_id, user.id],
+            |row| row.get(0),
+        )?;
+
+        if !session_exists {
+            return Err(AuthError::Database(rusqlite::Error::QueryReturnedNoRows));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO session_run_logs (session_id, logged_at, level, phase_id, message)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+            )?;
+            for entry in entries {
+                stmt.execute(params![
+                    session_id,
+                    entry.timestamp,
+                    entry.level,
+                    entry.phase_id,
+                    entry.message
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Get the run log for a research session (oldest first)
+    pub fn get_run_log(&self, session_id: i64) -> Result<Vec<RunLogEntry>, AuthError> {
+        let user = self.current_user.as_ref().ok_or(AuthError::NotLoggedIn)?;
+
+        // Verify the session belongs to the current user
+        let session_exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM research_sessions WHERE id = ?1 AND user_id = ?2)",
+            params![session_id, user.id],
+            |row| row.get(0),
+        )?;
+
+        if !session_exists {
+            return Err(AuthError::Database(rusqlite::Error::QueryReturnedNoRows));
+        }
+
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT logged_at, level, phase_id, message
+            FROM session_run_logs
+            WHERE session_id = ?1
+            ORDER BY id ASC
+            "#,
+        )?;
+
+        let entries = stmt
+            .query_map(params![session_id], |row| {
+                Ok(RunLogEntry {
+                    timestamp: row.get(0)?,
+                    level: row.get(1)?,
+                    phase_id: row.get(2)?,
+                    message: row.get(3)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
     // ------------------------------------------------------------------
     // Session Conversation Management (IM-5030, IM-5031, IM-5032)
     // ------------------------------------------------------------------
@@ -1697,7 +2534,7 @@ impl AuthManager {
                 SELECT id, session_id, phase_id, role, content, created_at
                 FROM session_conversations
                 WHERE session_id = ?1 AND phase_id = ?2
-                ORDER BY created_at ASC
+                ORDER BY created_at ASC, id ASC
                 {}
                 "#,
                 limit_clause
@@ -1724,7 +2561,7 @@ impl AuthManager {
                 SELECT id, session_id, phase_id, role, content, created_at
                 FROM session_conversations
                 WHERE session_id = ?1
-                ORDER BY created_at ASC
+                ORDER BY created_at ASC, id ASC
                 {}
                 "#,
                 limit_clause
@@ -2213,6 +3050,45 @@ impl AuthManager {
     }
 }
 
+/// Manifest version summary from (id, manifest_id, revision, version, content_hash,
+/// author, created_at)
+fn manifest_version_summary_from_row(
+    row: &rusqlite::Row,
+) -> rusqlite::Result<ManifestVersionSummary> {
+    Ok(ManifestVersionSummary {
+        id: row.get(0)?,
+        manifest_id: row.get(1)?,
+        revision: row.get(2)?,
+        version: row.get(3)?,
+        content_hash: row.get(4)?,
+        author: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Timing metrics stored on a phase_outputs row, starting at column `start`
+/// (provider, model, started_at, finished_at, ttft_ms, duration_ms, retries,
+/// output_tokens, tokens_per_sec); None when the phase has no recorded timing
+fn phase_metrics_from_row(
+    row: &rusqlite::Row,
+    start: usize,
+) -> rusqlite::Result<Option<PhaseMetrics>> {
+    let Some(duration_ms) = row.get::<_, Option<i64>>(start + 5)? else {
+        return Ok(None);
+    };
+    Ok(Some(PhaseMetrics {
+        provider: row.get(start)?,
+        model: row.get::<_, Option<String>>(start + 1)?.unwrap_or_default(),
+        started_at: row.get::<_, Option<String>>(start + 2)?.unwrap_or_default(),
+        finished_at: row.get::<_, Option<String>>(start + 3)?.unwrap_or_default(),
+        ttft_ms: row.get::<_, Option<i64>>(start + 4)?.map(|v| v as u64),
+        duration_ms: duration_ms as u64,
+        retries: row.get::<_, Option<u32>>(start + 6)?.unwrap_or_default(),
+        output_tokens: row.get::<_, Option<i64>>(start + 7)?.map(|v| v as u64),
+        tokens_per_sec: row.get(start + 8)?,
+    }))
+}
+
 // ------------------------------------------------------------------
 // Tests
 // ------------------------------------------------------------------
@@ -2305,4 +3181,312 @@ mod tests {
         let deepseek = keys.iter().find(|k| k.provider == "deepseek").unwrap();
         assert!(!deepseek.has_key);
     }
+
+    #[test]
+    fn test_phase_output_meta_records_run_metadata() {
+        let dir = tempdir().unwrap();
+        let mut manager = AuthManager::new(&dir.path().join("test.db")).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+
+        let session_id = manager
+            .create_research_session("Acme Corp", "mock", None)
+            .unwrap();
+        manager
+            .save_phase_output(
+                session_id,
+                "PHASE-01",
+                "Profile",
+                "completed",
+                None,
+                None,
+                Some("output"),
+                None,
+            )
+            .unwrap();
+        assert!(!manager.get_phase_outputs(session_id).unwrap()[0].cache_hit);
+
+        manager
+            .update_phase_output_meta(
+                session_id,
+                "PHASE-01",
+                &PhaseOutputMeta {
+                    cache_hit: Some(true),
+                    reasoning: Some("Revenue grew, so...".to_string()),
+                    stop_reason: Some("max_tokens".to_string()),
+                    truncated: Some(true),
+                    metrics: Some(PhaseMetrics {
+                        provider: Some("mock".to_string()),
+                        model: "mock-fast".to_string(),
+                        started_at: "2026-10-01T09:00:00.000Z".to_string(),
+                        finished_at: "2026-10-01T09:00:02.500Z".to_string(),
+                        ttft_ms: Some(300),
+                        duration_ms: 2500,
+                        ..Default::default()
+                    }),
+                },
+            )
+            .unwrap();
+        let output = &manager.get_phase_outputs(session_id).unwrap()[0];
+        assert!(output.cache_hit);
+        assert_eq!(output.reasoning.as_deref(), Some("Revenue grew, so..."));
+        assert_eq!(output.stop_reason.as_deref(), Some("max_tokens"));
+        assert!(output.truncated);
+        let metrics = output.metrics.as_ref().unwrap();
+        assert_eq!((metrics.ttft_ms, metrics.duration_ms), (Some(300), 2500));
+
+        let stats = manager.get_latency_stats(Some("2026-10-01"), None).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(
+            (stats[0].model.as_str(), stats[0].p50_ms),
+            ("mock-fast", 2500)
+        );
+        assert!(manager
+            .get_latency_stats(None, Some("2026-09-30"))
+            .unwrap()
+            .is_empty());
+
+        // Fields left as None keep their recorded value
+        manager
+            .update_phase_output_meta(session_id, "PHASE-01", &PhaseOutputMeta::default())
+            .unwrap();
+        let output = &manager.get_phase_outputs(session_id).unwrap()[0];
+        assert!(output.cache_hit);
+        assert!(output.reasoning.is_some());
+        assert!(output.truncated);
+        assert!(output.metrics.is_some());
+    }
+
+    #[test]
+    fn test_run_log_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut manager = AuthManager::new(&dir.path().join("test.db")).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+
+        let session_id = manager
+            .create_research_session("Acme Corp", "mock", None)
+            .unwrap();
+        let first_run = vec![
+            RunLogEntry::new(tracing::Level::INFO, Some("PHASE-01"), "📤 SENDING"),
+            RunLogEntry::new(tracing::Level::ERROR, Some("PHASE-01"), "Phase failed"),
+        ];
+        manager.append_run_log(session_id, &first_run).unwrap();
+        manager
+            .append_run_log(
+                session_id,
+                &[RunLogEntry::new(tracing::Level::INFO, None, "resumed")],
+            )
+            .unwrap();
+
+        let log = manager.get_run_log(session_id).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[..2], first_run[..]);
+        assert_eq!(log[2].message, "resumed");
+
+        // Sessions the user does not own are rejected
+        assert!(manager.get_run_log(session_id + 1).is_err());
+    }
+
+    #[test]
+    fn test_manifest_versions_dedupe_and_link_sessions() {
+        let dir = tempdir().unwrap();
+        let mut manager = AuthManager::new(&dir.path().join("test.db")).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+
+        let snapshot = |version: &str, content: &str| ManifestSnapshot {
+            manifest_key: "M-1".to_string(),
+            name: "Test".to_string(),
+            version: version.to_string(),
+            path: Some("/tmp/m.yaml".to_string()),
+            content: content.to_string(),
+            resolved_content: None,
+            content_hash: crate::manifest::history::content_hash(content, None),
+        };
+
+        let first = manager
+            .record_manifest_version(&snapshot("1.0.0", "a"))
+            .unwrap();
+        assert_eq!((first.revision, first.author.as_str()), (1, "testuser"));
+
+        // Unchanged content reuses the latest version
+        let again = manager
+            .record_manifest_version(&snapshot("1.0.0", "a"))
+            .unwrap();
+        assert_eq!(again.id, first.id);
+
+        let second = manager
+            .record_manifest_version(&snapshot("1.1.0", "b"))
+            .unwrap();
+        assert_eq!(second.revision, 2);
+
+        let versions = manager.list_manifest_versions("M-1").unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.revision).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        let library = manager.list_manifests().unwrap();
+        assert_eq!(library.len(), 1);
+        assert_eq!(library[0].latest_version.as_deref(), Some("1.1.0"));
+        assert_eq!(library[0].revision_count, 2);
+
+        let stored = manager.get_manifest_version(first.id).unwrap().unwrap();
+        assert_eq!(stored.content, "a");
+
+        let session_id = manager
+            .create_research_session("Acme Corp", "mock", Some("m.yaml"))
+            .unwrap();
+        manager
+            .set_session_manifest_version(session_id, second.id)
+            .unwrap();
+        let session = manager.get_research_session(session_id).unwrap().unwrap();
+        assert_eq!(session.manifest_version_id, Some(second.id));
+    }
+
+    #[test]
+    fn test_phase_outputs_store_prompts_on_fresh_and_existing_databases() {
+        let save_with_prompts = |manager: &mut AuthManager, session_id: i64| {
+            manager
+                .save_phase_output(
+                    session_id,
+                    "PHASE-02",
+                    "Brief",
+                    "completed",
+                    Some("You are a research agent."),
+                    Some("Acme Corp"),
+                    Some("output"),
+                    None,
+                )
+                .unwrap();
+            let outputs = manager.get_phase_outputs(session_id).unwrap();
+            let saved = outputs.iter().find(|o| o.phase_id == "PHASE-02").unwrap();
+            assert_eq!(
+                saved.system_prompt.as_deref(),
+                Some("You are a research agent.")
+            );
+            assert_eq!(saved.user_input.as_deref(), Some("Acme Corp"));
+        };
+
+        // Fresh database: the columns come with the table itself
+        let dir = tempdir().unwrap();
+        let mut manager = AuthManager::new(&dir.path().join("fresh.db")).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+        let session_id = manager
+            .create_research_session("Acme Corp", "mock", None)
+            .unwrap();
+        save_with_prompts(&mut manager, session_id);
+
+        // Existing database from before the prompt fields: migrated on open
+        let path = dir.path().join("existing.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE research_sessions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL,
+                    company TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    manifest_name TEXT,
+                    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                    current_phase_id TEXT,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE TABLE phase_outputs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    session_id INTEGER NOT NULL,
+                    phase_id TEXT NOT NULL,
+                    phase_name TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                    output TEXT,
+                    error TEXT,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (session_id) REFERENCES research_sessions(id) ON DELETE CASCADE,
+                    UNIQUE(session_id, phase_id)
+                );
+                INSERT INTO research_sessions (user_id, company, model, status)
+                VALUES (1, 'Acme Corp', 'mock', 'running');
+                INSERT INTO phase_outputs (session_id, phase_id, phase_name, status, output)
+                VALUES (1, 'PHASE-01', 'Profile', 'completed', 'profile');
+                "#,
+            )
+            .unwrap();
+        }
+        let mut manager = AuthManager::new(&path).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+        let outputs = manager.get_phase_outputs(1).unwrap();
+        assert_eq!(outputs[0].output.as_deref(), Some("profile"));
+        assert!(outputs[0].system_prompt.is_none());
+        save_with_prompts(&mut manager, 1);
+    }
+
+    #[test]
+    fn test_awaiting_approval_migration_keeps_sessions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        {
+            // Schema before approval checkpoints
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE research_sessions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL,
+                    company TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    manifest_name TEXT,
+                    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                    current_phase_id TEXT,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE TABLE phase_outputs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    session_id INTEGER NOT NULL,
+                    phase_id TEXT NOT NULL,
+                    phase_name TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'completed', 'failed')),
+                    output TEXT,
+                    error TEXT,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (session_id) REFERENCES research_sessions(id) ON DELETE CASCADE,
+                    UNIQUE(session_id, phase_id)
+                );
+                INSERT INTO research_sessions (user_id, company, model, status)
+                VALUES (1, 'Acme Corp', 'mock', 'completed');
+                INSERT INTO phase_outputs (session_id, phase_id, phase_name, status, output)
+                VALUES (1, 'PHASE-01', 'Profile', 'completed', 'profile');
+                "#,
+            )
+            .unwrap();
+        }
+
+        let mut manager = AuthManager::new(&path).unwrap();
+        manager.register("testuser", "testpass123").unwrap();
+        manager.login("testuser", "testpass123").unwrap();
+
+        let session = manager.get_research_session(1).unwrap().unwrap();
+        assert_eq!(
+            (session.company.as_str(), session.status.as_str()),
+            ("Acme Corp", "completed")
+        );
+        let outputs = manager.get_phase_outputs(1).unwrap();
+        assert_eq!(outputs[0].output.as_deref(), Some("profile"));
+        manager
+            .update_research_session(1, "awaiting_approval", Some("PHASE-01"))
+            .unwrap();
+        let session = manager.get_research_session(1).unwrap().unwrap();
+        assert_eq!(session.status, "awaiting_approval");
+        assert!(manager.update_research_session(1, "paused", None).is_err());
+
+        // Already migrated: reopening leaves the table alone
+        drop(manager);
+        AuthManager::new(&path).unwrap();
+    }
 }
@@ -0,0 +1,105 @@
+//! Dry-Run Reporting (Prompt Preview & Cost Estimation)
+//!
+//! A dry run walks the manifest exactly as `Agent::run_workflow` would, but renders
+//! each phase's prompts instead of calling a provider. Upstream phase outputs are
+//! replaced with placeholders, so the report shows what every phase would send,
+//! which model would receive it, and roughly what it would cost.
+//!
+//! Token counts are estimates (~4 characters per token); costs use the model
+//! catalog's list prices and assume the output uses the full `max_tokens` budget.
+
+use serde::{Deserialize, Serialize};
+
+/// Output budget requested from every provider (mirrors `max_tokens` in llm.rs)
+pub const MAX_OUTPUT_TOKENS: usize = 4096;
+
+// ------------------------------------------------------------------
+// Report Structures
+// ------------------------------------------------------------------
+
+/// Where a phase's model came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelSource {
+    Override, // UI-selected model for the whole run
+    Phase,    // `model:` set on the phase in the manifest
+    Default,  // Agent default
+}
+
+/// Rendered request and estimates for a single phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseDryRun {
+    pub phase_id: String,
+    pub phase_name: String,
+    pub model: String,
+    pub model_source: ModelSource,
+    pub system_prompt: String,
+    pub user_input: String,
+    pub estimated_input_tokens: usize,
+    pub estimated_output_tokens: usize,
+    /// None when the model has no known pricing
+    pub estimated_cost_usd: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+/// Full dry-run result for a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub manifest_id: String,
+    pub manifest_name: String,
+    pub initial_input: String,
+    pub phases: Vec<PhaseDryRun>,
+    pub total_estimated_tokens: usize,
+    /// Sum over phases with known pricing
+    pub total_estimated_cost_usd: f64,
+    /// Count of warnings across all phases
+    pub warning_count: usize,
+}
+
+impl DryRunReport {
+    pub fn new(manifest_id: &str, manifest_name: &str, initial_input: &str) -> Self {
+        Self {
+            manifest_id: manifest_id.to_string(),
+            manifest_name: manifest_name.to_string(),
+            initial_input: initial_input.to_string(),
+            phases: Vec::new(),
+            total_estimated_tokens: 0,
+            total_estimated_cost_usd: 0.0,
+            warning_count: 0,
+        }
+    }
+
+    /// Append a phase and update the totals
+    pub fn push(&mut self, phase: PhaseDryRun) {
+        self.total_estimated_tokens += phase.estimated_input_tokens + phase.estimated_output_tokens;
+        self.total_estimated_cost_usd += phase.estimated_cost_usd.unwrap_or(0.0);
+        self.warning_count += phase.warnings.len();
+        self.phases.push(phase);
+    }
+}
+
+// ------------------------------------------------------------------
+// Estimation
+// ------------------------------------------------------------------
+
+/// Rough token estimate (~4 characters per token, rounded up)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+    }
+}
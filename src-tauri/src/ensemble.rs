@@ -0,0 +1,292 @@
+//! Multi-Model Ensembles (Vote or Judge)
+//!
+//! Phases whose answers vary across models (situation classification, ROI estimates)
+//! can run on several models at once (`ensemble` in the manifest). The agent collects
+//! one candidate per model and combines them here:
+//! - `vote`: each field of the structured output takes the value most candidates gave
+//!   (ties go to the earlier model in the list)
+//! - `select`: a judge model picks the best candidate and explains why
+//! - `merge`: a judge model combines the candidates into one output
+//!
+//! Every candidate and the verdict's rationale are persisted as phase outputs of their
+//! own (`PHASE-02@gpt-4o`, `PHASE-02@verdict`).
+
+use crate::llm::LLMRequest;
+use crate::manifest::{parse_json_output, EnsembleStrategy};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Suffix of the phase output holding the verdict and its rationale
+pub const VERDICT_SUFFIX: &str = "verdict";
+
+const JUDGE_SYSTEM_PROMPT: &str = "You are the judge of a research ensemble. Several models were \
+given the same research task; compare their answers for accuracy, specificity and how well they \
+follow the instructions.";
+
+/// One model's output for an ensemble phase
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub model: String,
+    pub output: String,
+}
+
+/// The combined output and how it was reached
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub output: String,
+    pub rationale: String,
+    /// Index of the selected candidate (`select`, or a single candidate)
+    pub chosen: Option<usize>,
+}
+
+/// Phase id under which a candidate (or the verdict) is persisted
+pub fn candidate_phase_id(phase_id: &str, model: &str) -> String {
+    format!("{}@{}", phase_id, model)
+}
+
+/// Combine candidates by majority: per top-level field for JSON objects, otherwise
+/// over the whole value. Candidates without JSON output do not vote.
+pub fn vote(candidates: &[Candidate]) -> Result<Verdict> {
+    let mut ballots = Vec::new();
+    let mut abstained = Vec::new();
+    for candidate in candidates {
+        match parse_json_output(&candidate.output) {
+            Some(value) => ballots.push(value),
+            None => abstained.push(candidate.model.as_str()),
+        }
+    }
+    if ballots.is_empty() {
+        anyhow::bail!("No ensemble candidate returned JSON output to vote on");
+    }
+
+    let mut lines = Vec::new();
+    let output = if ballots.iter().all(Value::is_object) {
+        let mut fields: Vec<&String> = Vec::new();
+        for ballot in &ballots {
+            for field in ballot.as_object().into_iter().flat_map(|o| o.keys()) {
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+        }
+        let mut merged = serde_json::Map::new();
+        for field in fields {
+            let values: Vec<&Value> = ballots.iter().filter_map(|b| b.get(field)).collect();
+            let (value, votes) = majority(&values);
+            lines.push(tally_line(field, value, votes, values.len()));
+            merged.insert(field.clone(), value.clone());
+        }
+        Value::Object(merged)
+    } else {
+        let values: Vec<&Value> = ballots.iter().collect();
+        let (value, votes) = majority(&values);
+        lines.push(tally_line("output", value, votes, values.len()));
+        value.clone()
+    };
+    if !abstained.is_empty() {
+        lines.push(format!(
+            "No JSON output (not counted): {}",
+            abstained.join(", ")
+        ));
+    }
+
+    Ok(Verdict {
+        output: serde_json::to_string_pretty(&output)?,
+        rationale: lines.join("\n"),
+        chosen: None,
+    })
+}
+
+/// The most common value and its count; the first one given wins a tie
+fn majority<'a>(values: &[&'a Value]) -> (&'a Value, usize) {
+    let mut best = (values[0], 0);
+    for value in values {
+        let votes = values
+            .iter()
+            .filter(|v| vote_key(v) == vote_key(value))
+            .count();
+        if votes > best.1 {
+            best = (value, votes);
+        }
+    }
+    best
+}
+
+/// Values that count as the same answer ("High" and " HIGH" agree)
+fn vote_key(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.trim().to_lowercase(),
+        other => other.to_string(),
+    }
+}
+
+fn tally_line(field: &str, value: &Value, votes: usize, total: usize) -> String {
+    format!("- {}: {} ({}/{} votes)", field, value, votes, total)
+}
+
+/// Request asking `model` to select or merge the candidates for a phase
+pub fn build_judge_request(
+    model: &str,
+    strategy: EnsembleStrategy,
+    instructions: &str,
+    input: &str,
+    candidates: &[Candidate],
+) -> LLMRequest {
+    let reply_format = match strategy {
+        EnsembleStrategy::Merge => {
+            "Merge the answers into one that keeps what they agree on and the best-supported \
+             version of what they disagree on, in the format the instructions ask for. Reply with \
+             JSON only: {\"rationale\": \"<how you resolved the disagreements>\", \"output\": \
+             <the merged answer: a string, or a JSON object if the instructions ask for JSON>}"
+        }
+        _ => {
+            "Pick the best answer. Reply with JSON only: {\"choice\": <answer number>, \
+             \"rationale\": \"<why it is better than the others>\"}"
+        }
+    };
+    let answers = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("## Answer {} ({})\n\n{}", i + 1, c.model, c.output.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    LLMRequest {
+        system: format!("{}\n\n{}", JUDGE_SYSTEM_PROMPT, reply_format),
+        user: format!(
+            "# Task instructions\n\n{}\n\n# Task input\n\n{}\n\n# Answers\n\n{}",
+            instructions.trim(),
+            input.trim(),
+            answers
+        ),
+        model: model.to_string(),
+        ..LLMRequest::default()
+    }
+}
+
+#[derive(Deserialize)]
+struct JudgeReply {
+    #[serde(default)]
+    choice: Option<usize>,
+    #[serde(default)]
+    output: Option<Value>,
+    #[serde(default)]
+    rationale: String,
+}
+
+/// Verdict from the judge's reply (JSON, optionally fenced or surrounded by text)
+pub fn parse_judge_reply(
+    strategy: EnsembleStrategy,
+    reply: &str,
+    candidates: &[Candidate],
+) -> Result<Verdict> {
+    let reply: JudgeReply = parse_json_output(reply)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .ok_or_else(|| anyhow!("The judge did not reply with a JSON verdict"))?;
+
+    if strategy == EnsembleStrategy::Merge {
+        let output = match reply.output {
+            Some(Value::String(text)) => text,
+            Some(value) => serde_json::to_string_pretty(&value)?,
+            None => anyhow::bail!("The judge's verdict has no merged output"),
+        };
+        return Ok(Verdict {
+            output,
+            rationale: reply.rationale,
+            chosen: None,
+        });
+    }
+
+    let chosen = reply
+        .choice
+        .and_then(|n| n.checked_sub(1))
+        .filter(|&i| i < candidates.len())
+        .ok_or_else(|| anyhow!("The judge chose no valid answer ({:?})", reply.choice))?;
+    Ok(Verdict {
+        output: candidates[chosen].output.clone(),
+        rationale: format!(
+            "Selected answer {} ({}): {}",
+            chosen + 1,
+            candidates[chosen].model,
+            reply.rationale
+        ),
+        chosen: Some(chosen),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(model: &str, output: &str) -> Candidate {
+        Candidate {
+            model: model.to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_vote_takes_majority_per_field() {
+        let candidates = [
+            candidate("claude", r#"{"situation": "Crisis", "roi": 3}"#),
+            candidate(
+                "gpt",
+                "```json\n{\"situation\": \"GROWTH\", \"roi\": 5}\n```",
+            ),
+            candidate("gemini", r#"{"situation": "growth ", "roi": 3}"#),
+            candidate("mock", "I could not classify this company."),
+        ];
+        let verdict = vote(&candidates).unwrap();
+        let output: Value = serde_json::from_str(&verdict.output).unwrap();
+        assert_eq!(output["situation"], "GROWTH");
+        assert_eq!(output["roi"], 3);
+        assert!(verdict
+            .rationale
+            .contains("situation: \"GROWTH\" (2/3 votes)"));
+        assert!(verdict.rationale.contains("not counted): mock"));
+
+        assert!(vote(&candidates[3..]).is_err());
+    }
+
+    #[test]
+    fn test_judge_reply_selects_or_merges() {
+        let candidates = [
+            candidate("claude", "Answer A"),
+            candidate("gpt", "Answer B"),
+        ];
+        let request = build_judge_request(
+            "judge",
+            EnsembleStrategy::Select,
+            "Classify.",
+            "Acme",
+            &candidates,
+        );
+        assert!(request.user.contains("## Answer 2 (gpt)\n\nAnswer B"));
+
+        let selected = parse_judge_reply(
+            EnsembleStrategy::Select,
+            r#"Verdict: {"choice": 2, "rationale": "Cites the 10-K"}"#,
+            &candidates,
+        )
+        .unwrap();
+        assert_eq!(selected.output, "Answer B");
+        assert_eq!(selected.chosen, Some(1));
+        assert!(selected.rationale.contains("Cites the 10-K"));
+        assert!(parse_judge_reply(
+            EnsembleStrategy::Select,
+            r#"{"choice": 3, "rationale": ""}"#,
+            &candidates
+        )
+        .is_err());
+
+        let merged = parse_judge_reply(
+            EnsembleStrategy::Merge,
+            r#"{"rationale": "Kept A's numbers", "output": {"roi": 4}}"#,
+            &candidates,
+        )
+        .unwrap();
+        assert_eq!(merged.output, "{\n  \"roi\": 4\n}");
+        assert_eq!(merged.rationale, "Kept A's numbers");
+    }
+}
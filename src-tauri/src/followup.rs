@@ -0,0 +1,155 @@
+//! Follow-up Chat (Multi-Turn Q&A over a Generated Report)
+//!
+//! Builds the `MultiTurnRequest` for a follow-up question:
+//! - The report goes in the system prompt, marked for provider caching so that
+//!   repeated questions about the same brief reuse it
+//! - Prior turns (from `session_conversations`, or from the UI for unsaved chats)
+//!   are replayed as alternating user/assistant messages
+//! - The new question is appended as the final user message
+
+use crate::llm::{ChatMessage, MultiTurnRequest};
+use serde::{Deserialize, Serialize};
+
+/// `phase_id` under which follow-up turns are stored in `session_conversations`
+pub const FOLLOWUP_PHASE_ID: &str = "followup";
+
+/// Most recent messages replayed to the model (older turns are dropped)
+pub const MAX_HISTORY_MESSAGES: usize = 20;
+
+const FOLLOWUP_SYSTEM_PROMPT: &str = "You are a helpful assistant analyzing business intelligence reports. \
+The user has generated a research report and wants to ask follow-up questions. \
+Use the provided context and the earlier questions in this conversation to give accurate, relevant answers.";
+
+/// One stored question or answer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FollowupTurn {
+    pub role: String, // "user" | "assistant" (other roles are ignored)
+    pub content: String,
+}
+
+impl FollowupTurn {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Build the request for `question`, grounded in `context` and the prior `history`
+pub fn build_followup_request(
+    model: &str,
+    context: &str,
+    history: &[FollowupTurn],
+    question: &str,
+) -> MultiTurnRequest {
+    let system = if context.trim().is_empty() {
+        FOLLOWUP_SYSTEM_PROMPT.to_string()
+    } else {
+        format!(
+            "{}\n\nHere is the generated report:\n\n{}",
+            FOLLOWUP_SYSTEM_PROMPT, context
+        )
+    };
+
+    let mut messages = history_messages(history);
+    push_merged(&mut messages, ChatMessage::user(question));
+
+    MultiTurnRequest::new(model)
+        .with_system(system)
+        .with_messages(messages)
+        .with_caching()
+}
+
+/// Convert stored turns to chat messages the providers accept: the last
+/// `MAX_HISTORY_MESSAGES`, starting with a user turn, with no two consecutive
+/// messages from the same role (e.g. a question whose answer failed)
+fn history_messages(history: &[FollowupTurn]) -> Vec<ChatMessage> {
+    let start = history.len().saturating_sub(MAX_HISTORY_MESSAGES);
+    let mut messages: Vec<ChatMessage> = Vec::new();
+
+    for turn in &history[start..] {
+        let message = match turn.role.as_str() {
+            "user" => ChatMessage::user(&turn.content),
+            "assistant" if !messages.is_empty() => ChatMessage::assistant(&turn.content),
+            _ => continue,
+        };
+        push_merged(&mut messages, message);
+    }
+
+    messages
+}
+
+fn push_merged(messages: &mut Vec<ChatMessage>, message: ChatMessage) {
+    match messages.last_mut() {
+        Some(last) if last.role == message.role => {
+            last.content.push_str("\n\n");
+            last.content.push_str(&message.content);
+        }
+        _ => messages.push(message),
+    }
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatRole;
+
+    #[test]
+    fn test_request_replays_history_and_caches_report() {
+        let history = vec![
+            FollowupTurn::new("user", "Who is the CEO?"),
+            FollowupTurn::new("assistant", "Jane Doe."),
+        ];
+
+        let req =
+            build_followup_request("mock", "REPORT BODY", &history, "How long has she served?");
+
+        assert!(req.enable_caching);
+        assert!(req.system.as_deref().unwrap().contains("REPORT BODY"));
+        assert_eq!(req.messages.len(), 3);
+        assert_eq!(req.messages[1].role, ChatRole::Assistant);
+        assert_eq!(req.messages[2].content, "How long has she served?");
+        assert!(req.validate().is_ok());
+    }
+
+    #[test]
+    fn test_history_is_normalized_for_providers() {
+        let history = vec![
+            FollowupTurn::new("assistant", "orphaned answer"),
+            FollowupTurn::new("system", "ignored"),
+            FollowupTurn::new("user", "first question (no answer)"),
+            FollowupTurn::new("user", "second question"),
+            FollowupTurn::new("assistant", "answer"),
+        ];
+
+        let req = build_followup_request("mock", "", &history, "third question");
+
+        let roles: Vec<_> = req.messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            vec![ChatRole::User, ChatRole::Assistant, ChatRole::User]
+        );
+        assert!(req.messages[0].content.contains("first question"));
+        assert!(req.messages[0].content.contains("second question"));
+    }
+
+    #[test]
+    fn test_history_is_capped_to_recent_turns() {
+        let history: Vec<_> = (0..40)
+            .map(|i| {
+                let role = if i % 2 == 0 { "user" } else { "assistant" };
+                FollowupTurn::new(role, format!("turn {}", i))
+            })
+            .collect();
+
+        let req = build_followup_request("mock", "report", &history, "latest");
+
+        assert_eq!(req.messages.len(), MAX_HISTORY_MESSAGES + 1);
+        assert_eq!(req.messages[0].content, "turn 20");
+    }
+}
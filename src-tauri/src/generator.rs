@@ -0,0 +1,286 @@
+//! Manifest Generator (Draft a Protocol from a Brief)
+//!
+//! Drafts a complete manifest from a natural-language description of the research
+//! and, optionally, example outputs the protocol should produce:
+//! - The system prompt carries the manifest JSON Schema and the rules the linter
+//!   enforces, so the first draft is usually close
+//! - Each draft is linted; if it has errors, the draft and the problems are sent
+//!   back for repair, up to `MAX_REPAIR_ROUNDS` times
+//! - The last draft is returned either way, with its diagnostics, so the author can
+//!   finish it in the editor
+
+use crate::llm::{ChatMessage, LLMClient, MultiTurnRequest};
+use crate::manifest::json_schema::manifest_json_schema;
+use crate::manifest::lint::{self, Diagnostic};
+use crate::manifest::Manifest;
+use crate::model_catalog::ModelCatalog;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Repair requests after the first draft
+pub const MAX_REPAIR_ROUNDS: usize = 3;
+
+const GENERATOR_SYSTEM_PROMPT: &str = "You design research protocols for a business intelligence agent. \
+A protocol is a YAML manifest: a sequence of phases, each an LLM call with instructions, whose outputs \
+feed later phases and are combined into the final report.
+
+Write a complete manifest for the user's research brief:
+- `manifest`: id (UPPER-KEBAB-CASE), version \"1.0.0\", name and description
+- `schemas`: one entry per structured phase output; use `enum` on fields with a fixed set of values
+- `phases`: ids \"PHASE-01\", \"PHASE-02\", ...; clear, specific `instructions`; `dependencies` listing \
+the earlier phases each one builds on; `input` naming the context key it reads (the first phase reads \
+`target_company`, the research subject the user enters); `output_schema` or `output_target` naming \
+what it produces; a `logic_map` where the analysis applies fixed rules (e.g. scoring or \
+classification tables)
+- To repeat an analysis per entity (competitors, segments, ...), have one phase list them in a \
+structured output and give the next phase `foreach: {items: \"<OutputSchema>.<list field>\"}`
+- Where answers are judgment calls that vary between models (classifications, estimates), a phase may \
+set `ensemble: {models: [...], strategy: \"vote\"}` (vote needs an `output_schema`; \"select\" or \"merge\" \
+use a judge model)
+- Give `approval: required` to a phase whose output later phases depend on getting right (e.g. \
+identifying the company), so a reviewer can check it before the run continues
+- The last phase writes the final report with `output_target: \"markdown_file\"`
+- Optionally a top-level `report` with a `cover` page template, e.g. \
+`\"# Brief: {{CompanyProfile.company_name}}\\nPrepared {{date}}\"`, and `template: \"{{markdown_file}}\"`
+- `quality_gates`: at least one check per key phase, with a `fail_action` such as \"RETRY\"
+
+Rules the manifest is validated against:
+- phase ids are unique; dependencies name earlier phases (or \"ALL\")
+- every `output_schema` is defined in `schemas`; every quality gate names an existing phase
+- a phase `input` must be produced by an earlier phase (or be `target_company`)
+- do not set `model`, `imports` or `extends`
+
+Reply with the YAML manifest only, without commentary.";
+
+/// What the protocol should research
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManifestBrief {
+    /// e.g. "research a hospital system's communications challenges"
+    pub description: String,
+    /// Example outputs the protocol should produce (report excerpts, tables, ...)
+    #[serde(default)]
+    pub examples: Vec<String>,
+}
+
+/// A generated manifest draft and how it fared against the linter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedManifest {
+    pub content: String,
+    /// LLM calls made (first draft plus repairs)
+    pub rounds: usize,
+    /// False if errors remain after the last repair
+    pub valid: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Request for the first draft
+pub fn build_generation_request(
+    model: &str,
+    brief: &ManifestBrief,
+    catalog: &ModelCatalog,
+) -> MultiTurnRequest {
+    MultiTurnRequest::new(model)
+        .with_system(system_prompt(catalog))
+        .with_message(ChatMessage::user(brief_prompt(brief)))
+}
+
+/// Request to fix `draft`, which has `problems`
+pub fn build_repair_request(
+    model: &str,
+    brief: &ManifestBrief,
+    draft: &str,
+    problems: &[String],
+    catalog: &ModelCatalog,
+) -> MultiTurnRequest {
+    let repair = format!(
+        "The manifest has these problems:\n{}\n\nReply with the corrected YAML manifest only.",
+        problems
+            .iter()
+            .map(|p| format!("- {}", p))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    MultiTurnRequest::new(model)
+        .with_system(system_prompt(catalog))
+        .with_messages(vec![
+            ChatMessage::user(brief_prompt(brief)),
+            ChatMessage::assistant(draft),
+            ChatMessage::user(repair),
+        ])
+}
+
+fn system_prompt(catalog: &ModelCatalog) -> String {
+    let schema = manifest_json_schema(catalog);
+    format!(
+        "{}\n\nJSON Schema of the manifest format:\n{}",
+        GENERATOR_SYSTEM_PROMPT,
+        serde_json::to_string(&schema).unwrap_or_default()
+    )
+}
+
+fn brief_prompt(brief: &ManifestBrief) -> String {
+    let mut prompt = format!("Research brief:\n{}", brief.description.trim());
+    for (i, example) in brief
+        .examples
+        .iter()
+        .filter(|e| !e.trim().is_empty())
+        .enumerate()
+    {
+        prompt.push_str(&format!(
+            "\n\nExample output {} (the protocol should produce results like this):\n{}",
+            i + 1,
+            example.trim()
+        ));
+    }
+    prompt
+}
+
+/// The YAML in a model reply (drops a surrounding ``` fence and any text around it)
+pub fn extract_yaml(reply: &str) -> String {
+    let trimmed = reply.trim();
+    let Some(start) = trimmed.find("```") else {
+        return trimmed.to_string();
+    };
+    // Skip the fence's language tag line (```yaml)
+    let body = &trimmed[start + 3..];
+    let body = body.split_once('\n').map_or("", |(_, rest)| rest);
+    let end = body.find("```").unwrap_or(body.len());
+    body[..end].trim_end().to_string()
+}
+
+/// Problems to send back for repair: lint errors, as "Line N: message"
+pub fn draft_problems(diagnostics: &[Diagnostic], content: &str) -> Vec<String> {
+    // A draft without lint errors parses; an empty protocol is not useful either
+    let mut problems: Vec<String> = diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|d| match d.span {
+            Some(span) => format!("Line {}: {}", span.line, d.message),
+            None => d.message.clone(),
+        })
+        .collect();
+    let no_phases = serde_yaml::from_str::<Manifest>(content).is_ok_and(|m| m.phases.is_empty());
+    if problems.is_empty() && no_phases {
+        problems.push("The manifest has no phases".to_string());
+    }
+    problems
+}
+
+/// Draft a manifest for `brief`, repairing it until it lints without errors
+pub async fn generate_manifest(
+    client: &mut LLMClient,
+    model: &str,
+    brief: &ManifestBrief,
+    catalog: &ModelCatalog,
+) -> Result<GeneratedManifest> {
+    if brief.description.trim().is_empty() {
+        anyhow::bail!("Describe the research the protocol should do");
+    }
+
+    let mut request = build_generation_request(model, brief, catalog);
+    let mut rounds = 0;
+    loop {
+        let reply = client.generate_multi_turn(request).await?;
+        rounds += 1;
+
+        let content = extract_yaml(&reply.text);
+        let diagnostics = lint::lint(&content, None, catalog);
+        let problems = draft_problems(&diagnostics, &content);
+        tracing::info!(
+            round = rounds,
+            problems = problems.len(),
+            "Manifest generator draft"
+        );
+
+        if problems.is_empty() || rounds > MAX_REPAIR_ROUNDS {
+            return Ok(GeneratedManifest {
+                valid: problems.is_empty(),
+                content,
+                rounds,
+                diagnostics,
+            });
+        }
+        request = build_repair_request(model, brief, &content, &problems, catalog);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockConfig;
+
+    const VALID_DRAFT: &str = r#"manifest:
+  id: "HOSPITAL-COMMS-001"
+  version: "1.0.0"
+  name: "Hospital Communications"
+  description: "Communications challenges of a hospital system."
+schemas:
+  ChallengeList:
+    fields:
+      - name: challenge
+      - name: severity
+        enum: [HIGH, MEDIUM, LOW]
+phases:
+  - id: "PHASE-01"
+    name: "Challenges"
+    input: "target_company"
+    instructions: "List the communications challenges."
+    output_schema: "ChallengeList"
+  - id: "PHASE-02"
+    name: "Report"
+    dependencies: ["PHASE-01"]
+    input: "ChallengeList"
+    instructions: "Write the report."
+    output_target: "markdown_file"
+quality_gates:
+  - phase: "PHASE-01"
+    check: "Every challenge has a severity"
+    fail_action: "RETRY"
+"#;
+
+    #[test]
+    fn test_extract_yaml_strips_fences() {
+        assert_eq!(
+            extract_yaml("Here it is:\n```yaml\nmanifest:\n  id: X\n```\nDone."),
+            "manifest:\n  id: X"
+        );
+        assert_eq!(extract_yaml("  manifest:\n  id: X\n"), "manifest:\n  id: X");
+    }
+
+    #[tokio::test]
+    async fn test_generate_repairs_until_valid() {
+        // First draft depends on a phase that does not exist
+        let broken = VALID_DRAFT.replace(
+            r#"dependencies: ["PHASE-01"]"#,
+            r#"dependencies: ["PHASE-09"]"#,
+        );
+        let config = MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            responses: vec![format!("```yaml\n{}```", broken), VALID_DRAFT.to_string()],
+            ..MockConfig::default()
+        };
+        let mut client = LLMClient::new(String::new()).with_mock_config(config);
+        let brief = ManifestBrief {
+            description: "research a hospital system's communications challenges".to_string(),
+            examples: vec![],
+        };
+
+        let generated = generate_manifest(&mut client, "mock", &brief, &ModelCatalog::builtin())
+            .await
+            .unwrap();
+        assert!(generated.valid);
+        assert_eq!(generated.rounds, 2);
+        assert_eq!(generated.content, VALID_DRAFT.trim_end());
+
+        let repair = build_repair_request(
+            "mock",
+            &brief,
+            &broken,
+            &["Line 19: unknown dependency".to_string()],
+            &ModelCatalog::builtin(),
+        );
+        assert_eq!(repair.messages.len(), 3);
+        assert!(repair.messages[2].content.contains("Line 19"));
+    }
+}
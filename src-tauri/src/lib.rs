@@ -1,8 +1,22 @@
 // Library exports for integration tests
 
 pub mod agent;
+pub mod approval;
+pub mod attachments;
+pub mod dry_run;
+pub mod ensemble;
+pub mod followup;
+pub mod generator;
 pub mod llm;
 pub mod manifest;
+pub mod metrics;
+pub mod mock;
+pub mod model_catalog;
+pub mod report;
+pub mod response_cache;
+pub mod sse;
+pub mod telemetry;
+pub mod token_batch;
 
 // Re-export commonly used types for convenience
 pub use agent::Agent;
@@ -1,12 +1,20 @@
 #![allow(dead_code)]
+use crate::mock::{MockConfig, MockProvider};
+use crate::model_catalog;
+use crate::response_cache::ResponseCache;
+use crate::sse::{self, GeminiUsage, OpenAIUsage, StreamEvent, StreamFormat};
 use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use futures::stream::{Stream, StreamExt};
 use reqwest::Client;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
+use tracing::Instrument;
 
 // ------------------------------------------------------------------
 // Error Types
@@ -101,6 +109,9 @@ impl ChatRole {
 pub struct ChatMessage {
     pub role: ChatRole,  // IM-4001-F1: Semantic role (abstracted)
     pub content: String, // IM-4001-F2: Message content
+    /// Images/documents sent ahead of the text (user messages only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ContentBlock>,
 }
 
 impl ChatMessage {
@@ -109,9 +120,16 @@ impl ChatMessage {
         Self {
             role,
             content: content.into(),
+            attachments: Vec::new(),
         }
     }
 
+    /// Builder: Attach content blocks (images, documents) to this message
+    pub fn with_attachments(mut self, attachments: Vec<ContentBlock>) -> Self {
+        self.attachments.extend(attachments);
+        self
+    }
+
     /// Create a user message
     pub fn user(content: impl Into<String>) -> Self {
         Self::new(ChatRole::User, content)
@@ -128,6 +146,180 @@ impl ChatMessage {
     }
 }
 
+/// Multimodal content block attached to a user message.
+/// Binary data is base64-encoded; each provider gets its own encoding.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        media_type: String, // image/png, image/jpeg, image/gif, image/webp
+        data: String,
+    },
+    Document {
+        name: String,
+        media_type: String, // application/pdf
+        data: String,
+    },
+}
+
+impl ContentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    pub fn image(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        ContentBlock::Image {
+            media_type: media_type.into(),
+            data: BASE64.encode(bytes),
+        }
+    }
+
+    pub fn document(name: impl Into<String>, media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        ContentBlock::Document {
+            name: name.into(),
+            media_type: media_type.into(),
+            data: BASE64.encode(bytes),
+        }
+    }
+
+    /// Whether the block needs a multimodal-capable provider
+    pub fn is_binary(&self) -> bool {
+        !matches!(self, ContentBlock::Text { .. })
+    }
+
+    /// Anthropic Messages API content block
+    fn to_anthropic(&self) -> serde_json::Value {
+        match self {
+            ContentBlock::Text { text } => serde_json::json!({"type": "text", "text": text}),
+            ContentBlock::Image { media_type, data } => serde_json::json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": media_type, "data": data}
+            }),
+            ContentBlock::Document {
+                name,
+                media_type,
+                data,
+            } => serde_json::json!({
+                "type": "document",
+                "title": name,
+                "source": {"type": "base64", "media_type": media_type, "data": data}
+            }),
+        }
+    }
+
+    /// OpenAI Chat Completions content part
+    fn to_openai(&self) -> serde_json::Value {
+        match self {
+            ContentBlock::Text { text } => serde_json::json!({"type": "text", "text": text}),
+            ContentBlock::Image { media_type, data } => serde_json::json!({
+                "type": "image_url",
+                "image_url": {"url": format!("data:{};base64,{}", media_type, data)}
+            }),
+            ContentBlock::Document {
+                name,
+                media_type,
+                data,
+            } => serde_json::json!({
+                "type": "file",
+                "file": {
+                    "filename": name,
+                    "file_data": format!("data:{};base64,{}", media_type, data)
+                }
+            }),
+        }
+    }
+
+    /// Gemini content part (images and PDFs are both inline data)
+    fn to_gemini(&self) -> serde_json::Value {
+        match self {
+            ContentBlock::Text { text } => serde_json::json!({"text": text}),
+            ContentBlock::Image { media_type, data }
+            | ContentBlock::Document {
+                media_type, data, ..
+            } => serde_json::json!({
+                "inlineData": {"mimeType": media_type, "data": data}
+            }),
+        }
+    }
+}
+
+/// Anthropic user content: plain string when possible, otherwise attachment blocks
+/// followed by the text block (which carries the prompt cache breakpoint if enabled)
+fn anthropic_user_content(
+    text: &str,
+    attachments: &[ContentBlock],
+    cache: bool,
+) -> serde_json::Value {
+    if attachments.is_empty() && !cache {
+        return serde_json::json!(text);
+    }
+
+    let mut blocks: Vec<serde_json::Value> =
+        attachments.iter().map(ContentBlock::to_anthropic).collect();
+    let mut text_block = serde_json::json!({"type": "text", "text": text});
+    if cache {
+        text_block["cache_control"] = serde_json::json!({"type": "ephemeral"});
+    }
+    blocks.push(text_block);
+    serde_json::json!(blocks)
+}
+
+/// OpenAI user content: plain string when there are no attachments, otherwise parts
+fn openai_user_content(text: &str, attachments: &[ContentBlock]) -> serde_json::Value {
+    if attachments.is_empty() {
+        return serde_json::json!(text);
+    }
+
+    let mut parts: Vec<serde_json::Value> =
+        attachments.iter().map(ContentBlock::to_openai).collect();
+    parts.push(serde_json::json!({"type": "text", "text": text}));
+    serde_json::json!(parts)
+}
+
+/// User content for text-only providers (DeepSeek): text attachments are inlined,
+/// images and documents are rejected
+fn text_only_user_content(
+    provider: &str,
+    text: &str,
+    attachments: &[ContentBlock],
+) -> Result<String> {
+    let mut content = String::new();
+    for block in attachments {
+        match block {
+            ContentBlock::Text { text } => {
+                content.push_str(text);
+                content.push_str("\n\n");
+            }
+            _ => {
+                return Err(anyhow!(
+                    "{} does not support image or document attachments",
+                    provider
+                ))
+            }
+        }
+    }
+    content.push_str(text);
+    Ok(content)
+}
+
+/// Reject conversations carrying images/documents for text-only providers
+fn ensure_text_only_history(provider: &str, req: &MultiTurnRequest) -> Result<()> {
+    let has_binary = req
+        .messages
+        .iter()
+        .any(|m| m.attachments.iter().any(ContentBlock::is_binary));
+    if has_binary {
+        return Err(anyhow!(
+            "{} does not support image or document attachments",
+            provider
+        ));
+    }
+    Ok(())
+}
+
 /// IM-4005: CacheTTL - Cache duration options (Anthropic-specific)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum CacheTTL {
@@ -159,6 +351,168 @@ impl Default for CacheConfig {
     }
 }
 
+/// Token usage reported by the provider (Anthropic `usage` object, including
+/// prompt cache writes/reads)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64, // Prompt cache write
+    #[serde(default)]
+    pub cache_read_input_tokens: u64, // Prompt cache read
+}
+
+/// Gemini-specific generation settings, sent as `generationConfig` and `safetySettings`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GeminiConfig {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+    pub stop_sequences: Vec<String>,
+    pub safety_settings: Vec<GeminiSafetySetting>,
+}
+
+/// One Gemini safety filter, e.g. HARM_CATEGORY_HARASSMENT / BLOCK_ONLY_HIGH
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeminiSafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+impl GeminiConfig {
+    /// Add `generationConfig` / `safetySettings` to a Gemini body (unset fields are omitted)
+    fn apply_to(&self, body: &mut serde_json::Value) {
+        let mut generation = serde_json::Map::new();
+        if let Some(v) = self.temperature {
+            generation.insert("temperature".into(), serde_json::json!(v));
+        }
+        if let Some(v) = self.top_p {
+            generation.insert("topP".into(), serde_json::json!(v));
+        }
+        if let Some(v) = self.top_k {
+            generation.insert("topK".into(), serde_json::json!(v));
+        }
+        if let Some(v) = self.max_output_tokens {
+            generation.insert("maxOutputTokens".into(), serde_json::json!(v));
+        }
+        if !self.stop_sequences.is_empty() {
+            generation.insert(
+                "stopSequences".into(),
+                serde_json::json!(self.stop_sequences),
+            );
+        }
+
+        if !generation.is_empty() {
+            body["generationConfig"] = serde_json::Value::Object(generation);
+        }
+        if !self.safety_settings.is_empty() {
+            body["safetySettings"] = serde_json::json!(self.safety_settings);
+        }
+    }
+}
+
+/// OpenAI o-series `reasoning_effort`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// Reasoning controls: extended thinking budget for Claude models, reasoning effort
+/// for OpenAI o-series models. Each is only sent to a model that can reason
+/// (`model_catalog::supports_reasoning`); other models get a plain request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct ReasoningConfig {
+    /// Anthropic extended thinking budget in tokens (minimum 1024)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// Smallest extended thinking budget Anthropic accepts
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
+/// Completion token ceiling for o-series requests (reasoning tokens count against it)
+const OPENAI_REASONING_MAX_COMPLETION_TOKENS: u32 = 25_000;
+
+impl ReasoningConfig {
+    /// Enable extended thinking; `max_tokens` is raised so the answer keeps its own budget
+    fn apply_to_anthropic(&self, body: &mut serde_json::Value) {
+        let Some(budget) = self.thinking_budget.filter(|_| body_model_reasons(body)) else {
+            return;
+        };
+        let budget = budget.max(MIN_THINKING_BUDGET);
+        let max_tokens = body["max_tokens"].as_u64().unwrap_or(4096);
+        body["max_tokens"] = serde_json::json!(max_tokens + budget as u64);
+        body["thinking"] = serde_json::json!({"type": "enabled", "budget_tokens": budget});
+    }
+
+    /// Set `reasoning_effort`; o-series models take `max_completion_tokens`, not `max_tokens`
+    fn apply_to_openai(&self, body: &mut serde_json::Value) {
+        let Some(effort) = self.reasoning_effort.filter(|_| body_model_reasons(body)) else {
+            return;
+        };
+        body["reasoning_effort"] = serde_json::json!(effort);
+        if let Some(obj) = body.as_object_mut() {
+            obj.remove("max_tokens");
+        }
+        body["max_completion_tokens"] = serde_json::json!(OPENAI_REASONING_MAX_COMPLETION_TOKENS);
+    }
+}
+
+/// Whether the model a request body is for accepts reasoning parameters
+fn body_model_reasons(body: &serde_json::Value) -> bool {
+    body["model"]
+        .as_str()
+        .is_some_and(model_catalog::supports_reasoning)
+}
+
+/// Append a reasoning/thinking chunk to the shared trace slot
+fn record_reasoning(slot: &Mutex<Option<String>>, chunk: &str) {
+    if let Ok(mut trace) = slot.lock() {
+        trace.get_or_insert_with(String::new).push_str(chunk);
+    }
+}
+
+impl TokenUsage {
+    /// Accumulate another call's usage into this total
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+
+    /// Fold in a streamed usage update. Providers report running totals (Anthropic splits
+    /// them across message_start and message_delta), so keep the largest count per field.
+    pub fn merge(&mut self, update: &TokenUsage) {
+        self.input_tokens = self.input_tokens.max(update.input_tokens);
+        self.output_tokens = self.output_tokens.max(update.output_tokens);
+        self.cache_creation_input_tokens = self
+            .cache_creation_input_tokens
+            .max(update.cache_creation_input_tokens);
+        self.cache_read_input_tokens = self
+            .cache_read_input_tokens
+            .max(update.cache_read_input_tokens);
+    }
+}
+
+/// Merge a streamed usage update into the shared slot
+fn record_usage(slot: &Mutex<Option<TokenUsage>>, update: &TokenUsage) {
+    if let Ok(mut usage) = slot.lock() {
+        usage.get_or_insert_with(TokenUsage::default).merge(update);
+    }
+}
+
 /// IM-4003: MultiTurnRequest - Full conversation request
 #[derive(Debug, Clone, Serialize)]
 pub struct MultiTurnRequest {
@@ -168,6 +522,8 @@ pub struct MultiTurnRequest {
     pub enable_caching: bool,       // IM-4003-F4: Enable provider caching
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_config: Option<CacheConfig>, // IM-4003-F5: Cache configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<ReasoningConfig>, // Extended thinking / reasoning effort
 }
 
 impl MultiTurnRequest {
@@ -179,6 +535,7 @@ impl MultiTurnRequest {
             model: model.into(),
             enable_caching: false,
             cache_config: None,
+            reasoning: None,
         }
     }
 
@@ -214,6 +571,12 @@ impl MultiTurnRequest {
         self
     }
 
+    /// Builder: Request extended thinking / reasoning effort
+    pub fn with_reasoning(mut self, reasoning: ReasoningConfig) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
     /// Builder: Disable caching
     pub fn without_caching(mut self) -> Self {
         self.enable_caching = false;
@@ -458,15 +821,11 @@ fn to_anthropic_body(req: &MultiTurnRequest) -> serde_json::Value {
         .iter()
         .filter(|m| m.role != ChatRole::System) // System is separate field in Anthropic
         .map(|m| {
-            if req.enable_caching && m.role == ChatRole::User {
+            if m.role == ChatRole::User && (req.enable_caching || !m.attachments.is_empty()) {
                 // IM-4010-B1: Add cache_control to cacheable user messages
                 serde_json::json!({
                     "role": m.role.to_provider_string("anthropic"),
-                    "content": [{
-                        "type": "text",
-                        "text": m.content,
-                        "cache_control": {"type": "ephemeral"}
-                    }]
+                    "content": anthropic_user_content(&m.content, &m.attachments, req.enable_caching)
                 })
             } else {
                 serde_json::json!({
@@ -483,6 +842,10 @@ fn to_anthropic_body(req: &MultiTurnRequest) -> serde_json::Value {
         "messages": messages
     });
 
+    if let Some(reasoning) = &req.reasoning {
+        reasoning.apply_to_anthropic(&mut body);
+    }
+
     // Add system prompt if present
     if let Some(ref system) = req.system {
         if req.enable_caching {
@@ -500,6 +863,63 @@ fn to_anthropic_body(req: &MultiTurnRequest) -> serde_json::Value {
     body
 }
 
+/// Transform a single-turn LLMRequest to an Anthropic JSON body.
+/// With a cache_config, the system prompt (preamble + manifest instructions) and the
+/// user input (shared company context) are marked as prompt cache breakpoints.
+fn to_anthropic_single_body(req: &LLMRequest, stream: bool) -> serde_json::Value {
+    let mut body = if req.cache_config.is_some() {
+        serde_json::json!({
+            "model": req.model,
+            "max_tokens": 4096,
+            "system": [{
+                "type": "text",
+                "text": req.system,
+                "cache_control": {"type": "ephemeral"}
+            }],
+            "messages": [{
+                "role": "user",
+                "content": anthropic_user_content(&req.user, &req.attachments, true)
+            }]
+        })
+    } else {
+        serde_json::json!({
+            "model": req.model,
+            "max_tokens": 4096,
+            "system": req.system,
+            "messages": [{
+                "role": "user",
+                "content": anthropic_user_content(&req.user, &req.attachments, false)
+            }]
+        })
+    };
+
+    if let Some(reasoning) = &req.reasoning {
+        reasoning.apply_to_anthropic(&mut body);
+    }
+
+    if stream {
+        body["stream"] = serde_json::json!(true);
+    }
+
+    body
+}
+
+/// Split Anthropic content blocks into (answer text, thinking trace)
+fn split_anthropic_content(content: &[AnthropicContent]) -> (String, Option<String>) {
+    let mut text = String::new();
+    let mut thinking: Option<String> = None;
+    for block in content {
+        match block.block_type.as_str() {
+            "text" => text.push_str(block.text.as_deref().unwrap_or_default()),
+            "thinking" => thinking
+                .get_or_insert_with(String::new)
+                .push_str(block.thinking.as_deref().unwrap_or_default()),
+            _ => {}
+        }
+    }
+    (text, thinking)
+}
+
 /// IM-4011: Transform MultiTurnRequest to OpenAI-compatible JSON body
 /// Used for OpenAI and DeepSeek (both use OpenAI-compatible format)
 fn to_openai_body(req: &MultiTurnRequest) -> serde_json::Value {
@@ -517,7 +937,7 @@ fn to_openai_body(req: &MultiTurnRequest) -> serde_json::Value {
     for msg in &req.messages {
         messages.push(serde_json::json!({
             "role": msg.role.to_provider_string("openai"),
-            "content": msg.content
+            "content": openai_user_content(&msg.content, &msg.attachments)
         }));
     }
 
@@ -544,9 +964,12 @@ fn to_gemini_body(req: &MultiTurnRequest) -> serde_json::Value {
         .iter()
         .filter(|m| m.role != ChatRole::System) // Handle system separately
         .map(|m| {
+            let mut parts: Vec<serde_json::Value> =
+                m.attachments.iter().map(ContentBlock::to_gemini).collect();
+            parts.push(serde_json::json!({"text": m.content}));
             serde_json::json!({
                 "role": m.role.to_provider_string("gemini"),
-                "parts": [{"text": m.content}]
+                "parts": parts
             })
         })
         .collect();
@@ -566,10 +989,38 @@ fn to_gemini_body(req: &MultiTurnRequest) -> serde_json::Value {
     body
 }
 
+/// Transform a single-turn LLMRequest to a Gemini JSON body (system prompt goes to
+/// `systemInstruction`, same as the multi-turn path)
+fn to_gemini_single_body(req: &LLMRequest) -> serde_json::Value {
+    let multi = MultiTurnRequest::new(req.model.clone())
+        .with_system(req.system.clone())
+        .with_message(
+            ChatMessage::user(req.user.clone()).with_attachments(req.attachments.clone()),
+        );
+    to_gemini_body(&multi)
+}
+
+/// Gemini endpoint URL for a model. The API key travels in the `x-goog-api-key`
+/// header, never in the query string, so it cannot leak into logs or error messages.
+fn gemini_url(model: &str, stream: bool) -> String {
+    if stream {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse",
+            model
+        )
+    } else {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            model
+        )
+    }
+}
+
 /// IM-4013: Transform MultiTurnRequest to streaming OpenAI body
 fn to_openai_stream_body(req: &MultiTurnRequest) -> serde_json::Value {
     let mut body = to_openai_body(req);
     body["stream"] = serde_json::json!(true);
+    body["stream_options"] = serde_json::json!({"include_usage": true});
     body
 }
 
@@ -580,6 +1031,18 @@ fn to_anthropic_stream_body(req: &MultiTurnRequest) -> serde_json::Value {
     body
 }
 
+/// Collapse a multi-turn request into (system, latest user message) for the mock provider
+fn flatten_for_mock(req: &MultiTurnRequest) -> (String, String) {
+    let user = req
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == ChatRole::User)
+        .map(|m| m.content.clone())
+        .unwrap_or_default();
+    (req.system.clone().unwrap_or_default(), user)
+}
+
 // ------------------------------------------------------------------
 // Request/Response Types
 // ------------------------------------------------------------------
@@ -590,13 +1053,116 @@ pub struct LLMClient {
     api_key: String,
     rate_limiters: HashMap<String, RateLimiter>,
     circuit_breakers: HashMap<String, CircuitBreaker>,
+    mock: MockProvider,                    // Offline provider for mock* models
+    response_cache: Option<ResponseCache>, // Opt-in cache for single-turn requests
+    last_cache_hit: bool,
+    last_usage: Arc<Mutex<Option<TokenUsage>>>, // Shared with in-flight streams
+    last_reasoning: Arc<Mutex<Option<String>>>, // Thinking trace of the last call
+    last_stop_reason: Arc<Mutex<Option<String>>>, // Provider stop reason of the last call
+    last_model: Arc<Mutex<Option<String>>>,     // Model the provider reported for the last call
+    gemini_config: GeminiConfig,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Default)]
 pub struct LLMRequest {
     pub system: String,
     pub user: String,
     pub model: String,
+    /// JSON Schema the response should conform to (from the phase's output_schema)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    /// Bypass the response cache for this request (per-phase opt-out); not part of the cache key
+    #[serde(skip)]
+    pub skip_cache: bool,
+    /// Anthropic prompt caching for the system prompt and user input (ignored by other providers)
+    #[serde(skip)]
+    pub cache_config: Option<CacheConfig>,
+    /// Images/documents sent ahead of the user input
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ContentBlock>,
+    /// Extended thinking / reasoning effort (part of the cache key: it changes the answer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<ReasoningConfig>,
+    /// Text within `system` that changes between otherwise identical runs (today's date);
+    /// left out of the response cache key
+    #[serde(skip)]
+    pub volatile_system: Option<String>,
+}
+
+/// Why the model stopped, normalized across providers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    EndTurn,   // Natural end ("end_turn", "stop", "STOP")
+    MaxTokens, // Output limit reached: the text is truncated
+    StopSequence,
+    ToolUse,
+    ContentFilter, // Refusal or safety block
+    Other(String),
+}
+
+impl StopReason {
+    /// Map a provider's stop/finish reason
+    pub fn from_provider(reason: &str) -> Self {
+        match reason {
+            "end_turn" | "stop" | "STOP" => Self::EndTurn,
+            "max_tokens" | "length" | "MAX_TOKENS" => Self::MaxTokens,
+            "stop_sequence" => Self::StopSequence,
+            "tool_use" | "tool_calls" | "function_call" => Self::ToolUse,
+            "refusal" | "content_filter" | "SAFETY" | "RECITATION" | "BLOCKLIST"
+            | "PROHIBITED_CONTENT" | "SPII" => Self::ContentFilter,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::EndTurn => "end_turn",
+            Self::MaxTokens => "max_tokens",
+            Self::StopSequence => "stop_sequence",
+            Self::ToolUse => "tool_use",
+            Self::ContentFilter => "content_filter",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+/// Result of a generation: the text plus how it ended and what it cost
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Generation {
+    pub text: String,
+    pub stop_reason: Option<StopReason>, // None if not reported (e.g. cache hits)
+    pub usage: Option<TokenUsage>,
+    pub model: String, // Model the provider reports having used (else the requested id)
+    pub latency_ms: u64,
+    pub cache_hit: bool,
+    pub reasoning: Option<String>, // Thinking/reasoning trace, kept separate from the text
+}
+
+impl Generation {
+    /// The output stopped at the token limit
+    pub fn is_truncated(&self) -> bool {
+        self.stop_reason == Some(StopReason::MaxTokens)
+    }
+
+    /// Stitch a continuation onto this (truncated) generation
+    pub fn append(&mut self, continuation: Generation) {
+        self.text.push_str(&continuation.text);
+        self.stop_reason = continuation.stop_reason;
+        if let Some(usage) = continuation.usage {
+            self.usage
+                .get_or_insert_with(TokenUsage::default)
+                .add(&usage);
+        }
+        self.model = continuation.model;
+        self.latency_ms += continuation.latency_ms;
+        self.cache_hit = false;
+        if let Some(reasoning) = continuation.reasoning {
+            self.reasoning
+                .get_or_insert_with(String::new)
+                .push_str(&reasoning);
+        }
+    }
 }
 
 // ------------------------------------------------------------------
@@ -607,37 +1173,41 @@ pub struct LLMRequest {
 #[derive(Debug, Deserialize)]
 struct AnthropicResponse {
     content: Vec<AnthropicContent>,
-}
-
-#[derive(Debug, Deserialize)]
-struct AnthropicContent {
-    text: String,
-}
-
-// Anthropic Streaming
-#[derive(Debug, Deserialize)]
-struct AnthropicStreamEvent {
-    #[serde(rename = "type")]
-    event_type: String,
     #[serde(default)]
-    delta: Option<AnthropicDelta>,
+    usage: Option<TokenUsage>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct AnthropicDelta {
+struct AnthropicContent {
+    #[serde(rename = "type", default)]
+    block_type: String, // "text" or "thinking"
     #[serde(default)]
     text: Option<String>,
+    #[serde(default)]
+    thinking: Option<String>,
 }
 
 // Google (Gemini)
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiResponse {
     candidates: Vec<GeminiCandidate>,
+    #[serde(default)]
+    usage_metadata: Option<GeminiUsage>,
+    #[serde(default)]
+    model_version: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiCandidate {
     content: GeminiContent,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -654,11 +1224,17 @@ struct GeminiPart {
 #[derive(Debug, Deserialize)]
 struct DeepSeekResponse {
     choices: Vec<DeepSeekChoice>,
+    #[serde(default)]
+    usage: Option<OpenAIUsage>,
+    #[serde(default)]
+    model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct DeepSeekChoice {
     message: DeepSeekMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -671,58 +1247,30 @@ struct DeepSeekMessage {
     reasoning_content: Option<String>,
 }
 
-// DeepSeek Streaming
-#[derive(Debug, Deserialize)]
-struct DeepSeekStreamChunk {
-    choices: Vec<DeepSeekStreamChoice>,
-}
-
-#[derive(Debug, Deserialize)]
-struct DeepSeekStreamChoice {
-    delta: DeepSeekStreamDelta,
-}
-
-#[derive(Debug, Deserialize)]
-struct DeepSeekStreamDelta {
-    /// The final answer content chunk
-    #[serde(default)]
-    content: Option<String>,
-    /// Chain of Thought reasoning chunk (R1 reasoning models only)
-    #[serde(default)]
-    reasoning_content: Option<String>,
-}
-
 // OpenAI (GPT models) - OpenAI-compatible format (same as DeepSeek)
 #[derive(Debug, Deserialize)]
 struct OpenAIResponse {
     choices: Vec<OpenAIChoice>,
+    #[serde(default)]
+    usage: Option<OpenAIUsage>,
+    #[serde(default)]
+    model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIChoice {
     message: OpenAIMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIMessage {
-    content: String,
-}
-
-// OpenAI Streaming
-#[derive(Debug, Deserialize)]
-struct OpenAIStreamChunk {
-    choices: Vec<OpenAIStreamChoice>,
-}
-
-#[derive(Debug, Deserialize)]
-struct OpenAIStreamChoice {
-    delta: OpenAIStreamDelta,
-}
-
-#[derive(Debug, Deserialize)]
-struct OpenAIStreamDelta {
     #[serde(default)]
-    content: Option<String>,
+    content: String,
+    /// Reasoning summary from OpenAI-compatible reasoning models
+    #[serde(default, alias = "reasoning")]
+    reasoning_content: Option<String>,
 }
 
 // ------------------------------------------------------------------
@@ -739,6 +1287,7 @@ impl LLMClient {
         rate_limiters.insert("google".to_string(), RateLimiter::new(60.0)); // 60 RPM
         rate_limiters.insert("deepseek".to_string(), RateLimiter::new(100.0)); // 100 RPM
         rate_limiters.insert("openai".to_string(), RateLimiter::new(60.0)); // 60 RPM (Tier 1)
+        rate_limiters.insert("mock".to_string(), RateLimiter::new(1000.0)); // Offline, effectively unlimited
 
         // Configure circuit breakers per provider
         circuit_breakers.insert(
@@ -757,34 +1306,283 @@ impl LLMClient {
             "openai".to_string(),
             CircuitBreaker::new(5, 2, Duration::from_secs(60)),
         );
+        circuit_breakers.insert(
+            "mock".to_string(),
+            CircuitBreaker::new(5, 2, Duration::from_secs(60)),
+        );
 
         Self {
             client: Client::new(),
             api_key,
             rate_limiters,
             circuit_breakers,
+            mock: MockProvider::default(),
+            response_cache: None,
+            last_cache_hit: false,
+            last_usage: Arc::new(Mutex::new(None)),
+            last_reasoning: Arc::new(Mutex::new(None)),
+            last_stop_reason: Arc::new(Mutex::new(None)),
+            last_model: Arc::new(Mutex::new(None)),
+            gemini_config: GeminiConfig::default(),
         }
     }
 
-    /// Detect provider from model name
-    fn detect_provider(&self, model: &str) -> Result<String, LLMError> {
-        if model.starts_with("claude") {
-            Ok("anthropic".to_string())
-        } else if model.starts_with("gemini") {
-            Ok("google".to_string())
-        } else if model.starts_with("deepseek") {
-            Ok("deepseek".to_string())
-        } else if model.starts_with("gpt") || model.starts_with("o1") || model.starts_with("o3") {
-            Ok("openai".to_string())
-        } else {
-            Err(LLMError::UnsupportedModel(model.to_string()))
+    /// Builder: Configure the offline mock provider (scripts, latency, injected failures)
+    pub fn with_mock_config(mut self, config: MockConfig) -> Self {
+        self.mock = MockProvider::new(config);
+        self
+    }
+
+    /// Builder: Gemini generationConfig and safety settings for all Gemini calls
+    pub fn with_gemini_config(mut self, config: GeminiConfig) -> Self {
+        self.gemini_config = config;
+        self
+    }
+
+    /// Builder: Enable the response cache for single-turn generate/generate_stream calls
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Copy for a call made concurrently with this client's: same configuration, mock
+    /// sequence and response cache, but its own last-call state (usage, reasoning, ...)
+    pub fn fork(&self) -> Self {
+        Self {
+            last_cache_hit: false,
+            last_usage: Arc::new(Mutex::new(None)),
+            last_reasoning: Arc::new(Mutex::new(None)),
+            last_stop_reason: Arc::new(Mutex::new(None)),
+            last_model: Arc::new(Mutex::new(None)),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the most recent generate/generate_stream call was served from the response cache
+    pub fn last_cache_hit(&self) -> bool {
+        self.last_cache_hit
+    }
+
+    /// Token usage of the most recent single-turn call, if the provider reported it.
+    /// For streams this is complete once the stream has been drained.
+    pub fn last_usage(&self) -> Option<TokenUsage> {
+        self.last_usage.lock().ok().and_then(|u| u.clone())
+    }
+
+    /// Reasoning trace (Anthropic thinking blocks, OpenAI-compatible `reasoning_content`)
+    /// of the most recent call. For streams this is complete once the stream has been drained.
+    pub fn last_reasoning(&self) -> Option<String> {
+        self.last_reasoning.lock().ok().and_then(|r| r.clone())
+    }
+
+    /// Why the most recent call stopped. For streams this is set once the stream has been drained.
+    pub fn last_stop_reason(&self) -> Option<StopReason> {
+        self.last_stop_reason
+            .lock()
+            .ok()
+            .and_then(|r| r.as_deref().map(StopReason::from_provider))
+    }
+
+    /// `Generation` for the call that just completed, from the last-call slots.
+    /// For streams, call this once the stream has been drained.
+    pub fn last_generation(
+        &self,
+        text: String,
+        requested_model: &str,
+        started: Instant,
+    ) -> Generation {
+        let model = self.last_model.lock().ok().and_then(|m| m.clone());
+        Generation {
+            text,
+            stop_reason: self.last_stop_reason(),
+            usage: self.last_usage(),
+            model: model.unwrap_or_else(|| requested_model.to_string()),
+            latency_ms: started.elapsed().as_millis() as u64,
+            cache_hit: self.last_cache_hit,
+            reasoning: self.last_reasoning(),
+        }
+    }
+
+    /// Record the stop reason and served model reported by a provider
+    fn record_call(&self, stop_reason: Option<&str>, model: Option<&str>) {
+        if let (Some(reason), Ok(mut slot)) = (stop_reason, self.last_stop_reason.lock()) {
+            *slot = Some(reason.to_string());
+        }
+        if let (Some(model), Ok(mut slot)) = (model, self.last_model.lock()) {
+            *slot = Some(model.to_string());
+        }
+    }
+
+    fn record_gemini_response(&self, res: &GeminiResponse) {
+        if let (Some(usage), Ok(mut slot)) = (&res.usage_metadata, self.last_usage.lock()) {
+            *slot = Some(usage.into());
+        }
+        self.record_call(
+            res.candidates
+                .first()
+                .and_then(|c| c.finish_reason.as_deref()),
+            res.model_version.as_deref(),
+        );
+    }
+
+    fn record_openai_compatible(
+        &self,
+        finish_reason: Option<&str>,
+        usage: Option<&OpenAIUsage>,
+        model: Option<&str>,
+    ) {
+        if let (Some(usage), Ok(mut slot)) = (usage, self.last_usage.lock()) {
+            *slot = Some(usage.into());
+        }
+        self.record_call(finish_reason, model);
+    }
+
+    /// Span for one provider call (`latency_ms` is recorded by `finish_request_span`)
+    fn request_span(provider: &str, model: &str, streaming: bool) -> tracing::Span {
+        tracing::info_span!(
+            "provider_request",
+            provider,
+            model,
+            streaming,
+            latency_ms = tracing::field::Empty
+        )
+    }
+
+    /// Record the call's latency on its span and log the outcome within it.
+    /// For streams this is the time until the provider started responding.
+    fn finish_request_span<T>(span: &tracing::Span, started: Instant, result: &Result<T>) {
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        span.in_scope(|| match result {
+            Ok(_) => tracing::info!("provider responded"),
+            Err(e) => tracing::warn!("provider request failed: {}", e),
+        });
+    }
+
+    fn reset_last_call(&self) {
+        if let Ok(mut usage) = self.last_usage.lock() {
+            *usage = None;
+        }
+        if let Ok(mut reasoning) = self.last_reasoning.lock() {
+            *reasoning = None;
+        }
+        if let Ok(mut stop_reason) = self.last_stop_reason.lock() {
+            *stop_reason = None;
+        }
+        if let Ok(mut model) = self.last_model.lock() {
+            *model = None;
         }
     }
 
+    /// Turn decoded provider events into the text token stream, recording reasoning,
+    /// usage and stop reason in the last-call slots. With `inline_reasoning` the reasoning
+    /// is streamed as text instead, separated from the answer by a rule.
+    fn token_stream(
+        &self,
+        events: impl Stream<Item = Result<StreamEvent, LLMError>> + Send + 'static,
+        inline_reasoning: bool,
+    ) -> Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>> {
+        let usage_slot = self.last_usage.clone();
+        let reasoning_slot = self.last_reasoning.clone();
+        let stop_slot = self.last_stop_reason.clone();
+        let model_slot = self.last_model.clone();
+        let mut in_reasoning = false;
+
+        let tokens = events.filter_map(move |event| {
+            let token = match event {
+                Ok(StreamEvent::Text(text)) if text.is_empty() => None,
+                Ok(StreamEvent::Text(text)) if in_reasoning => {
+                    in_reasoning = false;
+                    Some(Ok(format!("\n\n---\n\n{}", text)))
+                }
+                Ok(StreamEvent::Text(text)) => Some(Ok(text)),
+                Ok(StreamEvent::Reasoning(chunk)) if inline_reasoning => {
+                    in_reasoning = true;
+                    Some(Ok(chunk))
+                }
+                Ok(StreamEvent::Reasoning(chunk)) => {
+                    record_reasoning(&reasoning_slot, &chunk);
+                    None
+                }
+                Ok(StreamEvent::Usage(update)) => {
+                    record_usage(&usage_slot, &update);
+                    None
+                }
+                Ok(StreamEvent::Stop(reason)) => {
+                    if let Ok(mut stop_reason) = stop_slot.lock() {
+                        *stop_reason = Some(reason);
+                    }
+                    None
+                }
+                Ok(StreamEvent::Model(model)) => {
+                    if let Ok(mut served) = model_slot.lock() {
+                        *served = Some(model);
+                    }
+                    None
+                }
+                Ok(StreamEvent::Done) => None,
+                Err(e) => Some(Err(e)),
+            };
+            futures::future::ready(token)
+        });
+
+        Box::pin(tokens)
+    }
+
+    /// Cache key for a request, or None if it should bypass the cache
+    /// (cache disabled, per-request opt-out, or mock models whose scripts must stay observable)
+    fn response_cache_key(&self, req: &LLMRequest) -> Option<String> {
+        if self.response_cache.is_none() || req.skip_cache || Self::is_mock_model(&req.model) {
+            return None;
+        }
+        // Gemini generation settings change the answer, but only for Gemini models
+        let settings = (model_catalog::provider_for(&req.model) == Some("google"))
+            .then_some(&self.gemini_config);
+        Some(ResponseCache::key_for(req, settings))
+    }
+
+    /// Look up a cached response, treating cache errors as misses. A hit becomes the
+    /// last call, so its stop reason is reported like a provider's.
+    fn cached_response(&self, key: Option<&str>) -> Option<String> {
+        let (cache, key) = (self.response_cache.as_ref()?, key?);
+        match cache.get(key) {
+            Ok(hit) => hit.map(|cached| {
+                self.record_call(cached.stop_reason.as_deref(), None);
+                cached.text
+            }),
+            Err(e) => {
+                tracing::warn!("Response cache lookup failed, calling provider: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Whether a model is served by the built-in mock provider (no API key required)
+    pub fn is_mock_model(model: &str) -> bool {
+        model_catalog::provider_for(model) == Some("mock")
+    }
+
+    /// Detect provider from model name (prefix rules live in the model catalog)
+    fn detect_provider(&self, model: &str) -> Result<String, LLMError> {
+        model_catalog::provider_for(model)
+            .map(|provider| provider.to_string())
+            .ok_or_else(|| LLMError::UnsupportedModel(model.to_string()))
+    }
+
     /// Generate text with full rate limiting and circuit breaker protection
-    pub async fn generate(&mut self, req: LLMRequest) -> Result<String> {
+    pub async fn generate(&mut self, req: LLMRequest) -> Result<Generation> {
+        let started = Instant::now();
         let provider_name = self.detect_provider(&req.model)?;
 
+        // Serve identical requests from the response cache (no rate limit or provider call)
+        self.last_cache_hit = false;
+        self.reset_last_call();
+        let cache_key = self.response_cache_key(&req);
+        if let Some(cached) = self.cached_response(cache_key.as_deref()) {
+            self.last_cache_hit = true;
+            return Ok(self.last_generation(cached, &req.model, started));
+        }
+        let model = req.model.clone();
+
         // Apply rate limiting BEFORE making request
         if let Some(limiter) = self.rate_limiters.get_mut(&provider_name) {
             match limiter.try_acquire() {
@@ -793,9 +1591,10 @@ impl LLMClient {
                 }
                 Err(wait_duration) => {
                     // Rate limited - wait and retry
-                    eprintln!(
+                    tracing::warn!(
                         "Rate limited by {} - waiting {:?}",
-                        provider_name, wait_duration
+                        provider_name,
+                        wait_duration
                     );
                     tokio::time::sleep(wait_duration).await;
                     limiter
@@ -817,20 +1616,33 @@ impl LLMClient {
         }
 
         // Execute the actual async provider call
-        let result = if req.model.starts_with("claude") {
-            self.generate_anthropic(req).await
-        } else if req.model.starts_with("gemini") {
-            self.generate_gemini(req).await
-        } else if req.model.starts_with("deepseek") {
-            self.generate_deepseek(req).await
-        } else if req.model.starts_with("gpt")
-            || req.model.starts_with("o1")
-            || req.model.starts_with("o3")
-        {
-            self.generate_openai(req).await
-        } else {
-            Err(anyhow!("Unsupported model: {}", req.model))
-        };
+        let span = Self::request_span(&provider_name, &model, false);
+        let call_started = Instant::now();
+        let result = async {
+            match provider_name.as_str() {
+                "anthropic" => self.generate_anthropic(req).await,
+                "google" => self.generate_gemini(req).await,
+                "deepseek" => self.generate_deepseek(req).await,
+                "openai" => self.generate_openai(req).await,
+                "mock" => {
+                    let (text, stop_reason) = self
+                        .mock
+                        .generate(
+                            &req.system,
+                            &req.user,
+                            &req.model,
+                            req.response_schema.as_ref(),
+                        )
+                        .await?;
+                    self.record_call(Some(stop_reason), None);
+                    Ok(text)
+                }
+                _ => Err(anyhow!("Unsupported model: {}", req.model)),
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        Self::finish_request_span(&span, call_started, &result);
 
         // IM-3042: Record outcome in circuit breaker after async call completes
         if let Some(breaker) = self.circuit_breakers.get_mut(&provider_name) {
@@ -840,7 +1652,18 @@ impl LLMClient {
             }
         }
 
-        result
+        // Truncated output is not cached: a replay would hide the max_tokens stop
+        let truncated = self.last_stop_reason() == Some(StopReason::MaxTokens);
+        if let (Ok(text), Some(cache), Some(key), false) =
+            (&result, &self.response_cache, &cache_key, truncated)
+        {
+            let stop_reason = self.last_stop_reason.lock().ok().and_then(|r| r.clone());
+            if let Err(e) = cache.put(key, &model, text, stop_reason.as_deref()) {
+                tracing::warn!("Failed to store response in cache: {}", e);
+            }
+        }
+
+        result.map(|text| self.last_generation(text, &model, started))
     }
 
     /// Generate text with streaming response (tokens arrive incrementally)
@@ -851,6 +1674,16 @@ impl LLMClient {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         let provider_name = self.detect_provider(&request.model)?;
 
+        // Cache hits are replayed as a single chunk
+        self.last_cache_hit = false;
+        self.reset_last_call();
+        let cache_key = self.response_cache_key(&request);
+        if let Some(cached) = self.cached_response(cache_key.as_deref()) {
+            self.last_cache_hit = true;
+            return Ok(Box::pin(futures::stream::once(async move { Ok(cached) })));
+        }
+        let model = request.model.clone();
+
         // Apply rate limiting before streaming
         if let Some(limiter) = self.rate_limiters.get_mut(&provider_name) {
             match limiter.try_acquire() {
@@ -865,23 +1698,62 @@ impl LLMClient {
         }
 
         // Route to provider-specific streaming
-        if request.model.starts_with("claude") {
-            self.generate_anthropic_stream(request).await
-        } else if request.model.starts_with("gemini") {
-            self.generate_gemini_stream(request).await
-        } else if request.model.starts_with("deepseek") {
-            self.generate_deepseek_stream(request).await
-        } else if request.model.starts_with("gpt")
-            || request.model.starts_with("o1")
-            || request.model.starts_with("o3")
-        {
-            self.generate_openai_stream(request).await
-        } else {
-            Err(anyhow!(
-                "Unsupported model for streaming: {}",
-                request.model
-            ))
+        let span = Self::request_span(&provider_name, &model, true);
+        let call_started = Instant::now();
+        let stream = async {
+            match provider_name.as_str() {
+                "anthropic" => self.generate_anthropic_stream(request).await,
+                "google" => self.generate_gemini_stream(request).await,
+                "deepseek" => self.generate_deepseek_stream(request).await,
+                "openai" => self.generate_openai_stream(request).await,
+                "mock" => {
+                    let (stream, stop_reason) = self
+                        .mock
+                        .generate_stream(
+                            &request.system,
+                            &request.user,
+                            &request.model,
+                            request.response_schema.as_ref(),
+                        )
+                        .await?;
+                    self.record_call(Some(stop_reason), None);
+                    Ok(stream)
+                }
+                _ => Err(anyhow!(
+                    "Unsupported model for streaming: {}",
+                    request.model
+                )),
+            }
         }
+        .instrument(span.clone())
+        .await;
+        Self::finish_request_span(&span, call_started, &stream);
+        let stream = stream?;
+
+        // On a miss, store the full text once the stream completes without error or truncation
+        let (Some(cache), Some(key)) = (self.response_cache.clone(), cache_key) else {
+            return Ok(stream);
+        };
+        let stop_slot = self.last_stop_reason.clone();
+        Ok(Box::pin(async_stream::stream! {
+            let mut stream = stream;
+            let mut full_text = String::new();
+            let mut failed = false;
+            while let Some(chunk) = stream.next().await {
+                match &chunk {
+                    Ok(token) => full_text.push_str(token),
+                    Err(_) => failed = true,
+                }
+                yield chunk;
+            }
+            let stop_reason = stop_slot.lock().ok().and_then(|r| r.clone());
+            let truncated = stop_reason.as_deref().map(StopReason::from_provider) == Some(StopReason::MaxTokens);
+            if !failed && !truncated && !full_text.is_empty() {
+                if let Err(e) = cache.put(&key, &model, &full_text, stop_reason.as_deref()) {
+                    tracing::warn!("Failed to store streamed response in cache: {}", e);
+                }
+            }
+        }))
     }
 
     // ------------------------------------------------------------------
@@ -890,9 +1762,11 @@ impl LLMClient {
 
     /// IM-4020: Generate text with multi-turn conversation support
     /// Supports full conversation history with provider-specific optimizations
-    pub async fn generate_multi_turn(&mut self, req: MultiTurnRequest) -> Result<String> {
+    pub async fn generate_multi_turn(&mut self, req: MultiTurnRequest) -> Result<Generation> {
+        let started = Instant::now();
         // Validate request
         req.validate().map_err(|e| anyhow!("{}", e))?;
+        self.reset_last_call();
 
         let provider_name = self.detect_provider(&req.model)?;
 
@@ -901,9 +1775,10 @@ impl LLMClient {
             match limiter.try_acquire() {
                 Ok(()) => {}
                 Err(wait_duration) => {
-                    eprintln!(
+                    tracing::warn!(
                         "Rate limited by {} - waiting {:?}",
-                        provider_name, wait_duration
+                        provider_name,
+                        wait_duration
                     );
                     tokio::time::sleep(wait_duration).await;
                     limiter
@@ -924,13 +1799,27 @@ impl LLMClient {
         }
 
         // IM-4020-B1: Route to provider-specific implementation
-        let result = match provider_name.as_str() {
-            "anthropic" => self.generate_multi_anthropic(&req).await,
-            "google" => self.generate_multi_gemini(&req).await,
-            "deepseek" => self.generate_multi_deepseek(&req).await,
-            "openai" => self.generate_multi_openai(&req).await,
-            _ => Err(anyhow!("Unsupported provider: {}", provider_name)),
-        };
+        let span = Self::request_span(&provider_name, &req.model, false);
+        let call_started = Instant::now();
+        let result = async {
+            match provider_name.as_str() {
+                "anthropic" => self.generate_multi_anthropic(&req).await,
+                "google" => self.generate_multi_gemini(&req).await,
+                "deepseek" => self.generate_multi_deepseek(&req).await,
+                "openai" => self.generate_multi_openai(&req).await,
+                "mock" => {
+                    let (system, user) = flatten_for_mock(&req);
+                    let (text, stop_reason) =
+                        self.mock.generate(&system, &user, &req.model, None).await?;
+                    self.record_call(Some(stop_reason), None);
+                    Ok(text)
+                }
+                _ => Err(anyhow!("Unsupported provider: {}", provider_name)),
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        Self::finish_request_span(&span, call_started, &result);
 
         // Record circuit breaker outcome
         if let Some(breaker) = self.circuit_breakers.get_mut(&provider_name) {
@@ -940,7 +1829,7 @@ impl LLMClient {
             }
         }
 
-        result
+        result.map(|text| self.last_generation(text, &req.model, started))
     }
 
     /// IM-4021: Anthropic multi-turn with explicit caching support
@@ -980,24 +1869,33 @@ impl LLMClient {
         }
 
         let anthropic_res: AnthropicResponse = res.json().await?;
-        anthropic_res
-            .content
-            .first()
-            .map(|c| c.text.clone())
-            .ok_or_else(|| anyhow!("No content in Anthropic response"))
+        if let Ok(mut usage) = self.last_usage.lock() {
+            *usage = anthropic_res.usage.clone();
+        }
+        self.record_call(
+            anthropic_res.stop_reason.as_deref(),
+            anthropic_res.model.as_deref(),
+        );
+        let (text, thinking) = split_anthropic_content(&anthropic_res.content);
+        if let Ok(mut reasoning) = self.last_reasoning.lock() {
+            *reasoning = thinking;
+        }
+        if text.is_empty() {
+            return Err(anyhow!("No content in Anthropic response"));
+        }
+        Ok(text)
     }
 
     /// IM-4022: Gemini multi-turn with proper "model" role handling
     async fn generate_multi_gemini(&self, req: &MultiTurnRequest) -> Result<String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            req.model, self.api_key
-        );
-        let body = to_gemini_body(req);
+        let url = gemini_url(&req.model, false);
+        let mut body = to_gemini_body(req);
+        self.gemini_config.apply_to(&mut body);
 
         let res = self
             .client
             .post(&url)
+            .header("x-goog-api-key", &self.api_key)
             .header("content-type", "application/json")
             .json(&body)
             .send()
@@ -1008,6 +1906,7 @@ impl LLMClient {
         }
 
         let gemini_res: GeminiResponse = res.json().await?;
+        self.record_gemini_response(&gemini_res);
         gemini_res
             .candidates
             .first()
@@ -1019,6 +1918,7 @@ impl LLMClient {
     /// IM-4023: DeepSeek multi-turn (OpenAI-compatible with R1 reasoning support)
     async fn generate_multi_deepseek(&self, req: &MultiTurnRequest) -> Result<String> {
         let url = "https://api.deepseek.com/chat/completions";
+        ensure_text_only_history("DeepSeek", req)?;
         let body = to_openai_body(req);
 
         // Check if this is a reasoning model (R1)
@@ -1038,6 +1938,14 @@ impl LLMClient {
         }
 
         let deepseek_res: DeepSeekResponse = res.json().await?;
+        self.record_openai_compatible(
+            deepseek_res
+                .choices
+                .first()
+                .and_then(|c| c.finish_reason.as_deref()),
+            deepseek_res.usage.as_ref(),
+            deepseek_res.model.as_deref(),
+        );
         let message = deepseek_res
             .choices
             .first()
@@ -1076,7 +1984,11 @@ impl LLMClient {
     /// IM-4024: OpenAI multi-turn (automatic caching for prompts >1024 tokens)
     async fn generate_multi_openai(&self, req: &MultiTurnRequest) -> Result<String> {
         let url = "https://api.openai.com/v1/chat/completions";
-        let body = to_openai_body(req);
+        let mut body = to_openai_body(req);
+        // reasoning_effort is OpenAI-only (DeepSeek shares the body builder)
+        if let Some(reasoning) = &req.reasoning {
+            reasoning.apply_to_openai(&mut body);
+        }
 
         let res = self
             .client
@@ -1100,11 +2012,23 @@ impl LLMClient {
         }
 
         let openai_res: OpenAIResponse = res.json().await?;
-        openai_res
+        self.record_openai_compatible(
+            openai_res
+                .choices
+                .first()
+                .and_then(|c| c.finish_reason.as_deref()),
+            openai_res.usage.as_ref(),
+            openai_res.model.as_deref(),
+        );
+        let message = openai_res
             .choices
             .first()
-            .map(|c| c.message.content.clone())
-            .ok_or_else(|| anyhow!("No content in OpenAI response"))
+            .map(|c| &c.message)
+            .ok_or_else(|| anyhow!("No content in OpenAI response"))?;
+        if let Ok(mut reasoning) = self.last_reasoning.lock() {
+            *reasoning = message.reasoning_content.clone();
+        }
+        Ok(message.content.clone())
     }
 
     /// IM-4030: Multi-turn streaming with conversation history
@@ -1114,6 +2038,7 @@ impl LLMClient {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         // Validate request
         req.validate().map_err(|e| anyhow!("{}", e))?;
+        self.reset_last_call();
 
         let provider_name = self.detect_provider(&req.model)?;
 
@@ -1131,16 +2056,33 @@ impl LLMClient {
         }
 
         // Route to provider-specific streaming
-        match provider_name.as_str() {
-            "anthropic" => self.stream_multi_anthropic(&req).await,
-            "google" => self.stream_multi_gemini(&req).await,
-            "deepseek" => self.stream_multi_deepseek(&req).await,
-            "openai" => self.stream_multi_openai(&req).await,
-            _ => Err(anyhow!(
-                "Unsupported provider for streaming: {}",
-                provider_name
-            )),
+        let span = Self::request_span(&provider_name, &req.model, true);
+        let call_started = Instant::now();
+        let stream = async {
+            match provider_name.as_str() {
+                "anthropic" => self.stream_multi_anthropic(&req).await,
+                "google" => self.stream_multi_gemini(&req).await,
+                "deepseek" => self.stream_multi_deepseek(&req).await,
+                "openai" => self.stream_multi_openai(&req).await,
+                "mock" => {
+                    let (system, user) = flatten_for_mock(&req);
+                    let (stream, stop_reason) = self
+                        .mock
+                        .generate_stream(&system, &user, &req.model, None)
+                        .await?;
+                    self.record_call(Some(stop_reason), None);
+                    Ok(stream)
+                }
+                _ => Err(anyhow!(
+                    "Unsupported provider for streaming: {}",
+                    provider_name
+                )),
+            }
         }
+        .instrument(span.clone())
+        .await;
+        Self::finish_request_span(&span, call_started, &stream);
+        stream
     }
 
     /// IM-4031: Anthropic multi-turn streaming with caching
@@ -1177,37 +2119,8 @@ impl LLMClient {
             return Err(anyhow!("Anthropic API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-                    for line in text.lines() {
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if json_str == "[DONE]" {
-                                return None;
-                            }
-                            if let Ok(event) =
-                                serde_json::from_str::<AnthropicStreamEvent>(json_str)
-                            {
-                                if event.event_type == "content_block_delta" {
-                                    if let Some(delta) = event.delta {
-                                        if let Some(text) = delta.text {
-                                            return Some(Ok(text));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::Anthropic);
+        Ok(self.token_stream(events, false))
     }
 
     /// IM-4032: Gemini multi-turn streaming
@@ -1215,15 +2128,14 @@ impl LLMClient {
         &self,
         req: &MultiTurnRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?key={}&alt=sse",
-            req.model, self.api_key
-        );
-        let body = to_gemini_body(req);
+        let url = gemini_url(&req.model, true);
+        let mut body = to_gemini_body(req);
+        self.gemini_config.apply_to(&mut body);
 
         let res = self
             .client
             .post(&url)
+            .header("x-goog-api-key", &self.api_key)
             .header("content-type", "application/json")
             .json(&body)
             .send()
@@ -1236,42 +2148,8 @@ impl LLMClient {
             return Err(anyhow!("Gemini API Error ({}): {}", status, error_text));
         }
 
-        let stream = res.bytes_stream();
-
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-                    for line in text.lines() {
-                        let trimmed = line.trim();
-                        if trimmed.is_empty() || trimmed == "[" || trimmed == "]" || trimmed == ","
-                        {
-                            continue;
-                        }
-
-                        let json_str = if let Some(data) = trimmed.strip_prefix("data: ") {
-                            data
-                        } else if trimmed.starts_with('{') {
-                            trimmed
-                        } else {
-                            continue;
-                        };
-
-                        if let Ok(response) = serde_json::from_str::<GeminiResponse>(json_str) {
-                            if let Some(candidate) = response.candidates.first() {
-                                if let Some(part) = candidate.content.parts.first() {
-                                    return Some(Ok(part.text.clone()));
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::Gemini);
+        Ok(self.token_stream(events, false))
     }
 
     /// IM-4033: DeepSeek multi-turn streaming
@@ -1280,6 +2158,7 @@ impl LLMClient {
         req: &MultiTurnRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         let url = "https://api.deepseek.com/chat/completions";
+        ensure_text_only_history("DeepSeek", req)?;
         let body = to_openai_stream_body(req);
 
         let is_reasoning_model = req.model.contains("reasoner");
@@ -1298,69 +2177,9 @@ impl LLMClient {
             return Err(anyhow!("DeepSeek API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-        let started_content = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-        let started_content_clone = started_content.clone();
-
-        let token_stream = stream.filter_map(move |chunk_result| {
-            let started_content = started_content_clone.clone();
-            let is_r1 = is_reasoning_model;
-
-            async move {
-                match chunk_result {
-                    Ok(chunk) => {
-                        let text = String::from_utf8_lossy(&chunk);
-                        let mut result_tokens = Vec::new();
-
-                        for line in text.lines() {
-                            if let Some(json_str) = line.strip_prefix("data: ") {
-                                if json_str == "[DONE]" {
-                                    continue;
-                                }
-                                if let Ok(chunk_data) =
-                                    serde_json::from_str::<DeepSeekStreamChunk>(json_str)
-                                {
-                                    if let Some(choice) = chunk_data.choices.first() {
-                                        if is_r1 {
-                                            if let Some(reasoning) = &choice.delta.reasoning_content
-                                            {
-                                                if !reasoning.is_empty() {
-                                                    result_tokens.push(reasoning.clone());
-                                                }
-                                            }
-                                        }
-                                        if let Some(content) = &choice.delta.content {
-                                            if !content.is_empty() {
-                                                if is_r1
-                                                    && !started_content
-                                                        .load(std::sync::atomic::Ordering::Relaxed)
-                                                {
-                                                    started_content.store(
-                                                        true,
-                                                        std::sync::atomic::Ordering::Relaxed,
-                                                    );
-                                                    result_tokens.push("\n\n---\n\n".to_string());
-                                                }
-                                                result_tokens.push(content.clone());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        if result_tokens.is_empty() {
-                            None
-                        } else {
-                            Some(Ok(result_tokens.join("")))
-                        }
-                    }
-                    Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-                }
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::OpenAI);
+        // R1 reasoning is streamed inline, ahead of the answer
+        Ok(self.token_stream(events, is_reasoning_model))
     }
 
     /// IM-4034: OpenAI multi-turn streaming
@@ -1369,7 +2188,11 @@ impl LLMClient {
         req: &MultiTurnRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         let url = "https://api.openai.com/v1/chat/completions";
-        let body = to_openai_stream_body(req);
+        let mut body = to_openai_stream_body(req);
+        // reasoning_effort is OpenAI-only (DeepSeek shares the body builder)
+        if let Some(reasoning) = &req.reasoning {
+            reasoning.apply_to_openai(&mut body);
+        }
 
         let res = self
             .client
@@ -1385,35 +2208,8 @@ impl LLMClient {
             return Err(anyhow!("OpenAI API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-                    for line in text.lines() {
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if json_str == "[DONE]" {
-                                return None;
-                            }
-                            if let Ok(chunk_data) =
-                                serde_json::from_str::<OpenAIStreamChunk>(json_str)
-                            {
-                                if let Some(choice) = chunk_data.choices.first() {
-                                    if let Some(content) = &choice.delta.content {
-                                        return Some(Ok(content.clone()));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::OpenAI);
+        Ok(self.token_stream(events, false))
     }
 
     // ------------------------------------------------------------------
@@ -1429,8 +2225,8 @@ impl LLMClient {
         } else {
             &self.api_key
         };
-        println!(
-            "[DEBUG] Anthropic request with key prefix: {}... (len={})",
+        tracing::debug!(
+            "Anthropic request with key prefix: {}... (len={})",
             key_prefix,
             self.api_key.len()
         );
@@ -1440,27 +2236,21 @@ impl LLMClient {
             return Err(anyhow!("Invalid Anthropic API key format. Key should start with 'sk-ant-'. Got prefix: '{}'", key_prefix));
         }
 
-        let body = serde_json::json!({
-            "model": req.model,
-            "max_tokens": 4096,
-            "system": req.system,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": req.user
-                }
-            ]
-        });
+        let body = to_anthropic_single_body(&req, false);
 
-        let res = self
+        let mut request_builder = self
             .client
             .post(url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
+            .header("content-type", "application/json");
+
+        if let Some(config) = &req.cache_config {
+            request_builder =
+                request_builder.header("anthropic-beta", config.ttl.to_anthropic_header());
+        }
+
+        let res = request_builder.json(&body).send().await?;
 
         if !res.status().is_success() {
             let status = res.status();
@@ -1473,30 +2263,33 @@ impl LLMClient {
 
         let anthropic_res: AnthropicResponse = res.json().await?;
 
-        anthropic_res
-            .content
-            .first()
-            .map(|c| c.text.clone())
-            .ok_or_else(|| anyhow!("No content in Anthropic response"))
+        if let Ok(mut usage) = self.last_usage.lock() {
+            *usage = anthropic_res.usage.clone();
+        }
+        self.record_call(
+            anthropic_res.stop_reason.as_deref(),
+            anthropic_res.model.as_deref(),
+        );
+
+        let (text, thinking) = split_anthropic_content(&anthropic_res.content);
+        if let Ok(mut reasoning) = self.last_reasoning.lock() {
+            *reasoning = thinking;
+        }
+        if text.is_empty() {
+            return Err(anyhow!("No content in Anthropic response"));
+        }
+        Ok(text)
     }
 
     async fn generate_gemini(&self, req: LLMRequest) -> Result<String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            req.model, self.api_key
-        );
-
-        let body = serde_json::json!({
-            "contents": [{
-                "parts": [{
-                    "text": format!("System Instruction: {}\n\nUser Request: {}", req.system, req.user)
-                }]
-            }]
-        });
+        let url = gemini_url(&req.model, false);
+        let mut body = to_gemini_single_body(&req);
+        self.gemini_config.apply_to(&mut body);
 
         let res = self
             .client
             .post(&url)
+            .header("x-goog-api-key", &self.api_key)
             .header("content-type", "application/json")
             .json(&body)
             .send()
@@ -1507,6 +2300,7 @@ impl LLMClient {
         }
 
         let gemini_res: GeminiResponse = res.json().await?;
+        self.record_gemini_response(&gemini_res);
 
         gemini_res
             .candidates
@@ -1522,11 +2316,13 @@ impl LLMClient {
         // Check if this is a reasoning model (R1)
         let is_reasoning_model = req.model.contains("reasoner");
 
+        let user_content = text_only_user_content("DeepSeek", &req.user, &req.attachments)?;
+
         let body = serde_json::json!({
             "model": req.model,
             "messages": [
                 {"role": "system", "content": req.system},
-                {"role": "user", "content": req.user}
+                {"role": "user", "content": user_content}
             ],
             "stream": false
         });
@@ -1545,6 +2341,14 @@ impl LLMClient {
         }
 
         let deepseek_res: DeepSeekResponse = res.json().await?;
+        self.record_openai_compatible(
+            deepseek_res
+                .choices
+                .first()
+                .and_then(|c| c.finish_reason.as_deref()),
+            deepseek_res.usage.as_ref(),
+            deepseek_res.model.as_deref(),
+        );
 
         let message = deepseek_res
             .choices
@@ -1594,8 +2398,8 @@ impl LLMClient {
         } else {
             &self.api_key
         };
-        println!(
-            "[DEBUG] OpenAI request with key prefix: {}... (len={})",
+        tracing::debug!(
+            "OpenAI request with key prefix: {}... (len={})",
             key_prefix,
             self.api_key.len()
         );
@@ -1608,14 +2412,17 @@ impl LLMClient {
             ));
         }
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": req.model,
             "messages": [
                 {"role": "system", "content": req.system},
-                {"role": "user", "content": req.user}
+                {"role": "user", "content": openai_user_content(&req.user, &req.attachments)}
             ],
             "max_tokens": 4096
         });
+        if let Some(reasoning) = &req.reasoning {
+            reasoning.apply_to_openai(&mut body);
+        }
 
         let res = self
             .client
@@ -1636,12 +2443,24 @@ impl LLMClient {
         }
 
         let openai_res: OpenAIResponse = res.json().await?;
+        self.record_openai_compatible(
+            openai_res
+                .choices
+                .first()
+                .and_then(|c| c.finish_reason.as_deref()),
+            openai_res.usage.as_ref(),
+            openai_res.model.as_deref(),
+        );
 
-        openai_res
+        let message = openai_res
             .choices
             .first()
-            .map(|c| c.message.content.clone())
-            .ok_or_else(|| anyhow!("No content in OpenAI response"))
+            .map(|c| &c.message)
+            .ok_or_else(|| anyhow!("No content in OpenAI response"))?;
+        if let Ok(mut reasoning) = self.last_reasoning.lock() {
+            *reasoning = message.reasoning_content.clone();
+        }
+        Ok(message.content.clone())
     }
 
     // ------------------------------------------------------------------
@@ -1656,23 +2475,21 @@ impl LLMClient {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         let url = "https://api.anthropic.com/v1/messages";
 
-        let body = serde_json::json!({
-            "model": req.model,
-            "max_tokens": 4096,
-            "system": req.system,
-            "messages": [{
-                "role": "user",
-                "content": req.user
-            }],
-            "stream": true
-        });
+        let body = to_anthropic_single_body(&req, true);
 
-        let res = self
+        let mut request_builder = self
             .client
             .post(url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
+            .header("content-type", "application/json");
+
+        if let Some(config) = &req.cache_config {
+            request_builder =
+                request_builder.header("anthropic-beta", config.ttl.to_anthropic_header());
+        }
+
+        let res = request_builder
             .json(&body)
             .send()
             .await
@@ -1682,39 +2499,8 @@ impl LLMClient {
             return Err(anyhow!("Anthropic API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-
-                    for line in text.lines() {
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if json_str == "[DONE]" {
-                                return None;
-                            }
-
-                            if let Ok(event) =
-                                serde_json::from_str::<AnthropicStreamEvent>(json_str)
-                            {
-                                if event.event_type == "content_block_delta" {
-                                    if let Some(delta) = event.delta {
-                                        if let Some(text) = delta.text {
-                                            return Some(Ok(text));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::Anthropic);
+        Ok(self.token_stream(events, false))
     }
 
     /// Gemini newline-delimited JSON streaming implementation
@@ -1724,27 +2510,17 @@ impl LLMClient {
         &self,
         req: LLMRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?key={}&alt=sse",
-            req.model, self.api_key
-        );
+        let url = gemini_url(&req.model, true);
 
-        println!(
-            "[DEBUG] Gemini stream URL: {}",
-            url.replace(&self.api_key, "***KEY***")
-        );
+        tracing::debug!("Gemini stream URL: {}", url);
 
-        let body = serde_json::json!({
-            "contents": [{
-                "parts": [{
-                    "text": format!("System Instruction: {}\n\nUser Request: {}", req.system, req.user)
-                }]
-            }]
-        });
+        let mut body = to_gemini_single_body(&req);
+        self.gemini_config.apply_to(&mut body);
 
         let res = self
             .client
             .post(&url)
+            .header("x-goog-api-key", &self.api_key)
             .header("content-type", "application/json")
             .json(&body)
             .send()
@@ -1754,67 +2530,13 @@ impl LLMClient {
         if !res.status().is_success() {
             let status = res.status();
             let error_text = res.text().await.unwrap_or_default();
-            println!("[DEBUG] Gemini API Error {}: {}", status, error_text);
+            tracing::debug!("Gemini API Error {}: {}", status, error_text);
             return Err(anyhow!("Gemini API Error ({}): {}", status, error_text));
         }
 
-        println!("[DEBUG] Gemini stream connected successfully");
-        let stream = res.bytes_stream();
-
-        // Gemini with alt=sse returns SSE format: "data: {json}\n\n"
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-                    println!("[DEBUG] Gemini chunk: {}", &text[..text.len().min(200)]);
-
-                    // Parse SSE format: "data: {...}\n\n"
-                    for line in text.lines() {
-                        // Skip empty lines and array brackets
-                        let trimmed = line.trim();
-                        if trimmed.is_empty() || trimmed == "[" || trimmed == "]" || trimmed == ","
-                        {
-                            continue;
-                        }
-
-                        // Handle SSE format
-                        let json_str = if let Some(data) = trimmed.strip_prefix("data: ") {
-                            data
-                        } else if trimmed.starts_with('{') {
-                            // Direct JSON object (non-SSE format)
-                            trimmed
-                        } else {
-                            continue;
-                        };
-
-                        // Try to parse the JSON
-                        if let Ok(response) = serde_json::from_str::<GeminiResponse>(json_str) {
-                            if let Some(candidate) = response.candidates.first() {
-                                if let Some(part) = candidate.content.parts.first() {
-                                    println!(
-                                        "[DEBUG] Gemini extracted text: {}...",
-                                        &part.text[..part.text.len().min(50)]
-                                    );
-                                    return Some(Ok(part.text.clone()));
-                                }
-                            }
-                        } else {
-                            println!(
-                                "[DEBUG] Failed to parse Gemini JSON: {}",
-                                &json_str[..json_str.len().min(100)]
-                            );
-                        }
-                    }
-                    None
-                }
-                Err(e) => {
-                    println!("[DEBUG] Gemini stream error: {}", e);
-                    Some(Err(LLMError::NetworkError(e.to_string())))
-                }
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        tracing::debug!("Gemini stream connected");
+        let events = sse::decode(res.bytes_stream(), StreamFormat::Gemini);
+        Ok(self.token_stream(events, false))
     }
 
     /// DeepSeek OpenAI-compatible SSE streaming implementation
@@ -1829,13 +2551,16 @@ impl LLMClient {
         // Check if this is a reasoning model (R1)
         let is_reasoning_model = req.model.contains("reasoner");
 
+        let user_content = text_only_user_content("DeepSeek", &req.user, &req.attachments)?;
+
         let body = serde_json::json!({
             "model": req.model,
             "messages": [
                 {"role": "system", "content": req.system},
-                {"role": "user", "content": req.user}
+                {"role": "user", "content": user_content}
             ],
-            "stream": true
+            "stream": true,
+            "stream_options": {"include_usage": true}
         });
 
         let res = self
@@ -1852,76 +2577,9 @@ impl LLMClient {
             return Err(anyhow!("DeepSeek API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-
-        // Track if we've started receiving final content (for R1 formatting)
-        let started_content = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-        let started_content_clone = started_content.clone();
-
-        let token_stream = stream.filter_map(move |chunk_result| {
-            let started_content = started_content_clone.clone();
-            let is_r1 = is_reasoning_model;
-
-            async move {
-                match chunk_result {
-                    Ok(chunk) => {
-                        let text = String::from_utf8_lossy(&chunk);
-                        let mut result_tokens = Vec::new();
-
-                        for line in text.lines() {
-                            if let Some(json_str) = line.strip_prefix("data: ") {
-                                if json_str == "[DONE]" {
-                                    continue;
-                                }
-
-                                if let Ok(chunk_data) =
-                                    serde_json::from_str::<DeepSeekStreamChunk>(json_str)
-                                {
-                                    if let Some(choice) = chunk_data.choices.first() {
-                                        // For R1 models, stream reasoning_content first
-                                        if is_r1 {
-                                            if let Some(reasoning) = &choice.delta.reasoning_content
-                                            {
-                                                if !reasoning.is_empty() {
-                                                    result_tokens.push(reasoning.clone());
-                                                }
-                                            }
-                                        }
-
-                                        // Stream content (final answer)
-                                        if let Some(content) = &choice.delta.content {
-                                            if !content.is_empty() {
-                                                // For R1, add separator when transitioning from reasoning to content
-                                                if is_r1
-                                                    && !started_content
-                                                        .load(std::sync::atomic::Ordering::Relaxed)
-                                                {
-                                                    started_content.store(
-                                                        true,
-                                                        std::sync::atomic::Ordering::Relaxed,
-                                                    );
-                                                    result_tokens.push("\n\n---\n\n".to_string());
-                                                }
-                                                result_tokens.push(content.clone());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        if result_tokens.is_empty() {
-                            None
-                        } else {
-                            Some(Ok(result_tokens.join("")))
-                        }
-                    }
-                    Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-                }
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::OpenAI);
+        // R1 reasoning is streamed inline, ahead of the answer
+        Ok(self.token_stream(events, is_reasoning_model))
     }
 
     /// OpenAI SSE streaming implementation
@@ -1932,14 +2590,18 @@ impl LLMClient {
     ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>> {
         let url = "https://api.openai.com/v1/chat/completions";
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": req.model,
             "messages": [
                 {"role": "system", "content": req.system},
-                {"role": "user", "content": req.user}
+                {"role": "user", "content": openai_user_content(&req.user, &req.attachments)}
             ],
-            "stream": true
+            "stream": true,
+            "stream_options": {"include_usage": true}
         });
+        if let Some(reasoning) = &req.reasoning {
+            reasoning.apply_to_openai(&mut body);
+        }
 
         let res = self
             .client
@@ -1955,37 +2617,8 @@ impl LLMClient {
             return Err(anyhow!("OpenAI API Error: {}", res.status()));
         }
 
-        let stream = res.bytes_stream();
-
-        let token_stream = stream.filter_map(|chunk_result| async move {
-            match chunk_result {
-                Ok(chunk) => {
-                    let text = String::from_utf8_lossy(&chunk);
-
-                    for line in text.lines() {
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if json_str == "[DONE]" {
-                                return None;
-                            }
-
-                            if let Ok(chunk_data) =
-                                serde_json::from_str::<OpenAIStreamChunk>(json_str)
-                            {
-                                if let Some(choice) = chunk_data.choices.first() {
-                                    if let Some(content) = &choice.delta.content {
-                                        return Some(Ok(content.clone()));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(LLMError::NetworkError(e.to_string()))),
-            }
-        });
-
-        Ok(Box::pin(token_stream))
+        let events = sse::decode(res.bytes_stream(), StreamFormat::OpenAI);
+        Ok(self.token_stream(events, false))
     }
 }
 
@@ -2212,6 +2845,7 @@ mod tests {
             model: "claude-3-sonnet".to_string(),
             system: "test".to_string(),
             user: "test".to_string(),
+            ..Default::default()
         };
 
         let _ = client.generate(req1.clone()).await; // Will fail at API, but pass rate limit
@@ -2374,6 +3008,7 @@ mod tests {
             model: "claude-3-sonnet".to_string(),
             system: "test".to_string(),
             user: "test".to_string(),
+            ..Default::default()
         };
 
         for _ in 0..5 {
@@ -2400,6 +3035,7 @@ mod tests {
             model: "claude-3-sonnet".to_string(),
             system: "test".to_string(),
             user: "test".to_string(),
+            ..Default::default()
         };
 
         // Method should exist and return Result<Stream>
@@ -2541,6 +3177,77 @@ mod tests {
         assert_eq!(provider5, "openai", "Should detect openai from o3-mini");
     }
 
+    #[test]
+    fn test_detect_provider_mock() {
+        // Built-in offline provider for mock* models
+        let client = LLMClient::new(String::new());
+
+        assert_eq!(client.detect_provider("mock").unwrap(), "mock");
+        assert_eq!(client.detect_provider("mock-flaky").unwrap(), "mock");
+        assert!(LLMClient::is_mock_model("mock-fast"));
+        assert!(!LLMClient::is_mock_model("claude-3-opus"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_mock_model_needs_no_key() {
+        let mut client = LLMClient::new(String::new()).with_mock_config(MockConfig {
+            responses: vec!["scripted answer".to_string()],
+            ..MockConfig::default()
+        });
+
+        let request = LLMRequest {
+            system: "test".to_string(),
+            user: "test".to_string(),
+            model: "mock-fast".to_string(),
+            ..Default::default()
+        };
+
+        let generation = client.generate(request).await.unwrap();
+        assert_eq!(generation.text, "scripted answer");
+        assert_eq!(generation.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(generation.model, "mock-fast");
+        assert!(!generation.cache_hit);
+    }
+
+    #[tokio::test]
+    async fn test_generate_reports_truncation() {
+        let mut client = LLMClient::new(String::new()).with_mock_config(MockConfig {
+            latency_ms: 0,
+            truncate_on_calls: vec![1],
+            ..MockConfig::default()
+        });
+        let request = LLMRequest {
+            model: "mock".to_string(),
+            ..Default::default()
+        };
+
+        assert!(client
+            .generate(request.clone())
+            .await
+            .unwrap()
+            .is_truncated());
+        assert!(!client.generate(request).await.unwrap().is_truncated());
+    }
+
+    #[test]
+    fn test_stop_reasons_are_normalized() {
+        for reason in ["max_tokens", "length", "MAX_TOKENS"] {
+            assert_eq!(StopReason::from_provider(reason), StopReason::MaxTokens);
+        }
+        for reason in ["end_turn", "stop", "STOP"] {
+            assert_eq!(StopReason::from_provider(reason), StopReason::EndTurn);
+        }
+        assert_eq!(
+            StopReason::from_provider("SAFETY"),
+            StopReason::ContentFilter
+        );
+        assert_eq!(StopReason::from_provider("tool_calls"), StopReason::ToolUse);
+        assert_eq!(
+            StopReason::from_provider("pause_turn").as_str(),
+            "pause_turn"
+        );
+    }
+
     #[test]
     fn test_detect_provider_unsupported_model() {
         // TEST-UNIT-LLMCLIENT-005: Verify detect_provider() rejects unknown models
@@ -2583,6 +3290,7 @@ mod tests {
             system: "You are a helpful assistant".to_string(),
             user: "Hello, world!".to_string(),
             model: "claude-sonnet-4-5-20250929".to_string(),
+            ..Default::default()
         };
 
         assert_eq!(request.system, "You are a helpful assistant");
@@ -2904,6 +3612,242 @@ mod tests {
         assert!(msg["content"][0]["cache_control"].is_object());
     }
 
+    #[test]
+    fn test_to_anthropic_single_body_with_prompt_caching() {
+        let mut req = LLMRequest {
+            system: "Phase instructions".to_string(),
+            user: "Company context".to_string(),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            ..Default::default()
+        };
+
+        // Without a cache config the plain string form is kept
+        let plain = to_anthropic_single_body(&req, false);
+        assert_eq!(plain["system"], "Phase instructions");
+        assert_eq!(plain["messages"][0]["content"], "Company context");
+        assert!(plain.get("stream").is_none());
+
+        req.cache_config = Some(CacheConfig::default());
+        let cached = to_anthropic_single_body(&req, true);
+        assert_eq!(cached["system"][0]["text"], "Phase instructions");
+        assert!(cached["system"][0]["cache_control"].is_object());
+        assert!(cached["messages"][0]["content"][0]["cache_control"].is_object());
+        assert_eq!(cached["stream"], true);
+    }
+
+    #[test]
+    fn test_record_usage_merges_running_totals() {
+        let slot = Mutex::new(None);
+        // Anthropic: message_start carries input/cache usage, message_delta the output count
+        record_usage(
+            &slot,
+            &TokenUsage {
+                input_tokens: 12,
+                output_tokens: 1,
+                cache_creation_input_tokens: 2048,
+                cache_read_input_tokens: 0,
+            },
+        );
+        record_usage(
+            &slot,
+            &TokenUsage {
+                output_tokens: 350,
+                ..Default::default()
+            },
+        );
+
+        let usage = slot.lock().unwrap().clone().unwrap();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.cache_creation_input_tokens, 2048);
+        assert_eq!(usage.output_tokens, 350);
+    }
+
+    #[test]
+    fn test_anthropic_single_body_puts_attachments_before_text() {
+        let req = LLMRequest {
+            system: "Analyze the filing".to_string(),
+            user: "Acme Corp".to_string(),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            attachments: vec![
+                ContentBlock::image("image/png", b"png-bytes"),
+                ContentBlock::document("10k.pdf", "application/pdf", b"%PDF"),
+            ],
+            ..Default::default()
+        };
+
+        let body = to_anthropic_single_body(&req, false);
+        let content = body["messages"][0]["content"].as_array().unwrap();
+
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0]["type"], "image");
+        assert_eq!(content[0]["source"]["media_type"], "image/png");
+        assert_eq!(content[0]["source"]["data"], BASE64.encode(b"png-bytes"));
+        assert_eq!(content[1]["type"], "document");
+        assert_eq!(content[1]["title"], "10k.pdf");
+        assert_eq!(content[2]["text"], "Acme Corp");
+        assert!(content[2].get("cache_control").is_none());
+    }
+
+    #[test]
+    fn test_attachment_encodings_for_openai_and_gemini() {
+        let image = ContentBlock::image("image/jpeg", b"jpg");
+        let pdf = ContentBlock::document("deck.pdf", "application/pdf", b"%PDF");
+
+        let parts = openai_user_content("Summarize", &[image.clone(), pdf.clone()]);
+        assert_eq!(parts[0]["type"], "image_url");
+        assert!(parts[0]["image_url"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+        assert_eq!(parts[1]["file"]["filename"], "deck.pdf");
+        assert_eq!(parts[2]["text"], "Summarize");
+        assert_eq!(openai_user_content("Summarize", &[]), "Summarize");
+
+        assert_eq!(pdf.to_gemini()["inlineData"]["mimeType"], "application/pdf");
+        assert_eq!(
+            image.to_gemini()["inlineData"]["data"],
+            BASE64.encode(b"jpg")
+        );
+    }
+
+    #[test]
+    fn test_text_only_provider_rejects_binary_attachments() {
+        let csv = ContentBlock::text("Attachment: leads.csv\n\nname\nJane");
+        let content = text_only_user_content("DeepSeek", "Acme", &[csv]).unwrap();
+        assert!(content.starts_with("Attachment: leads.csv"));
+        assert!(content.ends_with("Acme"));
+
+        let image = ContentBlock::image("image/png", b"png");
+        let err = text_only_user_content("DeepSeek", "Acme", &[image]).unwrap_err();
+        assert!(err.to_string().contains("DeepSeek does not support"));
+    }
+
+    #[test]
+    fn test_anthropic_thinking_budget_raises_max_tokens() {
+        let req = LLMRequest {
+            system: "Analyze".to_string(),
+            user: "Acme Corp".to_string(),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            reasoning: Some(ReasoningConfig {
+                thinking_budget: Some(500),
+                reasoning_effort: Some(ReasoningEffort::High),
+            }),
+            ..Default::default()
+        };
+
+        let body = to_anthropic_single_body(&req, true);
+
+        // Budget is clamped to Anthropic's minimum; effort is OpenAI-only
+        assert_eq!(body["thinking"]["type"], "enabled");
+        assert_eq!(body["thinking"]["budget_tokens"], MIN_THINKING_BUDGET);
+        assert_eq!(body["max_tokens"], 4096 + MIN_THINKING_BUDGET);
+        assert!(body.get("reasoning_effort").is_none());
+
+        let plain = to_anthropic_body(&MultiTurnRequest::new("claude-sonnet-4-5-20250929"));
+        assert!(plain.get("thinking").is_none());
+        assert_eq!(plain["max_tokens"], 4096);
+
+        // Models without extended thinking get a plain request
+        let older = to_anthropic_single_body(
+            &LLMRequest {
+                model: "claude-3-5-haiku-20241022".to_string(),
+                ..req
+            },
+            false,
+        );
+        assert!(older.get("thinking").is_none());
+        assert_eq!(older["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_openai_reasoning_effort_uses_max_completion_tokens() {
+        let mut body = serde_json::json!({"model": "o3-mini", "max_tokens": 4096});
+        ReasoningConfig {
+            thinking_budget: Some(8000),
+            reasoning_effort: Some(ReasoningEffort::Medium),
+        }
+        .apply_to_openai(&mut body);
+
+        assert_eq!(body["reasoning_effort"], "medium");
+        assert!(body.get("max_tokens").is_none());
+        assert_eq!(
+            body["max_completion_tokens"],
+            OPENAI_REASONING_MAX_COMPLETION_TOKENS
+        );
+        assert!(body.get("thinking").is_none());
+
+        let mut body = serde_json::json!({"model": "gpt-4o", "max_tokens": 4096});
+        ReasoningConfig {
+            thinking_budget: None,
+            reasoning_effort: Some(ReasoningEffort::Medium),
+        }
+        .apply_to_openai(&mut body);
+        assert!(body.get("reasoning_effort").is_none());
+        assert_eq!(body["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_split_anthropic_content_separates_thinking() {
+        let res: AnthropicResponse = serde_json::from_str(
+            r#"{"content": [
+                {"type": "thinking", "thinking": "Revenue doubled, so...", "signature": "abc"},
+                {"type": "text", "text": "Acme is growing."}
+            ]}"#,
+        )
+        .unwrap();
+
+        let (text, thinking) = split_anthropic_content(&res.content);
+
+        assert_eq!(text, "Acme is growing.");
+        assert_eq!(thinking.as_deref(), Some("Revenue doubled, so..."));
+    }
+
+    #[tokio::test]
+    async fn test_token_stream_records_reasoning_usage_and_stop_reason() {
+        let client = LLMClient::new("test_key".to_string());
+        let events = futures::stream::iter(vec![
+            Ok(StreamEvent::Reasoning("Step 1".to_string())),
+            Ok(StreamEvent::Reasoning(", step 2".to_string())),
+            Ok(StreamEvent::Text("Acme ".to_string())),
+            Ok(StreamEvent::Text(String::new())),
+            Ok(StreamEvent::Text("is growing.".to_string())),
+            Ok(StreamEvent::Stop("max_tokens".to_string())),
+            Ok(StreamEvent::Usage(TokenUsage {
+                output_tokens: 42,
+                ..Default::default()
+            })),
+            Ok(StreamEvent::Done),
+        ]);
+
+        let tokens: Vec<String> = client
+            .token_stream(events, false)
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(tokens, vec!["Acme ", "is growing."]);
+        assert_eq!(client.last_reasoning().as_deref(), Some("Step 1, step 2"));
+        assert_eq!(client.last_stop_reason(), Some(StopReason::MaxTokens));
+        assert_eq!(client.last_usage().unwrap().output_tokens, 42);
+    }
+
+    #[tokio::test]
+    async fn test_token_stream_inlines_reasoning_and_surfaces_errors() {
+        let client = LLMClient::new("test_key".to_string());
+        let events = futures::stream::iter(vec![
+            Ok(StreamEvent::Reasoning("Thinking".to_string())),
+            Ok(StreamEvent::Text("Answer".to_string())),
+            Err(LLMError::ProviderError("Overloaded".to_string())),
+        ]);
+
+        let tokens: Vec<_> = client.token_stream(events, true).collect().await;
+
+        assert_eq!(tokens[0].as_deref().unwrap(), "Thinking");
+        assert_eq!(tokens[1].as_deref().unwrap(), "\n\n---\n\nAnswer");
+        assert!(matches!(tokens[2], Err(LLMError::ProviderError(_))));
+        assert!(client.last_reasoning().is_none());
+    }
+
     #[test]
     fn test_to_openai_body_basic() {
         // TEST-MT-060: Verify to_openai_body() transformation
@@ -2970,6 +3914,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_gemini_single_body_uses_system_instruction() {
+        let req = LLMRequest {
+            system: "Phase instructions".to_string(),
+            user: "Acme Corp".to_string(),
+            model: "gemini-2.5-flash".to_string(),
+            ..Default::default()
+        };
+
+        let body = to_gemini_single_body(&req);
+
+        assert_eq!(
+            body["systemInstruction"]["parts"][0]["text"],
+            "Phase instructions"
+        );
+        assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Acme Corp");
+    }
+
+    #[test]
+    fn test_gemini_url_has_no_api_key() {
+        let url = gemini_url("gemini-2.5-flash", true);
+        assert!(url.ends_with("gemini-2.5-flash:streamGenerateContent?alt=sse"));
+        assert!(!url.contains("key="));
+        assert!(!gemini_url("gemini-2.5-flash", false).contains('?'));
+    }
+
+    #[test]
+    fn test_gemini_config_applies_generation_and_safety() {
+        let mut body = serde_json::json!({"contents": []});
+        GeminiConfig::default().apply_to(&mut body);
+        assert!(body.get("generationConfig").is_none());
+        assert!(body.get("safetySettings").is_none());
+
+        let config = GeminiConfig {
+            temperature: Some(0.2),
+            max_output_tokens: Some(2048),
+            safety_settings: vec![GeminiSafetySetting {
+                category: "HARM_CATEGORY_HARASSMENT".to_string(),
+                threshold: "BLOCK_ONLY_HIGH".to_string(),
+            }],
+            ..Default::default()
+        };
+        config.apply_to(&mut body);
+
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 2048);
+        assert!(body["generationConfig"].get("topK").is_none());
+        assert_eq!(body["safetySettings"][0]["threshold"], "BLOCK_ONLY_HIGH");
+    }
+
     #[test]
     fn test_to_gemini_body_system_instruction() {
         // TEST-MT-052: Verify Gemini uses "systemInstruction" for system prompt
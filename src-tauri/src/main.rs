@@ -2,23 +2,51 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod agent;
+mod approval;
+mod attachments;
 mod auth;
+mod dry_run;
+mod ensemble;
+mod followup;
+mod generator;
 mod llm;
 mod manifest;
+mod metrics;
+mod mock;
+mod model_catalog;
+mod report;
+mod response_cache;
+mod sse;
+mod telemetry;
+mod token_batch;
 
 use agent::Agent;
+use approval::{ApprovalDecision, ApprovalRegistry, StoredReview, AWAITING_APPROVAL};
+use attachments::Attachment;
 use auth::{
     ApiKeyEntry, AuthManager, Brief, BriefSummary, ConversationMessage, CustomProvider,
-    CustomProviderSummary, PhaseOutput, Project, ProjectSummary, Provider, ResearchSession,
-    ResearchSessionSummary, ResumeSessionResult, SessionContext, SessionHistoryMessage,
-    SessionMessage, UserProfile,
+    CustomProviderSummary, ManifestSummary, ManifestVersion, ManifestVersionSummary, PendingReview,
+    PhaseOutput, PhaseOutputMeta, Project, ProjectSummary, Provider, ResearchSession,
+    ResearchSessionSummary, ResumeSessionResult, SessionAttachment, SessionContext,
+    SessionHistoryMessage, SessionMessage, UserProfile,
 };
-use manifest::Manifest;
+use dry_run::DryRunReport;
+use followup::{FollowupTurn, FOLLOWUP_PHASE_ID};
+use futures::StreamExt;
+use generator::{GeneratedManifest, ManifestBrief};
+use manifest::history::{self, ManifestSnapshot};
+use manifest::{InputField, InputType, Manifest};
+use metrics::{LatencyStats, PhaseMetrics};
+use mock::MockConfig;
+use model_catalog::{ModelCatalog, ModelInfo};
+use response_cache::ResponseCache;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::{image::Image, AppHandle, Manager, State};
+use tauri::{image::Image, AppHandle, Emitter, Manager, State};
+use telemetry::RunLogEntry;
 
 // ------------------------------------------------------------------
 // 1. Persistent Configuration Structs
@@ -37,6 +65,24 @@ struct AppConfig {
     api_key: Option<String>,
     last_manifest_path: Option<PathBuf>,
     saved_manifests: Vec<SavedManifest>,
+    /// Response cache for run_research (None = on for dev builds, off for release)
+    #[serde(default)]
+    response_cache_enabled: Option<bool>,
+    /// Response cache entry lifetime in hours (None = 7 days)
+    #[serde(default)]
+    response_cache_ttl_hours: Option<u64>,
+    /// Gemini generationConfig and safety settings
+    #[serde(default)]
+    gemini_config: llm::GeminiConfig,
+    /// Coalescing of stream-token events sent to the UI
+    #[serde(default)]
+    token_batching: token_batch::TokenBatchConfig,
+    /// Anthropic prompt caching for phase requests (None = on)
+    #[serde(default)]
+    prompt_cache_enabled: Option<bool>,
+    /// Prompt cache lifetime (None = five minutes)
+    #[serde(default)]
+    prompt_cache_ttl: Option<llm::CacheTTL>,
 }
 
 impl Default for AppConfig {
@@ -46,6 +92,12 @@ impl Default for AppConfig {
             // Paths will be resolved properly in setup() using resolve_manifest_path()
             last_manifest_path: None,
             saved_manifests: vec![],
+            response_cache_enabled: None,
+            response_cache_ttl_hours: None,
+            gemini_config: llm::GeminiConfig::default(),
+            token_batching: token_batch::TokenBatchConfig::default(),
+            prompt_cache_enabled: None,
+            prompt_cache_ttl: None,
         }
     }
 }
@@ -115,6 +167,33 @@ fn resolve_default_manifest_path() -> Option<PathBuf> {
 struct PhaseInfo {
     id: String,
     name: String,
+    #[serde(default)]
+    warnings: Vec<String>, // e.g. unknown or deprecated model
+    #[serde(default)]
+    source: Option<manifest::PhaseSource>, // File defining the phase (imports/extends)
+}
+
+impl PhaseInfo {
+    fn new(phase: &manifest::Phase, catalog: &ModelCatalog) -> Self {
+        Self {
+            id: phase.id.clone(),
+            name: phase.name.clone(),
+            warnings: phase
+                .model
+                .as_deref()
+                .and_then(|model| catalog.model_warning(model))
+                .into_iter()
+                .collect(),
+            source: phase.source.clone(),
+        }
+    }
+}
+
+/// Result of `validate_manifest`: the phases, and lint diagnostics for the caller to act on
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ManifestValidation {
+    phases: Vec<PhaseInfo>,
+    diagnostics: Vec<manifest::lint::Diagnostic>,
 }
 
 // ------------------------------------------------------------------
@@ -141,6 +220,101 @@ impl AppState {
         fs::write(&self.config_path, json).map_err(|e| e.to_string())?;
         Ok(())
     }
+
+    // Mock provider script (app_data/mock_responses.yaml), defaults if absent or invalid
+    fn mock_config(&self) -> MockConfig {
+        let path = self.config_path.with_file_name("mock_responses.yaml");
+        if !path.exists() {
+            return MockConfig::default();
+        }
+        MockConfig::load_from_file(&path).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid mock config {:?}: {}", path, e);
+            MockConfig::default()
+        })
+    }
+
+    // Model catalog (app_data/models.yaml), built-in defaults if absent or invalid
+    fn model_catalog(&self) -> ModelCatalog {
+        let path = self.config_path.with_file_name("models.yaml");
+        if !path.exists() {
+            return ModelCatalog::builtin();
+        }
+        ModelCatalog::load_from_file(&path).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid model catalog {:?}: {}", path, e);
+            ModelCatalog::builtin()
+        })
+    }
+
+    // Gemini generationConfig/safety settings from config.json
+    fn gemini_config(&self) -> llm::GeminiConfig {
+        self.config
+            .lock()
+            .map(|config| config.gemini_config.clone())
+            .unwrap_or_default()
+    }
+
+    // Stream-token batching settings from config.json
+    fn token_batching(&self) -> token_batch::TokenBatchConfig {
+        self.config
+            .lock()
+            .map(|config| config.token_batching)
+            .unwrap_or_default()
+    }
+
+    // Anthropic prompt caching settings from config.json, None when disabled
+    fn prompt_cache(&self) -> Option<llm::CacheConfig> {
+        let config = self.config.lock().ok()?;
+        config
+            .prompt_cache_enabled
+            .unwrap_or(true)
+            .then(|| llm::CacheConfig {
+                ttl: config
+                    .prompt_cache_ttl
+                    .unwrap_or(llm::CacheTTL::FiveMinutes),
+            })
+    }
+
+    // Response cache (app_data/response_cache.db), None when disabled or unavailable
+    fn response_cache(&self) -> Option<ResponseCache> {
+        let (enabled, ttl) = {
+            let config = self.config.lock().ok()?;
+            let ttl = config
+                .response_cache_ttl_hours
+                .map(|h| std::time::Duration::from_secs(h * 60 * 60))
+                .unwrap_or(response_cache::DEFAULT_TTL);
+            // Dev builds cache by default so manifest iterations stay cheap
+            (
+                config
+                    .response_cache_enabled
+                    .unwrap_or(cfg!(debug_assertions)),
+                ttl,
+            )
+        };
+        if !enabled {
+            return None;
+        }
+
+        let path = self.config_path.with_file_name("response_cache.db");
+        let cache = ResponseCache::open(&path, ttl)
+            .map_err(|e| tracing::warn!("Response cache unavailable {:?}: {}", path, e))
+            .ok()?;
+        // Expired entries are never served; drop them so the database stays small
+        match cache.purge_expired() {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!(purged, "Purged expired response cache entries"),
+            Err(e) => tracing::warn!("Could not purge the response cache: {}", e),
+        }
+        Some(cache)
+    }
+}
+
+/// Resolve the API key for a model. Mock models run offline and need no key.
+fn resolve_api_key(config: &AppConfig, model: &str) -> Result<String, String> {
+    match &config.api_key {
+        Some(key) => Ok(key.clone()),
+        None if llm::LLMClient::is_mock_model(model) => Ok(String::new()),
+        None => Err("API Key not set. Please configure in settings.".to_string()),
+    }
 }
 
 // ------------------------------------------------------------------
@@ -150,6 +324,11 @@ struct AuthState {
     manager: Mutex<AuthManager>,
 }
 
+/// Keeps the rolling log file writer alive (flushes on drop)
+struct LogGuard {
+    _guard: tracing_appender::non_blocking::WorkerGuard,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct UserInfo {
     id: i64,
@@ -638,13 +817,14 @@ async fn save_phase_output(
     user_input: Option<String>,
     output: Option<String>,
     error: Option<String>,
+    meta: Option<PhaseOutputMeta>,
     auth_state: State<'_, AuthState>,
 ) -> Result<i64, String> {
     let manager = auth_state
         .manager
         .lock()
         .map_err(|_| "Failed to lock auth state")?;
-    manager
+    let id = manager
         .save_phase_output(
             session_id,
             &phase_id,
@@ -655,7 +835,16 @@ async fn save_phase_output(
             output.as_deref(),
             error.as_deref(),
         )
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    // Run metadata (e.g. response cache hits) travels with the completed event
+    if let Some(meta) = meta {
+        manager
+            .update_phase_output_meta(session_id, &phase_id, &meta)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(id)
 }
 
 #[tauri::command]
@@ -725,6 +914,51 @@ async fn get_session_conversation(
         .map_err(|e| e.to_string())
 }
 
+/// Get the files attached to a research session
+#[tauri::command]
+async fn get_session_attachments(
+    session_id: i64,
+    auth_state: State<'_, AuthState>,
+) -> Result<Vec<SessionAttachment>, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager
+        .get_session_attachments(session_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Latency percentiles per provider/model over completed phases
+/// (`from`/`to`: inclusive YYYY-MM-DD dates; omit for all time)
+#[tauri::command]
+async fn get_latency_stats(
+    from: Option<String>,
+    to: Option<String>,
+    auth_state: State<'_, AuthState>,
+) -> Result<Vec<LatencyStats>, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager
+        .get_latency_stats(from.as_deref(), to.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Get the run log for a research session (agent progress, warnings and errors)
+#[tauri::command]
+async fn get_session_run_log(
+    session_id: i64,
+    auth_state: State<'_, AuthState>,
+) -> Result<Vec<RunLogEntry>, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager.get_run_log(session_id).map_err(|e| e.to_string())
+}
+
 // ------------------------------------------------------------------
 // 3e-ter. Session Resume Command (IM-5020, IM-5021)
 // ------------------------------------------------------------------
@@ -788,7 +1022,7 @@ async fn resume_research_session(
                 session_id
             ))
         }
-        "in_progress" | "paused" => {} // OK to resume
+        "in_progress" | "paused" | "running" | AWAITING_APPROVAL => {} // OK to resume
         _ => {
             return Err(format!(
                 "Session {} has invalid status: {}",
@@ -802,23 +1036,31 @@ async fn resume_research_session(
         .get_phase_outputs(session_id)
         .map_err(|e| e.to_string())?;
 
-    // 4. Find completed phases
+    // 4. Find completed phases, and a phase still held for review (its run ended while
+    // waiting: the review re-opens, decided through review_session_phase)
     let completed_phases: Vec<_> = phase_outputs
         .iter()
         .filter(|p| p.status == "completed")
         .collect();
+    let pending_review = pending_review(&session, &phase_outputs);
 
-    if completed_phases.is_empty() {
+    if completed_phases.is_empty() && pending_review.is_none() {
         return Err(format!(
             "Session {} has no completed phases to resume from",
             session_id
         ));
     }
 
-    let last_completed = completed_phases.last().unwrap();
+    let last_completed = completed_phases
+        .last()
+        .map(|p| p.phase_id.clone())
+        .unwrap_or_default();
 
     // 5. Determine next phase (simple increment for now - manifest-specific logic can be added)
-    let next_phase_id = determine_next_phase(&last_completed.phase_id);
+    let next_phase_id = match &pending_review {
+        Some(review) => review.phase_id.clone(),
+        None => determine_next_phase(&last_completed),
+    };
 
     // 6. Reconstruct context with sliding window (25 pairs default)
     let history = reconstruct_session_context(&phase_outputs, 25);
@@ -836,13 +1078,75 @@ async fn resume_research_session(
         next_phase_id,
         context: SessionContext {
             history,
-            last_completed_phase: last_completed.phase_id.clone(),
+            last_completed_phase: last_completed,
             total_phases,
             completed_phases: completed_phases.len(),
         },
+        pending_review,
     })
 }
 
+/// The phase an `awaiting_approval` session was held at, with its stored output
+fn pending_review(
+    session: &ResearchSession,
+    phase_outputs: &[PhaseOutput],
+) -> Option<PendingReview> {
+    if session.status != AWAITING_APPROVAL {
+        return None;
+    }
+    let phase_id = session.current_phase_id.as_deref()?;
+    phase_outputs
+        .iter()
+        .rev()
+        .find(|p| p.phase_id == phase_id && p.status == AWAITING_APPROVAL)
+        .map(|p| PendingReview {
+            phase_id: p.phase_id.clone(),
+            phase_name: p.phase_name.clone(),
+            output: p.output.clone().unwrap_or_default(),
+        })
+}
+
+/// Decide a review re-opened on resume, for a session whose run ended while it waited
+/// (the app exited). Updates the stored phase output and session; returns the phase id.
+#[tauri::command]
+async fn review_session_phase(
+    session_id: i64,
+    decision: ApprovalDecision,
+    auth_state: State<'_, AuthState>,
+) -> Result<String, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    let session = manager
+        .get_research_session(session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    let phase_outputs = manager
+        .get_phase_outputs(session_id)
+        .map_err(|e| e.to_string())?;
+    let review = pending_review(&session, &phase_outputs)
+        .ok_or_else(|| format!("Session {} is not waiting for approval", session_id))?;
+
+    let outcome = StoredReview::for_decision(decision);
+    manager
+        .save_phase_output(
+            session_id,
+            &review.phase_id,
+            &review.phase_name,
+            outcome.phase_status,
+            None,
+            None,
+            outcome.output.as_deref(),
+            outcome.error.as_deref(),
+        )
+        .map_err(|e| format!("Failed to save review: {}", e))?;
+    manager
+        .update_research_session(session_id, outcome.session_status, Some(&review.phase_id))
+        .map_err(|e| format!("Failed to save review: {}", e))?;
+    Ok(review.phase_id)
+}
+
 /// Determine the next phase ID after a completed phase
 /// This is a simple implementation - could be enhanced with manifest phase order
 fn determine_next_phase(last_phase_id: &str) -> String {
@@ -1381,6 +1685,80 @@ async fn set_api_key(key: String, state: State<'_, AppState>) -> Result<(), Stri
     Ok(())
 }
 
+/// Enable/disable the response cache for research runs (ttl_hours None = 7 days)
+#[tauri::command]
+async fn set_response_cache(
+    enabled: bool,
+    ttl_hours: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        config.response_cache_enabled = Some(enabled);
+        config.response_cache_ttl_hours = ttl_hours;
+    }
+    state.save()
+}
+
+/// Update Gemini generationConfig and safety settings
+#[tauri::command]
+async fn set_gemini_config(
+    config: llm::GeminiConfig,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    {
+        let mut app_config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        app_config.gemini_config = config;
+    }
+    state.save()
+}
+
+/// Update how streamed tokens are batched into stream-token events
+#[tauri::command]
+async fn set_token_batching(
+    config: token_batch::TokenBatchConfig,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    {
+        let mut app_config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        app_config.token_batching = config;
+    }
+    state.save()
+}
+
+/// Enable/disable Anthropic prompt caching for phase requests (ttl None = five minutes)
+#[tauri::command]
+async fn set_prompt_cache(
+    enabled: bool,
+    ttl: Option<llm::CacheTTL>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        config.prompt_cache_enabled = Some(enabled);
+        config.prompt_cache_ttl = ttl;
+    }
+    state.save()
+}
+
+/// Models from the catalog (app_data/models.yaml) with limits, capabilities and pricing
+#[tauri::command]
+async fn get_model_catalog(state: State<'_, AppState>) -> Result<Vec<ModelInfo>, String> {
+    Ok(state.model_catalog().models)
+}
+
+/// Delete all cached responses, returning how many were removed
+#[tauri::command]
+async fn clear_response_cache(state: State<'_, AppState>) -> Result<usize, String> {
+    let path = state.config_path.with_file_name("response_cache.db");
+    if !path.exists() {
+        return Ok(0);
+    }
+    ResponseCache::open(&path, response_cache::DEFAULT_TTL)
+        .and_then(|cache| cache.clear())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn get_app_state(state: State<'_, AppState>) -> Result<AppConfig, String> {
     let config = state.config.lock().map_err(|_| "Failed to lock state")?;
@@ -1423,13 +1801,11 @@ async fn get_manifest_phases(
 
     let manifest = Manifest::load_from_file(&path).map_err(|e| e.to_string())?;
 
+    let catalog = state.model_catalog();
     let phases: Vec<PhaseInfo> = manifest
         .phases
         .iter()
-        .map(|p| PhaseInfo {
-            id: p.id.clone(),
-            name: p.name.clone(),
-        })
+        .map(|p| PhaseInfo::new(p, &catalog))
         .collect();
 
     Ok(phases)
@@ -1443,6 +1819,7 @@ struct ManifestInfo {
     name: String,
     description: String,
     input_label: Option<String>,
+    inputs: Vec<InputField>, // Declared research inputs (empty = single free-text input)
 }
 
 #[tauri::command]
@@ -1473,6 +1850,7 @@ async fn get_manifest_info(
         name: manifest.manifest.name,
         description: manifest.manifest.description,
         input_label: manifest.manifest.input_label,
+        inputs: manifest.inputs,
     })
 }
 
@@ -1538,24 +1916,46 @@ async fn load_manifest_file(path: String) -> Result<String, String> {
     fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read manifest file: {}", e))
 }
 
-/// Validate manifest YAML content and return phases if valid
+/// Validate manifest YAML content and return its phases, with lint diagnostics
+/// (errors flag manifests that would fail or silently misbehave at run time)
 #[tauri::command]
-async fn validate_manifest(content: String) -> Result<Vec<PhaseInfo>, String> {
-    // Try to parse the YAML content
-    let manifest: Manifest =
-        serde_yaml::from_str(&content).map_err(|e| format!("Invalid YAML: {}", e))?;
-
-    // Return phase info
+async fn validate_manifest(
+    content: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ManifestValidation, String> {
+    // Try to parse the YAML content (imports/extends resolve relative to `path`)
+    let path = path.map(PathBuf::from);
+    let manifest =
+        Manifest::parse(&content, path.as_deref()).map_err(|e| format!("Invalid YAML: {:#}", e))?;
+
+    // Return phase info, with warnings for unknown or deprecated models
+    let catalog = state.model_catalog();
     let phases: Vec<PhaseInfo> = manifest
         .phases
         .iter()
-        .map(|p| PhaseInfo {
-            id: p.id.clone(),
-            name: p.name.clone(),
-        })
+        .map(|p| PhaseInfo::new(p, &catalog))
         .collect();
 
-    Ok(phases)
+    Ok(ManifestValidation {
+        phases,
+        diagnostics: manifest::lint::lint(&content, path.as_deref(), &catalog),
+    })
+}
+
+/// Semantic diagnostics for manifest YAML (positions for the editor to highlight)
+#[tauri::command]
+async fn lint_manifest(
+    content: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<manifest::lint::Diagnostic>, String> {
+    let path = path.map(PathBuf::from);
+    Ok(manifest::lint::lint(
+        &content,
+        path.as_deref(),
+        &state.model_catalog(),
+    ))
 }
 
 /// Get the input_label from a manifest file (for dynamic placeholder text)
@@ -1566,11 +1966,8 @@ async fn get_manifest_input_label(path: String) -> Result<Option<String>, String
         return Ok(None);
     }
 
-    let content =
-        fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read manifest: {}", e))?;
-
-    let manifest: Manifest =
-        serde_yaml::from_str(&content).map_err(|e| format!("Invalid manifest YAML: {}", e))?;
+    let manifest = Manifest::load_from_file(&path_buf)
+        .map_err(|e| format!("Invalid manifest YAML: {:#}", e))?;
 
     Ok(manifest.manifest.input_label)
 }
@@ -1589,24 +1986,26 @@ async fn get_manifest_name(path: String) -> Result<String, String> {
         return Ok(filename);
     }
 
-    let content =
-        fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read manifest: {}", e))?;
-
-    let manifest: Manifest =
-        serde_yaml::from_str(&content).map_err(|e| format!("Invalid manifest YAML: {}", e))?;
+    let manifest = Manifest::load_from_file(&path_buf)
+        .map_err(|e| format!("Invalid manifest YAML: {:#}", e))?;
 
     Ok(manifest.manifest.name)
 }
 
 /// Save manifest content to file
+/// When logged in, the save is also recorded as a version in the manifest library
 #[tauri::command]
-async fn save_manifest_file(path: String, content: String) -> Result<(), String> {
-    // First validate the content
-    let _manifest: Manifest =
-        serde_yaml::from_str(&content).map_err(|e| format!("Invalid YAML - cannot save: {}", e))?;
+async fn save_manifest_file(
+    path: String,
+    content: String,
+    auth_state: State<'_, AuthState>,
+) -> Result<Option<ManifestVersionSummary>, String> {
+    // First validate the content (imports/extends relative to where it will be saved)
+    let path_buf = PathBuf::from(&path);
+    let manifest = Manifest::parse(&content, Some(&path_buf))
+        .map_err(|e| format!("Invalid YAML - cannot save: {:#}", e))?;
 
     // Create parent directories if needed
-    let path_buf = PathBuf::from(&path);
     if let Some(parent) = path_buf.parent() {
         if !parent.exists() {
             fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
@@ -1616,7 +2015,126 @@ async fn save_manifest_file(path: String, content: String) -> Result<(), String>
     // Write the file
     fs::write(&path_buf, &content).map_err(|e| format!("Failed to write manifest file: {}", e))?;
 
-    Ok(())
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    if !manager.is_logged_in() {
+        return Ok(None);
+    }
+    let snapshot =
+        ManifestSnapshot::new(&content, &manifest, Some(&path_buf)).map_err(|e| e.to_string())?;
+    manager
+        .record_manifest_version(&snapshot)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+// ------------------------------------------------------------------
+// 3h. Manifest Library Commands (version history)
+// ------------------------------------------------------------------
+
+/// List the manifests in the user's library
+#[tauri::command]
+async fn list_manifest_library(
+    auth_state: State<'_, AuthState>,
+) -> Result<Vec<ManifestSummary>, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager.list_manifests().map_err(|e| e.to_string())
+}
+
+/// Saved versions of the manifest at `path` (newest first; empty if never saved in the app)
+#[tauri::command]
+async fn get_manifest_history(
+    path: String,
+    auth_state: State<'_, AuthState>,
+) -> Result<Vec<ManifestVersionSummary>, String> {
+    let manifest = Manifest::load_from_file(Path::new(&path))
+        .map_err(|e| format!("Invalid manifest YAML: {:#}", e))?;
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager
+        .list_manifest_versions(&manifest.manifest.id)
+        .map_err(|e| e.to_string())
+}
+
+/// Get a manifest version with its content (e.g. the version a session ran)
+#[tauri::command]
+async fn get_manifest_version(
+    version_id: i64,
+    auth_state: State<'_, AuthState>,
+) -> Result<Option<ManifestVersion>, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    manager
+        .get_manifest_version(version_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Unified diff between two manifest versions (empty when identical)
+#[tauri::command]
+async fn diff_manifest_versions(
+    from_version_id: i64,
+    to_version_id: i64,
+    auth_state: State<'_, AuthState>,
+) -> Result<String, String> {
+    let manager = auth_state
+        .manager
+        .lock()
+        .map_err(|_| "Failed to lock auth state")?;
+    let load = |id: i64| {
+        manager
+            .get_manifest_version(id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Manifest version {} not found", id))
+    };
+    let (from, to) = (load(from_version_id)?, load(to_version_id)?);
+
+    let label =
+        |v: &ManifestVersion| format!("{} v{} (rev {})", v.manifest_key, v.version, v.revision);
+    Ok(history::unified_diff(
+        &from.content,
+        &to.content,
+        &label(&from),
+        &label(&to),
+    ))
+}
+
+/// Draft a manifest from a natural-language brief, linted and repaired until valid
+/// (the draft is returned with its remaining problems if repairs run out)
+#[tauri::command]
+async fn generate_manifest(
+    brief: ManifestBrief,
+    model: String,
+    state: State<'_, AppState>,
+) -> Result<GeneratedManifest, String> {
+    let api_key = {
+        let config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        resolve_api_key(&config, &model)?
+    };
+
+    let mut llm_client = llm::LLMClient::new(api_key)
+        .with_mock_config(state.mock_config())
+        .with_gemini_config(state.gemini_config());
+
+    generator::generate_manifest(&mut llm_client, &model, &brief, &state.model_catalog())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// JSON Schema for manifest YAML (editor completion and validation)
+#[tauri::command]
+async fn get_manifest_json_schema(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    Ok(manifest::json_schema::manifest_json_schema(
+        &state.model_catalog(),
+    ))
 }
 
 /// Get a default manifest template for creating new manifests
@@ -1679,40 +2197,78 @@ quality_gates:
     .to_string())
 }
 
+#[derive(Clone, Serialize)]
+struct FollowupTokenPayload {
+    session_id: Option<i64>,
+    token: String,
+}
+
+/// Answer a follow-up question, streaming tokens as `followup-token` events.
+/// With a session, prior follow-ups are loaded from `session_conversations` and
+/// both turns are persisted; without one (standalone chat), the UI passes `history`.
 #[tauri::command]
 async fn send_followup(
+    session_id: Option<i64>,
     question: String,
     context: String,
     model: String,
+    history: Option<Vec<FollowupTurn>>,
+    app: AppHandle,
     state: State<'_, AppState>,
+    auth_state: State<'_, AuthState>,
 ) -> Result<String, String> {
     let api_key = {
         let config = state.config.lock().map_err(|_| "Failed to lock state")?;
-        config
-            .api_key
-            .clone()
-            .ok_or("API Key not set. Please configure in settings.")?
+        resolve_api_key(&config, &model)?
     };
 
-    let mut llm_client = llm::LLMClient::new(api_key);
+    let history = match session_id {
+        Some(id) => {
+            let manager = auth_state
+                .manager
+                .lock()
+                .map_err(|_| "Failed to lock auth state")?;
+            manager
+                .get_session_conversation(id, Some(FOLLOWUP_PHASE_ID), None)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|m| FollowupTurn::new(m.role, m.content))
+                .collect()
+        }
+        None => history.unwrap_or_default(),
+    };
 
-    let system_prompt = "You are a helpful assistant analyzing business intelligence reports. \
-        The user has generated a research report and wants to ask follow-up questions. \
-        Use the provided context to give accurate, relevant answers."
-        .to_string();
+    let mut llm_client = llm::LLMClient::new(api_key)
+        .with_mock_config(state.mock_config())
+        .with_gemini_config(state.gemini_config());
 
-    let user_prompt = format!(
-        "Here is the generated report:\n\n{}\n\n---\n\nUser question: {}",
-        context, question
-    );
+    let req = followup::build_followup_request(&model, &context, &history, &question);
+    let mut stream = llm_client
+        .generate_multi_turn_stream(req)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let req = llm::LLMRequest {
-        system: system_prompt,
-        user: user_prompt,
-        model,
-    };
+    let mut answer = String::new();
+    while let Some(chunk) = stream.next().await {
+        let token = chunk.map_err(|e| e.to_string())?;
+        answer.push_str(&token);
+        let _ = app.emit("followup-token", FollowupTokenPayload { session_id, token });
+    }
 
-    llm_client.generate(req).await.map_err(|e| e.to_string())
+    if let Some(id) = session_id {
+        let manager = auth_state
+            .manager
+            .lock()
+            .map_err(|_| "Failed to lock auth state")?;
+        manager
+            .add_session_message(id, Some(FOLLOWUP_PHASE_ID), "user", &question)
+            .and_then(|_| {
+                manager.add_session_message(id, Some(FOLLOWUP_PHASE_ID), "assistant", &answer)
+            })
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(answer)
 }
 
 /// Run a single phase with custom prompts (IM-5045: Phase Relaunch)
@@ -1731,10 +2287,7 @@ async fn run_single_phase(
     // 1. Get API key from state
     let api_key = {
         let config = state.config.lock().map_err(|_| "Failed to lock state")?;
-        config
-            .api_key
-            .clone()
-            .ok_or("API Key not set. Please configure in settings.")?
+        resolve_api_key(&config, &model)?
     };
 
     // 2. Mark phase as "running" and save the prompts
@@ -1758,18 +2311,37 @@ async fn run_single_phase(
     }
 
     // 3. Make the LLM API call
-    let mut llm_client = llm::LLMClient::new(api_key);
+    let mut llm_client = llm::LLMClient::new(api_key)
+        .with_mock_config(state.mock_config())
+        .with_gemini_config(state.gemini_config());
     let req = llm::LLMRequest {
         system: system_prompt.clone(),
         user: user_input.clone(),
         model,
+        cache_config: state.prompt_cache(),
+        ..Default::default()
     };
 
+    let started_at = chrono::Utc::now();
     let result = llm_client.generate(req).await;
+    if let Some(usage) = llm_client.last_usage() {
+        tracing::info!(
+            session_id,
+            phase_id,
+            input_tokens = usage.input_tokens,
+            output_tokens = usage.output_tokens,
+            cache_write_tokens = usage.cache_creation_input_tokens,
+            cache_read_tokens = usage.cache_read_input_tokens,
+            "Phase token usage"
+        );
+    }
 
     // 4. Save the result (success or failure)
     match result {
-        Ok(output) => {
+        Ok(generation) => {
+            let metrics = PhaseMetrics::from_generation(&generation, started_at, None, 0);
+            let truncated = generation.is_truncated();
+            let output = generation.text;
             // Save successful output
             let manager = auth_state
                 .manager
@@ -1787,6 +2359,19 @@ async fn run_single_phase(
                     None,
                 )
                 .map_err(|e| format!("Failed to save phase output: {}", e))?;
+            manager
+                .update_phase_output_meta(
+                    session_id,
+                    &phase_id,
+                    &PhaseOutputMeta {
+                        cache_hit: Some(generation.cache_hit),
+                        reasoning: generation.reasoning,
+                        stop_reason: generation.stop_reason.map(|r| r.as_str().to_string()),
+                        truncated: Some(truncated),
+                        metrics: Some(metrics),
+                    },
+                )
+                .map_err(|e| format!("Failed to save phase output: {}", e))?;
             Ok(output)
         }
         Err(e) => {
@@ -1813,22 +2398,87 @@ async fn run_single_phase(
     }
 }
 
+/// Render every phase's prompts and estimate tokens/cost without calling any provider.
+/// Uses the same model resolution as run_research; no API key is required.
+#[tauri::command]
+async fn dry_run_research(
+    company: String,
+    model: Option<String>,
+    manifest_path_override: Option<String>,
+    inputs: Option<HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<DryRunReport, String> {
+    let manifest_path = {
+        let config = state.config.lock().map_err(|_| "Failed to lock state")?;
+        manifest_path_override
+            .map(PathBuf::from)
+            .or(config.last_manifest_path.clone())
+            .ok_or("Manifest path not found.")?
+    };
+
+    if !manifest_path.exists() {
+        return Err(format!("Manifest not found at: {:?}", manifest_path));
+    }
+    let manifest = Manifest::load_from_file(&manifest_path).map_err(|e| e.to_string())?;
+    let inputs = manifest
+        .resolve_inputs(&inputs.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+
+    let mut agent = Agent::new(manifest, String::new(), None, model, None)
+        .with_model_catalog(state.model_catalog())
+        .with_inputs(inputs)
+        .with_dry_run(true);
+    agent
+        .run_workflow(&company)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    agent
+        .dry_run_report()
+        .cloned()
+        .ok_or_else(|| "Dry run produced no report".to_string())
+}
+
+/// Deliver the reviewer's decision to a run waiting at an approval checkpoint
+/// (approve, edit the output then approve, or reject with feedback to re-run the phase).
+/// Returns the id of the phase the decision applies to.
+#[tauri::command]
+fn review_phase(
+    run_id: String,
+    decision: ApprovalDecision,
+    approvals: State<'_, ApprovalRegistry>,
+) -> Result<String, String> {
+    approvals
+        .submit(&run_id, decision)
+        .map_err(|e| e.to_string())
+}
+
+/// Token usage for a finished run (sent as the `token-usage` event)
+#[derive(Clone, Serialize)]
+struct TokenUsagePayload {
+    #[serde(flatten)]
+    scope: agent::EventScope,
+    #[serde(flatten)]
+    usage: llm::TokenUsage,
+}
+
 #[tauri::command]
 async fn run_research(
     company: String,
     model: String,
     manifest_path_override: Option<String>,
+    attachments: Option<Vec<String>>,
+    run_id: Option<String>,
+    inputs: Option<HashMap<String, String>>,
     app: AppHandle,
     state: State<'_, AppState>,
     auth_state: State<'_, AuthState>,
+    approvals: State<'_, ApprovalRegistry>,
 ) -> Result<String, String> {
     // 1. Retrieve Credentials from State
     let (api_key, manifest_path) = {
         let config = state.config.lock().map_err(|_| "Failed to lock state")?;
-        let key = config
-            .api_key
-            .clone()
-            .ok_or("API Key not set. Please configure in settings.")?;
+        let key = resolve_api_key(&config, &model)?;
 
         // Use override if provided, otherwise use saved path
         let path = manifest_path_override
@@ -1843,7 +2493,36 @@ async fn run_research(
     if !manifest_path.exists() {
         return Err(format!("Manifest not found at: {:?}", manifest_path));
     }
-    let manifest = Manifest::load_from_file(&manifest_path).map_err(|e| e.to_string())?;
+    let manifest_content = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read manifest file: {}", e))?;
+    let manifest =
+        Manifest::parse(&manifest_content, Some(&manifest_path)).map_err(|e| e.to_string())?;
+
+    // 2a. Validate the manifest's declared inputs
+    let mut inputs = manifest
+        .resolve_inputs(&inputs.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+
+    // File inputs are sent as attachments; phases see the file name, as with other session files
+    let mut attachment_paths = attachments.unwrap_or_default();
+    for field in manifest
+        .inputs
+        .iter()
+        .filter(|f| f.r#type == InputType::File)
+    {
+        if let Some(value) = inputs.get_mut(&field.name) {
+            attachment_paths.push(value.clone());
+            if let Some(name) = Path::new(value.as_str()).file_name() {
+                *value = name.to_string_lossy().to_string();
+            }
+        }
+    }
+
+    // 2b. Load attached files up front so a bad file fails before any LLM call
+    let attachments = attachment_paths
+        .iter()
+        .map(|path| Attachment::from_file(path).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, String>>()?;
 
     // 3. Create research session for persistence (L1-ARCHITECTURE Section 5.3 requirement)
     // This enables auto-save of every phase completion to SQLite
@@ -1861,16 +2540,49 @@ async fn run_research(
         if manager.is_logged_in() {
             match manager.create_research_session(&company, &model, manifest_name.as_deref()) {
                 Ok(id) => {
-                    println!("[RESEARCH] Created session {} for company: {}", id, company);
+                    tracing::info!(session_id = id, "Created research session for {}", company);
+                    // Pin the exact manifest version this run uses (audit / reproducibility)
+                    let version =
+                        ManifestSnapshot::new(&manifest_content, &manifest, Some(&manifest_path))
+                            .map_err(|e| e.to_string())
+                            .and_then(|snapshot| {
+                                manager
+                                    .record_manifest_version(&snapshot)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .and_then(|version| {
+                                manager
+                                    .set_session_manifest_version(id, version.id)
+                                    .map_err(|e| e.to_string())
+                            });
+                    if let Err(e) = version {
+                        tracing::warn!(session_id = id, "Could not record manifest version: {}", e);
+                    }
+                    for attachment in &attachments {
+                        if let Err(e) = manager.add_session_attachment(
+                            id,
+                            &attachment.name,
+                            &attachment.path.to_string_lossy(),
+                            &attachment.media_type,
+                            attachment.size_bytes as i64,
+                        ) {
+                            tracing::warn!(
+                                session_id = id,
+                                "Could not record attachment {}: {}",
+                                attachment.name,
+                                e
+                            );
+                        }
+                    }
                     Some(id)
                 }
                 Err(e) => {
-                    eprintln!("[RESEARCH] Warning: Could not create session: {}", e);
+                    tracing::warn!("Could not create research session: {}", e);
                     None
                 }
             }
         } else {
-            println!("[RESEARCH] No user logged in, skipping session persistence");
+            tracing::info!("No user logged in, skipping session persistence");
             None
         }
     };
@@ -1885,13 +2597,62 @@ async fn run_research(
         Some(app.clone()),
         Some(model),
         session_id,
-    );
+    )
+    .with_mock_config(state.mock_config())
+    .with_gemini_config(state.gemini_config())
+    .with_model_catalog(state.model_catalog())
+    .with_token_batching(state.token_batching())
+    .with_prompt_cache(state.prompt_cache())
+    .with_inputs(inputs)
+    .with_attachments(attachments);
+    if let Some(cache) = state.response_cache() {
+        agent = agent.with_response_cache(cache);
+    }
+    // Events carry the run id so the UI can follow this run among concurrent ones
+    if let Some(run_id) = run_id {
+        agent = agent.with_run_id(run_id);
+    }
+
+    // 4a. Phases with `approval: required` wait here for review_phase decisions;
+    // the session shows 'awaiting_approval' while they do
+    let gate = approvals.open(agent.run_id());
+    let gate = match session_id {
+        Some(sid) => {
+            let app = app.clone();
+            gate.with_status_hook(Box::new(move |status, phase_id| {
+                let auth_state = app.state::<AuthState>();
+                let updated = match auth_state.manager.lock() {
+                    Ok(manager) => manager
+                        .update_research_session(sid, status, Some(phase_id))
+                        .map_err(|e| e.to_string()),
+                    Err(_) => Err("Failed to lock auth state".to_string()),
+                };
+                if let Err(e) = updated {
+                    tracing::warn!(session_id = sid, "Could not update session status: {}", e);
+                }
+            }))
+        }
+        None => gate,
+    };
+    agent = agent.with_approval_gate(gate);
 
     // 5. Execute Workflow (The Heavy Lifting)
     // This runs the phases defined in the YAML
     let workflow_result = agent.run_workflow(&company).await;
+    approvals.close(agent.run_id());
+
+    // 5a. Token usage summed over the run's phases, including prompt cache writes/reads
+    if let Err(e) = app.emit(
+        "token-usage",
+        TokenUsagePayload {
+            scope: agent.event_scope(),
+            usage: agent.token_usage().clone(),
+        },
+    ) {
+        tracing::warn!("Failed to emit token-usage: {}", e);
+    }
 
-    // 6. Update session status based on workflow result
+    // 6. Update session status based on workflow result, and store the run log
     if let Some(sid) = session_id {
         let manager = auth_state
             .manager
@@ -1900,13 +2661,16 @@ async fn run_research(
         match &workflow_result {
             Ok(_) => {
                 let _ = manager.update_research_session(sid, "completed", None);
-                println!("[RESEARCH] Session {} marked as completed", sid);
+                tracing::info!(session_id = sid, "Session marked as completed");
             }
-            Err(_) => {
+            Err(e) => {
                 let _ = manager.update_research_session(sid, "failed", None);
-                println!("[RESEARCH] Session {} marked as failed", sid);
+                tracing::warn!(session_id = sid, "Session marked as failed: {}", e);
             }
         }
+        if let Err(e) = manager.append_run_log(sid, agent.run_log()) {
+            tracing::warn!(session_id = sid, "Could not store run log: {}", e);
+        }
     }
 
     // 7. Return result
@@ -1926,7 +2690,33 @@ async fn run_research(
 // 4. Main Entry Point & Setup
 // ------------------------------------------------------------------
 
+/// `manifest-schema [path]`: print the manifest JSON Schema, or write it to `path`
+/// (uses the built-in model catalog)
+fn export_manifest_schema(path: Option<&String>) -> Result<(), String> {
+    let schema = manifest::json_schema::manifest_json_schema(&ModelCatalog::builtin());
+    let json = serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?;
+    match path {
+        Some(path) => {
+            fs::write(path, json + "\n").map_err(|e| format!("Failed to write {}: {}", path, e))
+        }
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
 fn main() {
+    // CLI subcommands run without starting the app
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("manifest-schema") {
+        if let Err(e) = export_manifest_schema(args.get(1)) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -1943,6 +2733,14 @@ fn main() {
             }
             let config_path = app_dir.join("config.json");
 
+            // A2. Structured logging: stdout + daily rolling file in app_data/logs
+            match telemetry::init(&app_dir.join(telemetry::LOG_DIR)) {
+                Ok(guard) => {
+                    app.manage(LogGuard { _guard: guard });
+                }
+                Err(e) => tracing::warn!("Logging to file disabled: {}", e),
+            }
+
             // B. Load or Create Config
             let mut config: AppConfig = if config_path.exists() {
                 let content = fs::read_to_string(&config_path).unwrap_or_default();
@@ -1984,6 +2782,11 @@ fn main() {
                 }
             }
 
+            // B3. Seed the editable model catalog (app_data/models.yaml)
+            if let Err(e) = ModelCatalog::ensure_file(app_dir.join("models.yaml")) {
+                tracing::warn!("Could not write model catalog: {}", e);
+            }
+
             // C. Manage State (Inject into Tauri)
             app.manage(AppState {
                 config: Mutex::new(config),
@@ -2000,6 +2803,9 @@ fn main() {
                 manager: Mutex::new(auth_manager),
             });
 
+            // Approval checkpoints of the runs in flight
+            app.manage(ApprovalRegistry::default());
+
             println!("[DEBUG] Auth system initialized at: {:?}", auth_db_path);
 
             // E. Set Window Icon (for dev mode - title bar and taskbar)
@@ -2077,11 +2883,15 @@ fn main() {
             save_phase_output,
             get_phase_outputs,
             get_last_completed_phase,
+            get_latency_stats,
             // Session conversation commands (IM-5031, IM-5032)
             add_session_message,
             get_session_conversation,
+            get_session_attachments,
+            get_session_run_log,
             // Session resume command (IM-5020)
             resume_research_session,
+            review_session_phase,
             // Project management commands
             create_project,
             list_projects,
@@ -2105,6 +2915,12 @@ fn main() {
             export_all_phases_as_markdown,
             // Legacy config commands
             set_api_key,
+            set_response_cache,
+            clear_response_cache,
+            set_gemini_config,
+            set_token_batching,
+            set_prompt_cache,
+            get_model_catalog,
             get_app_state,
             set_manifest_path,
             get_manifest_phases,
@@ -2115,13 +2931,23 @@ fn main() {
             // Manifest content management commands
             load_manifest_file,
             validate_manifest,
+            lint_manifest,
             get_manifest_input_label,
             get_manifest_name,
             save_manifest_file,
             get_default_manifest_template,
+            get_manifest_json_schema,
+            generate_manifest,
+            // Manifest library commands
+            list_manifest_library,
+            get_manifest_history,
+            get_manifest_version,
+            diff_manifest_versions,
             send_followup,
             run_single_phase,
-            run_research
+            dry_run_research,
+            run_research,
+            review_phase
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,26 +1,46 @@
 #![allow(dead_code)]
-use anyhow::{Context, Result};
+use crate::llm::ReasoningConfig;
+use anyhow::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
+pub mod compose;
+pub mod history;
+pub mod json_schema;
+pub mod lint;
+
 // ------------------------------------------------------------------
 // Data Structures (Matching the YAML Schema)
 // ------------------------------------------------------------------
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Research manifest: a multi-phase research workflow
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct Manifest {
     pub manifest: ManifestHeader,
+    /// Research inputs the user fills in (empty = one free-text input, see `input_label`)
+    #[serde(default)]
+    pub inputs: Vec<InputField>,
+    /// Named output shapes that phases reference in `output_schema`
     pub schemas: HashMap<String, DataSchema>,
+    /// Workflow steps, run in order
     pub phases: Vec<Phase>,
+    /// Checks applied to phase outputs
     pub quality_gates: Vec<QualityGate>,
+    /// How the final report is assembled (default: every phase output as a section)
+    #[serde(default)]
+    pub report: Option<ReportTemplate>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Identity and description of the manifest
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct ManifestHeader {
+    /// Stable identifier (the manifest library key)
     pub id: String,
+    /// Semantic version, e.g. "1.2.0"
     pub version: String,
+    /// Display name
     pub name: String,
     pub description: String,
     /// Optional label for the research subject input field (e.g., "industry or segment" instead of "company name")
@@ -28,47 +48,258 @@ pub struct ManifestHeader {
     pub input_label: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Value type of a declared research input
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InputType {
+    #[default]
+    String,
+    Url,
+    Enum,
+    Number,
+    /// YYYY-MM-DD
+    Date,
+    /// Path on disk; attached to the session (phases opt in via `attachments`)
+    File,
+}
+
+/// One named research input; its value is placed in the context under `name`
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct InputField {
+    pub name: String,
+    #[serde(default)]
+    pub r#type: InputType,
+    /// Form label (defaults to the name)
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+    /// Allowed values for `enum` inputs
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Hint shown under the form field
+    #[serde(default)]
+    pub help: Option<String>,
+}
+
+/// Output shape: the fields a phase's JSON output must contain
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct DataSchema {
     pub fields: Vec<SchemaField>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct SchemaField {
     pub name: String,
+    /// Allowed values for the field
     #[serde(default)]
     pub r#enum: Option<Vec<String>>, // 'enum' is a reserved keyword in Rust
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// One workflow step: an LLM call with its instructions and context
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct Phase {
+    /// Unique phase id, e.g. "PHASE-01"
     pub id: String,
     pub name: String,
+    /// Tools the phase may use (informational)
     #[serde(default)]
     pub tools: Vec<String>,
+    /// Phases that must run before this one (checked when the manifest is validated)
     #[serde(default)]
     pub dependencies: Vec<String>,
+    /// Prompt for the phase
     pub instructions: String,
+    /// Context key the phase reads (an input, or an earlier phase's output)
     #[serde(default)]
     pub input: Option<String>,
+    /// Name of an entry in `schemas` describing the output
     #[serde(default)]
     pub output_schema: Option<String>,
+    /// Context key the output is stored under (defaults to `output_schema`)
     #[serde(default)]
     pub output_target: Option<String>,
     #[serde(default)]
     pub output_format: Option<String>,
+    /// Lookup tables for the phase (table name -> key -> value; informational)
     #[serde(default)]
     pub logic_map: Option<HashMap<String, HashMap<String, String>>>,
-    /// LLM model to use for this phase (e.g., "claude-3-5-sonnet", "gemini-1.5-flash")
-    /// If not specified, defaults to "claude-3-5-sonnet"
+    /// LLM model to use for this phase (e.g., "claude-sonnet-4-5-20250929", "gemini-2.5-flash")
+    /// If not specified, defaults to `model_catalog::DEFAULT_MODEL`
     #[serde(default)]
     pub model: Option<String>,
+    /// Set to false to always call the provider, even when the response cache is enabled
+    #[serde(default)]
+    pub cache: Option<bool>,
+    /// Session attachments sent with this phase's input (file names, or "*" for all)
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Extended thinking budget (Claude) and/or reasoning effort (OpenAI o-series)
+    #[serde(default)]
+    pub reasoning: Option<ReasoningConfig>,
+    /// Continuation requests when the output stops at max_tokens (default 2; 0 keeps the
+    /// truncated output, flagged as incomplete)
+    #[serde(default)]
+    pub max_continuations: Option<u32>,
+    /// Run the phase once per item of a list in an earlier phase's output
+    #[serde(default)]
+    pub foreach: Option<ForEach>,
+    /// Run the phase on several models and combine their outputs
+    #[serde(default)]
+    pub ensemble: Option<Ensemble>,
+    /// Hold the workflow after this phase until its output is reviewed
+    #[serde(default)]
+    pub approval: Approval,
+    /// File the phase was defined in (set when loaded through `compose`)
+    #[serde(skip)]
+    pub source: Option<PhaseSource>,
+}
+
+/// Review checkpoint after a phase (see `approval`)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Approval {
+    /// Continue as soon as the phase completes
+    #[default]
+    None,
+    /// Wait for the reviewer to approve, edit or reject the output
+    Required,
+}
+
+/// Fan-out of a phase over a list: each item is sent as the phase input (after the
+/// `input` text, if set) and the outputs are stored as a JSON array
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct ForEach {
+    /// Context key of a structured output, then the path to the list within it,
+    /// e.g. "CompetitorList.competitors" (the key alone if the output is a list)
+    pub items: String,
+    /// Items run at the same time (default 3)
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+impl ForEach {
+    /// The context key the items are read from
+    pub fn source_key(&self) -> &str {
+        self.items.split('.').next().unwrap_or_default()
+    }
+
+    /// The list at `items` in the source output (JSON, optionally in a ``` fence)
+    pub fn resolve_items(&self, output: &str) -> Result<Vec<serde_json::Value>> {
+        let mut value = parse_json_output(output)
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not JSON output", self.source_key()))?;
+        for field in self.items.split('.').skip(1) {
+            value = value
+                .get_mut(field)
+                .map(serde_json::Value::take)
+                .ok_or_else(|| anyhow::anyhow!("'{}' has no field '{}'", self.items, field))?;
+        }
+        match value {
+            serde_json::Value::Array(items) => Ok(items),
+            _ => anyhow::bail!("'{}' is not a list", self.items),
+        }
+    }
+}
+
+/// JSON in a phase output: the whole text, a fenced block, or the outermost {...}/[...]
+pub fn parse_json_output(output: &str) -> Option<serde_json::Value> {
+    let trimmed = output.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+    let start = trimmed.find(['{', '['])?;
+    let end = trimmed.rfind(['}', ']'])?;
+    serde_json::from_str(trimmed.get(start..=end)?).ok()
+}
+
+/// Multi-model ensemble: the phase runs on every model in parallel, then the
+/// candidate outputs are combined by `strategy`
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct Ensemble {
+    /// Models the phase runs on (any supported provider), e.g. one Claude, one GPT, one Gemini
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub strategy: EnsembleStrategy,
+    /// Model that selects or merges the candidates (defaults to the phase model)
+    #[serde(default)]
+    pub judge_model: Option<String>,
+}
+
+/// How the candidates of an ensemble phase become the phase output
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EnsembleStrategy {
+    /// Majority value of each field of the structured output (requires `output_schema`)
+    Vote,
+    /// A judge model picks the best candidate
+    #[default]
+    Select,
+    /// A judge model merges the candidates into one output
+    Merge,
+}
+
+impl EnsembleStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnsembleStrategy::Vote => "vote",
+            EnsembleStrategy::Select => "select",
+            EnsembleStrategy::Merge => "merge",
+        }
+    }
+}
+
+/// `dependencies` / report `include` value meaning "every phase"
+pub const ALL_PHASES: &str = "ALL";
+
+/// Assembly of the final report (`markdown_file`): a cover page, a template and
+/// phase sections, in that order (see `report`). Placeholders `{{...}}` name a phase
+/// id, a context key, a field of a structured output (`Situation.situation`) or `date`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
+pub struct ReportTemplate {
+    /// Cover page (markdown), separated from the report by a rule
+    #[serde(default)]
+    pub cover: Option<String>,
+    /// Report body (markdown); defaults to `{{markdown_file}}`, the drafting phase's
+    /// output, when no phases are included
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Phase ids whose outputs follow the body as `## name` sections ("ALL" for every phase)
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Phase ids left out of `include` (e.g. raw research under "ALL")
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl ReportTemplate {
+    /// Whether a phase's output is added as a section
+    pub fn includes(&self, phase_id: &str) -> bool {
+        self.include
+            .iter()
+            .any(|id| id == phase_id || id == ALL_PHASES)
+            && !self.exclude.iter().any(|id| id == phase_id)
+    }
+}
+
+/// Provenance of a phase in a composed manifest (see `compose`)
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct PhaseSource {
+    pub file: String,
+    /// Files that override some of its keys (`extends`), in order
+    #[serde(default)]
+    pub overridden_in: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Check applied to a phase's output
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct QualityGate {
+    /// Phase id the gate applies to
     pub phase: String,
+    /// What the output must satisfy
     pub check: String,
+    /// Action when the check fails, e.g. "RETRY"
     pub fail_action: String,
 }
 
@@ -77,21 +308,134 @@ pub struct QualityGate {
 // ------------------------------------------------------------------
 
 impl Manifest {
-    /// Load and parse a manifest file from disk
+    /// Load and parse a manifest file from disk, resolving `imports` and `extends`
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read manifest file: {:?}", path.as_ref()))?;
-
-        let manifest: Manifest =
-            serde_yaml::from_str(&content).with_context(|| "Failed to parse YAML manifest")?;
+        compose::load(path.as_ref())
+    }
 
-        Ok(manifest)
+    /// Parse manifest YAML that lives (or will be saved) at `path`; relative
+    /// `imports`/`extends` paths are resolved from its directory
+    pub fn parse(content: &str, path: Option<&Path>) -> Result<Self> {
+        compose::parse(content, path)
     }
 
     /// Get a specific phase by ID
     pub fn get_phase(&self, id: &str) -> Option<&Phase> {
         self.phases.iter().find(|p| p.id == id)
     }
+
+    /// Validate user-supplied input values against the declared `inputs`, filling in
+    /// defaults. Returns the value for each input that has one, keyed by input name.
+    pub fn resolve_inputs(
+        &self,
+        values: &HashMap<String, String>,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut resolved = BTreeMap::new();
+        let mut problems = Vec::new();
+
+        for name in values.keys() {
+            if !self.inputs.iter().any(|field| &field.name == name) {
+                problems.push(format!("Unknown input '{}'", name));
+            }
+        }
+
+        for field in &self.inputs {
+            let value = values
+                .get(&field.name)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .or_else(|| field.default_value());
+            match value {
+                Some(value) => match field.check(&value) {
+                    Ok(()) => {
+                        resolved.insert(field.name.clone(), value);
+                    }
+                    Err(e) => problems.push(format!("{}: {}", field.display_name(), e)),
+                },
+                None if field.required => {
+                    problems.push(format!("{}: a value is required", field.display_name()))
+                }
+                None => {}
+            }
+        }
+
+        if !problems.is_empty() {
+            anyhow::bail!("Invalid inputs: {}", problems.join("; "));
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve a phase's output_schema to its JSON Schema form (None if unset or undefined)
+    pub fn output_json_schema(&self, phase: &Phase) -> Option<serde_json::Value> {
+        phase
+            .output_schema
+            .as_ref()
+            .and_then(|name| self.schemas.get(name))
+            .map(|schema| schema.to_json_schema())
+    }
+}
+
+impl InputField {
+    pub fn display_name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The declared default as text (numbers and booleans are written as in YAML)
+    pub fn default_value(&self) -> Option<String> {
+        match self.default.as_ref()? {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// Check a (non-empty) value against this input's type
+    pub fn check(&self, value: &str) -> std::result::Result<(), String> {
+        match self.r#type {
+            InputType::String => Ok(()),
+            InputType::Url => match reqwest::Url::parse(value) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+                _ => Err(format!("'{}' is not an http(s) URL", value)),
+            },
+            InputType::Enum if self.options.iter().any(|o| o == value) => Ok(()),
+            InputType::Enum => Err(format!(
+                "'{}' is not one of: {}",
+                value,
+                self.options.join(", ")
+            )),
+            InputType::Number => value
+                .parse::<f64>()
+                .map(|_| ())
+                .map_err(|_| format!("'{}' is not a number", value)),
+            InputType::Date => chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|_| ())
+                .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD)", value)),
+            InputType::File if Path::new(value).is_file() => Ok(()),
+            InputType::File => Err(format!("file not found: {}", value)),
+        }
+    }
+}
+
+impl DataSchema {
+    /// Convert to a JSON Schema object (all fields are strings; enums become `enum` constraints)
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        for field in &self.fields {
+            let mut property = serde_json::json!({ "type": "string" });
+            if let Some(values) = &field.r#enum {
+                property["enum"] = serde_json::json!(values);
+            }
+            properties.insert(field.name.clone(), property);
+        }
+
+        let required: Vec<&str> = self.fields.iter().map(|f| f.name.as_str()).collect();
+
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required
+        })
+    }
 }
 
 // ------------------------------------------------------------------
@@ -143,4 +487,108 @@ quality_gates:
             "test_field"
         );
     }
+
+    #[test]
+    fn test_resolve_inputs_validates_and_fills_defaults() {
+        let yaml_content = r#"
+manifest:
+  id: "PROTO-INPUTS"
+  version: "1.0.0"
+  name: "Inputs"
+  description: "Typed inputs."
+inputs:
+  - name: company
+    required: true
+  - name: region
+    type: enum
+    options: [EMEA, APAC, AMER]
+    default: EMEA
+  - name: website
+    type: url
+  - name: horizon_years
+    type: number
+    default: 3
+  - name: as_of
+    type: date
+schemas: {}
+phases: []
+quality_gates: []
+"#;
+        let manifest: Manifest = serde_yaml::from_str(yaml_content).unwrap();
+        let values = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let resolved = manifest
+            .resolve_inputs(&values(&[("company", "Acme"), ("website", "")]))
+            .unwrap();
+        assert_eq!(resolved.get("company").unwrap(), "Acme");
+        assert_eq!(resolved.get("region").unwrap(), "EMEA");
+        assert_eq!(resolved.get("horizon_years").unwrap(), "3");
+        assert!(!resolved.contains_key("website"));
+
+        let err = manifest
+            .resolve_inputs(&values(&[
+                ("region", "LATAM"),
+                ("website", "acme.com"),
+                ("as_of", "2025-13-01"),
+                ("color", "red"),
+            ]))
+            .unwrap_err()
+            .to_string();
+        for expected in [
+            "company: a value is required",
+            "LATAM",
+            "acme.com",
+            "2025-13-01",
+            "Unknown input 'color'",
+        ] {
+            assert!(err.contains(expected), "{} missing from {}", expected, err);
+        }
+    }
+
+    #[test]
+    fn test_data_schema_to_json_schema() {
+        let schema = DataSchema {
+            fields: vec![
+                SchemaField {
+                    name: "company_name".to_string(),
+                    r#enum: None,
+                },
+                SchemaField {
+                    name: "urgency_level".to_string(),
+                    r#enum: Some(vec!["HIGH".to_string(), "LOW".to_string()]),
+                },
+            ],
+        };
+
+        let json = schema.to_json_schema();
+
+        assert_eq!(json["type"], "object");
+        assert_eq!(json["properties"]["company_name"]["type"], "string");
+        assert_eq!(json["properties"]["urgency_level"]["enum"][1], "LOW");
+        assert_eq!(json["required"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_foreach_resolves_items_from_output() {
+        let foreach = ForEach {
+            items: "CompetitorList.competitors".to_string(),
+            concurrency: None,
+        };
+        assert_eq!(foreach.source_key(), "CompetitorList");
+
+        let output = "Here you go:\n```json\n{\"competitors\": [{\"name\": \"A\"}, \"B\"]}\n```";
+        let items = foreach.resolve_items(output).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["name"], "A");
+
+        let err = foreach.resolve_items(r#"{"rivals": []}"#).unwrap_err();
+        assert!(err.to_string().contains("no field 'competitors'"));
+        let err = foreach.resolve_items("no JSON here").unwrap_err();
+        assert!(err.to_string().contains("not JSON"));
+    }
 }
@@ -0,0 +1,437 @@
+//! Manifest Composition (`imports:` and `extends:`)
+//!
+//! A manifest can be assembled from several files before it is deserialized:
+//! - `imports: [paths]` pulls in libraries of shared `schemas`, `phases` and
+//!   `quality_gates` (files with any subset of those keys)
+//! - `extends: path` starts from a base manifest (header, inputs and all)
+//!
+//! The file's own entries are then applied on top: header keys and schemas replace
+//! inherited ones by name, `inputs` and `report` replace the inherited ones, quality
+//! gates are appended, and each phase either overrides the inherited phase with the
+//! same `id` (only the keys it sets, e.g. `instructions` or `model`) or is added as a
+//! new phase, placed with `before: <id>` / `after: <id>` or appended.
+//!
+//! Paths are relative to the file that names them. Cycles are reported with the chain
+//! of files; each phase records the file it came from (`Phase::source`).
+
+use super::{Manifest, PhaseSource};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Phase keys that position a new phase; not part of the phase itself
+const PLACEMENT_KEYS: [&str; 2] = ["before", "after"];
+
+/// Parse manifest YAML, resolving `imports`/`extends` relative to `path`'s directory.
+/// `path` is where the content lives (or will be saved); None = no composition.
+pub fn parse(content: &str, path: Option<&Path>) -> Result<Manifest> {
+    let doc: Value =
+        serde_yaml::from_str(content).with_context(|| "Failed to parse YAML manifest")?;
+    let mut stack: Vec<PathBuf> = path.map(identity).into_iter().collect();
+    let composed = compose(doc, path, &mut stack)?;
+    composed.into_manifest()
+}
+
+/// Load a manifest file with its imports and base manifests
+pub fn load(path: &Path) -> Result<Manifest> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest file: {:?}", path))?;
+    parse(&content, Some(path))
+}
+
+/// Canonical path when the file exists (for cycle detection)
+fn identity(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn label(path: Option<&Path>) -> String {
+    path.map_or_else(
+        || "(unsaved manifest)".to_string(),
+        |p| p.display().to_string(),
+    )
+}
+
+/// Manifest parts gathered from a file and everything it imports or extends
+/// (sections no file declares stay None, so required ones are still reported missing)
+#[derive(Default)]
+struct Composed {
+    header: Option<Mapping>,
+    inputs: Option<Value>,
+    schemas: Option<Mapping>,
+    phases: Option<Vec<(Mapping, PhaseSource)>>,
+    quality_gates: Option<Vec<Value>>,
+    report: Option<Value>,
+}
+
+impl Composed {
+    fn phase_index(&self, id: &str) -> Option<usize> {
+        self.phases
+            .as_ref()?
+            .iter()
+            .position(|(phase, _)| phase_id(phase).as_deref() == Some(id))
+    }
+
+    /// Add a library's schemas, phases and quality gates
+    fn import(&mut self, library: Composed, from: &str) -> Result<()> {
+        if let Some(schemas) = library.schemas {
+            self.schemas
+                .get_or_insert_with(Mapping::new)
+                .extend(schemas);
+        }
+        for (phase, source) in library.phases.unwrap_or_default() {
+            if let Some(id) = phase_id(&phase).filter(|id| self.phase_index(id).is_some()) {
+                bail!("Phase '{}' imported from {} is already defined", id, from);
+            }
+            self.phases
+                .get_or_insert_with(Vec::new)
+                .push((phase, source));
+        }
+        if let Some(gates) = library.quality_gates {
+            self.quality_gates
+                .get_or_insert_with(Vec::new)
+                .extend(gates);
+        }
+        Ok(())
+    }
+
+    /// Apply a file's own entries on top of what it imports/extends
+    fn apply(&mut self, doc: &Mapping, file: &str) -> Result<()> {
+        if let Some(header) = doc.get("manifest") {
+            let header = header
+                .as_mapping()
+                .ok_or_else(|| anyhow!("'manifest' must be a mapping in {}", file))?;
+            self.header
+                .get_or_insert_with(Mapping::new)
+                .extend(header.clone());
+        }
+        if let Some(inputs) = doc.get("inputs") {
+            self.inputs = Some(inputs.clone());
+        }
+        if let Some(report) = doc.get("report") {
+            self.report = Some(report.clone());
+        }
+        if let Some(schemas) = doc.get("schemas") {
+            let schemas = match schemas {
+                Value::Null => Mapping::new(),
+                Value::Mapping(schemas) => schemas.clone(),
+                _ => bail!("'schemas' must be a mapping in {}", file),
+            };
+            self.schemas
+                .get_or_insert_with(Mapping::new)
+                .extend(schemas);
+        }
+        if doc.contains_key("quality_gates") {
+            let gates = sequence(doc, "quality_gates", file)?;
+            self.quality_gates
+                .get_or_insert_with(Vec::new)
+                .extend(gates.iter().cloned());
+        }
+        if doc.contains_key("phases") {
+            self.phases.get_or_insert_with(Vec::new);
+        }
+        for phase in sequence(doc, "phases", file)? {
+            let mut phase = phase
+                .as_mapping()
+                .cloned()
+                .ok_or_else(|| anyhow!("Phases must be mappings in {}", file))?;
+            self.apply_phase(&mut phase, file)?;
+        }
+        Ok(())
+    }
+
+    fn apply_phase(&mut self, phase: &mut Mapping, file: &str) -> Result<()> {
+        let placement: Vec<(&str, String)> = PLACEMENT_KEYS
+            .iter()
+            .filter_map(|key| {
+                let target = phase.remove(*key)?;
+                Some((*key, target.as_str().unwrap_or_default().to_string()))
+            })
+            .collect();
+        let id = phase_id(phase);
+
+        // Override of an inherited phase: merge the keys it sets (a repeated id within
+        // one file stays a duplicate, for the linter to report)
+        let phases = self.phases.get_or_insert_with(Vec::new);
+        let existing = id.as_deref().and_then(|id| {
+            phases.iter().position(|(phase, source)| {
+                source.file != file && phase_id(phase).as_deref() == Some(id)
+            })
+        });
+        let entry = match existing {
+            Some(index) => {
+                let (mut merged, mut source) = phases.remove(index);
+                merged.extend(phase.clone());
+                source.overridden_in.push(file.to_string());
+                (merged, source)
+            }
+            None => (
+                phase.clone(),
+                PhaseSource {
+                    file: file.to_string(),
+                    overridden_in: Vec::new(),
+                },
+            ),
+        };
+
+        let position = match placement.first() {
+            Some((key, target)) => {
+                let index = self.phase_index(target).ok_or_else(|| {
+                    anyhow!(
+                        "Phase '{}' in {}: {} '{}' is not a phase",
+                        id.as_deref().unwrap_or("?"),
+                        file,
+                        key,
+                        target
+                    )
+                })?;
+                if *key == "after" {
+                    index + 1
+                } else {
+                    index
+                }
+            }
+            None => existing.unwrap_or_else(|| self.phases.as_ref().map_or(0, Vec::len)),
+        };
+        self.phases
+            .get_or_insert_with(Vec::new)
+            .insert(position, entry);
+        Ok(())
+    }
+
+    fn into_manifest(self) -> Result<Manifest> {
+        let mut doc = Mapping::new();
+        if let Some(header) = self.header {
+            doc.insert("manifest".into(), Value::Mapping(header));
+        }
+        if let Some(inputs) = self.inputs {
+            doc.insert("inputs".into(), inputs);
+        }
+        if let Some(schemas) = self.schemas {
+            doc.insert("schemas".into(), Value::Mapping(schemas));
+        }
+        let mut sources = Vec::new();
+        if let Some(phases) = self.phases {
+            let (phases, phase_sources): (Vec<Mapping>, Vec<PhaseSource>) =
+                phases.into_iter().unzip();
+            sources = phase_sources;
+            doc.insert(
+                "phases".into(),
+                Value::Sequence(phases.into_iter().map(Value::Mapping).collect()),
+            );
+        }
+        if let Some(gates) = self.quality_gates {
+            doc.insert("quality_gates".into(), Value::Sequence(gates));
+        }
+        if let Some(report) = self.report {
+            doc.insert("report".into(), report);
+        }
+
+        let mut manifest: Manifest = serde_yaml::from_value(Value::Mapping(doc))
+            .with_context(|| "Failed to parse YAML manifest")?;
+        for (phase, source) in manifest.phases.iter_mut().zip(sources) {
+            phase.source = Some(source);
+        }
+        Ok(manifest)
+    }
+}
+
+/// Resolve one document: its base manifest, then imports, then its own entries
+fn compose(doc: Value, path: Option<&Path>, stack: &mut Vec<PathBuf>) -> Result<Composed> {
+    let file = label(path);
+    let doc = doc
+        .as_mapping()
+        .ok_or_else(|| anyhow!("Manifest {} must be a YAML mapping", file))?;
+
+    let mut composed = match doc.get("extends") {
+        Some(base) => {
+            let base = base
+                .as_str()
+                .ok_or_else(|| anyhow!("'extends' must be a file path in {}", file))?;
+            compose_file(&resolve(path, base)?, stack)?
+        }
+        None => Composed::default(),
+    };
+
+    for import in sequence(doc, "imports", &file)? {
+        let import = import
+            .as_str()
+            .ok_or_else(|| anyhow!("'imports' entries must be file paths in {}", file))?;
+        let import_path = resolve(path, import)?;
+        let library = compose_file(&import_path, stack)?;
+        composed.import(library, &import_path.display().to_string())?;
+    }
+
+    composed.apply(doc, &file)?;
+    Ok(composed)
+}
+
+fn compose_file(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Composed> {
+    let id = identity(path);
+    if let Some(start) = stack.iter().position(|p| p == &id) {
+        let chain: Vec<String> = stack[start..]
+            .iter()
+            .chain(std::iter::once(&id))
+            .map(|p| p.display().to_string())
+            .collect();
+        bail!("Manifest import cycle: {}", chain.join(" -> "));
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest file: {:?}", path))?;
+    let doc: Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse YAML manifest: {:?}", path))?;
+
+    stack.push(id);
+    let composed = compose(doc, Some(path), stack);
+    stack.pop();
+    composed
+}
+
+/// Path named in `from` (relative to its directory)
+fn resolve(from: Option<&Path>, target: &str) -> Result<PathBuf> {
+    let target = Path::new(target);
+    if target.is_absolute() {
+        return Ok(target.to_path_buf());
+    }
+    let from = from.ok_or_else(|| {
+        anyhow!(
+            "Cannot resolve {:?} before the manifest is saved (relative paths need its location)",
+            target
+        )
+    })?;
+    Ok(from.parent().unwrap_or(Path::new("")).join(target))
+}
+
+fn sequence<'a>(doc: &'a Mapping, key: &str, file: &str) -> Result<&'a [Value]> {
+    match doc.get(key) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Sequence(items)) => Ok(items),
+        Some(_) => bail!("'{}' must be a list in {}", key, file),
+    }
+}
+
+fn phase_id(phase: &Mapping) -> Option<String> {
+    phase.get("id").and_then(Value::as_str).map(str::to_string)
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    const BASE: &str = r#"
+manifest:
+  id: "BASE"
+  version: "1.0.0"
+  name: "Base Protocol"
+  description: "Shared base."
+imports: ["lib/schemas.yaml"]
+phases:
+  - id: "PHASE-01"
+    name: "Research"
+    instructions: "Research the company."
+    output_schema: "Profile"
+  - id: "PHASE-02"
+    name: "Brief"
+    instructions: "Write the brief."
+quality_gates:
+  - phase: "PHASE-01"
+    check: "Complete?"
+    fail_action: "RETRY"
+"#;
+
+    const LIBRARY: &str = r#"
+schemas:
+  Profile:
+    fields:
+      - name: company_name
+"#;
+
+    #[test]
+    fn test_extends_overrides_and_inserts_phases() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "lib/schemas.yaml", LIBRARY);
+        write(dir.path(), "base.yaml", BASE);
+        let derived = write(
+            dir.path(),
+            "derived.yaml",
+            r#"
+extends: "base.yaml"
+manifest:
+  name: "Derived Protocol"
+phases:
+  - id: "PHASE-02"
+    instructions: "Write a one-page brief."
+    model: "gpt-4o"
+  - id: "PHASE-01B"
+    name: "Competitors"
+    instructions: "List competitors."
+    after: "PHASE-01"
+"#,
+        );
+
+        let manifest = Manifest::load_from_file(&derived).unwrap();
+
+        assert_eq!(manifest.manifest.id, "BASE");
+        assert_eq!(manifest.manifest.name, "Derived Protocol");
+        assert!(manifest.schemas.contains_key("Profile"));
+        let ids: Vec<&str> = manifest.phases.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["PHASE-01", "PHASE-01B", "PHASE-02"]);
+
+        let brief = manifest.get_phase("PHASE-02").unwrap();
+        assert_eq!(brief.name, "Brief");
+        assert_eq!(brief.instructions, "Write a one-page brief.");
+        assert_eq!(brief.model.as_deref(), Some("gpt-4o"));
+        let source = brief.source.as_ref().unwrap();
+        assert!(source.file.ends_with("base.yaml"));
+        assert!(source.overridden_in[0].ends_with("derived.yaml"));
+
+        let inserted = manifest.get_phase("PHASE-01B").unwrap().source.as_ref();
+        assert!(inserted.unwrap().file.ends_with("derived.yaml"));
+        assert_eq!(manifest.quality_gates.len(), 1);
+    }
+
+    #[test]
+    fn test_import_cycle_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write(dir.path(), "a.yaml", "imports: [\"b.yaml\"]\n");
+        write(dir.path(), "b.yaml", "imports: [\"a.yaml\"]\n");
+
+        let err = Manifest::load_from_file(&a).unwrap_err().to_string();
+
+        assert!(err.contains("cycle"), "{}", err);
+        assert!(
+            err.contains("a.yaml -> ") && err.contains("b.yaml -> "),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_unknown_insert_position_and_unsaved_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = write(dir.path(), "base.yaml", BASE);
+        write(dir.path(), "lib/schemas.yaml", LIBRARY);
+        let content = format!(
+            "extends: {:?}\nphases:\n  - id: X\n    name: X\n    instructions: X\n    before: PHASE-09\n",
+            base
+        );
+
+        let err = parse(&content, None).unwrap_err().to_string();
+        assert!(err.contains("before 'PHASE-09' is not a phase"), "{}", err);
+
+        let err = parse("extends: base.yaml\n", None).unwrap_err().to_string();
+        assert!(err.contains("before the manifest is saved"), "{}", err);
+    }
+}
@@ -0,0 +1,255 @@
+//! Manifest Version History
+//!
+//! Every saved (or run) manifest is recorded as a version in the manifest library
+//! (`AuthManager::record_manifest_version`), keyed by the manifest's `id` and
+//! identified by a hash of its content. A manifest that uses `imports:`/`extends:`
+//! also stores its resolved form, so a version pins exactly what the agent ran even
+//! after the imported files change.
+
+use super::Manifest;
+use anyhow::{Context, Result};
+use serde_yaml::Value;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Lines of unchanged context around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// A manifest as saved or run, ready to be recorded as a version
+#[derive(Debug, Clone)]
+pub struct ManifestSnapshot {
+    /// `manifest.id` from the header (the library key)
+    pub manifest_key: String,
+    pub name: String,
+    /// `manifest.version` from the header (semantic version, as written)
+    pub version: String,
+    pub path: Option<String>,
+    pub content: String,
+    /// The composed manifest, when the content imports or extends other files
+    pub resolved_content: Option<String>,
+    pub content_hash: String,
+}
+
+impl ManifestSnapshot {
+    /// Snapshot manifest `content`, already parsed (and composed) into `manifest`
+    pub fn new(content: &str, manifest: &Manifest, path: Option<&Path>) -> Result<Self> {
+        let resolved_content = if is_composed(content) {
+            Some(
+                serde_yaml::to_string(manifest)
+                    .with_context(|| "Failed to serialize resolved manifest")?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            manifest_key: manifest.manifest.id.clone(),
+            name: manifest.manifest.name.clone(),
+            version: manifest.manifest.version.clone(),
+            path: path.map(|p| p.display().to_string()),
+            content_hash: content_hash(content, resolved_content.as_deref()),
+            content: content.to_string(),
+            resolved_content,
+        })
+    }
+}
+
+/// Whether manifest YAML pulls in other files (`imports:` or `extends:`)
+fn is_composed(content: &str) -> bool {
+    match serde_yaml::from_str::<Value>(content) {
+        Ok(Value::Mapping(doc)) => doc.contains_key("imports") || doc.contains_key("extends"),
+        _ => false,
+    }
+}
+
+/// SHA-256 over the manifest content (and its resolved form, which changes with imports)
+pub fn content_hash(content: &str, resolved_content: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    if let Some(resolved) = resolved_content {
+        hasher.update([0u8]);
+        hasher.update(resolved.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// ------------------------------------------------------------------
+// Line Diff
+// ------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same,
+    Removed,
+    Added,
+}
+
+/// Unified diff (`---`/`+++` headers, `@@` hunks) between two manifest versions.
+/// Returns an empty string when the contents are identical.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    if edits.iter().all(|(edit, _, _)| *edit == Edit::Same) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut start = 0;
+    while let Some(first_change) = edits[start..]
+        .iter()
+        .position(|(edit, _, _)| *edit != Edit::Same)
+        .map(|i| start + i)
+    {
+        // Extend the hunk while changes are within 2x context of each other
+        let hunk_start = first_change.saturating_sub(DIFF_CONTEXT);
+        let mut last_change = first_change;
+        let mut i = first_change + 1;
+        while i < edits.len() && i <= last_change + 2 * DIFF_CONTEXT {
+            if edits[i].0 != Edit::Same {
+                last_change = i;
+            }
+            i += 1;
+        }
+        let hunk_end = (last_change + DIFF_CONTEXT + 1).min(edits.len());
+        let hunk = &edits[hunk_start..hunk_end];
+
+        let old_count = hunk.iter().filter(|(e, _, _)| *e != Edit::Added).count();
+        let new_count = hunk.iter().filter(|(e, _, _)| *e != Edit::Removed).count();
+        let (old_start, new_start) = hunk_origin(&edits, hunk_start);
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for (edit, old_idx, new_idx) in hunk {
+            let (marker, line) = match edit {
+                Edit::Same => (' ', old_lines[*old_idx]),
+                Edit::Removed => ('-', old_lines[*old_idx]),
+                Edit::Added => ('+', new_lines[*new_idx]),
+            };
+            out.push(marker);
+            out.push_str(line);
+            out.push('\n');
+        }
+
+        start = hunk_end;
+    }
+    out
+}
+
+/// 1-based start lines (old, new) of the hunk beginning at `edits[index]`
+fn hunk_origin(edits: &[(Edit, usize, usize)], index: usize) -> (usize, usize) {
+    let old = edits[..index]
+        .iter()
+        .filter(|(e, _, _)| *e != Edit::Added)
+        .count();
+    let new = edits[..index]
+        .iter()
+        .filter(|(e, _, _)| *e != Edit::Removed)
+        .count();
+    (old + 1, new + 1)
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty side points at the line before the hunk
+        0 => format!("{},0", start - 1),
+        1 => start.to_string(),
+        _ => format!("{},{}", start, count),
+    }
+}
+
+/// Line edits from the longest common subsequence, as (edit, old index, new index)
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<(Edit, usize, usize)> {
+    // Common prefix/suffix are trimmed first; manifests usually change in a few places
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] = LCS length of old_mid[i..] and new_mid[j..]
+    let width = new_mid.len() + 1;
+    let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits: Vec<(Edit, usize, usize)> = (0..prefix).map(|i| (Edit::Same, i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            edits.push((Edit::Same, prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len()
+            || (i < old_mid.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            edits.push((Edit::Removed, prefix + i, prefix + j));
+            i += 1;
+        } else {
+            edits.push((Edit::Added, prefix + i, prefix + j));
+            j += 1;
+        }
+    }
+    let (old_tail, new_tail) = (old.len() - suffix, new.len() - suffix);
+    edits.extend((0..suffix).map(|k| (Edit::Same, old_tail + k, new_tail + k)));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        let new = old
+            .replace("line 2\n", "line two\n")
+            .replace("line 18\n", "")
+            + "line 21\n";
+
+        let diff = unified_diff(&old, &new, "v1", "v2");
+        assert_eq!(
+            diff,
+            "--- v1\n+++ v2\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+line two\n line 3\n line 4\n line 5\n\
+             @@ -15,6 +15,6 @@\n line 15\n line 16\n line 17\n-line 18\n line 19\n line 20\n+line 21\n"
+        );
+        assert_eq!(unified_diff(&old, &old, "v1", "v1"), "");
+    }
+
+    #[test]
+    fn test_snapshot_hash_tracks_resolved_content() {
+        let content = "manifest:\n  id: M-1\n  version: 1.2.0\n  name: Test\n  description: Test\nschemas: {}\nphases: []\nquality_gates: []\n";
+        let manifest = Manifest::parse(content, None).unwrap();
+        let snapshot = ManifestSnapshot::new(content, &manifest, None).unwrap();
+
+        assert_eq!(snapshot.manifest_key, "M-1");
+        assert_eq!(snapshot.version, "1.2.0");
+        assert!(snapshot.resolved_content.is_none());
+        assert_eq!(snapshot.content_hash, content_hash(content, None));
+        assert_eq!(snapshot.content_hash.len(), 64);
+
+        // Same file, different imported content -> different version
+        assert_ne!(
+            content_hash(content, Some("a")),
+            content_hash(content, Some("b"))
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! JSON Schema for the Manifest Format
+//!
+//! Generated from the serde structs in `manifest.rs` (schemars), then extended with
+//! what serde does not see: the `imports`/`extends` composition keys and phase
+//! placement (`before`/`after`), and suggested values for free-text fields (`model`
+//! from the model catalog, common `fail_action`s). Suggestions are offered as
+//! completions but any string is still accepted, as at load time.
+//!
+//! Exported by the `get_manifest_json_schema` command and the `manifest-schema` CLI
+//! subcommand; `docs/manifest.schema.json` is the copy for editors (kept in sync by
+//! a test).
+
+use super::Manifest;
+use crate::model_catalog::ModelCatalog;
+use serde_json::{json, Value};
+
+/// `fail_action` values used by the bundled manifests (suggestions; any action is accepted)
+pub const KNOWN_FAIL_ACTIONS: &[&str] = &[
+    "RETRY",
+    "RETRY_SEARCH",
+    "RETRY_WITH_EVIDENCE",
+    "RETRY_WITH_DATA",
+    "RETRY_WITH_CURRENT_DATE_FILTER",
+    "REGENERATE_WITH_PENALTY",
+    "PURGE_UNVERIFIED_DATA",
+    "EXCLUDE_UNCONFIRMED",
+    "FILTER_INACTIVE",
+    "NARROW_SEGMENT_SCOPE",
+];
+
+/// Top-level keys that make a file a partial manifest (resolved through `compose`)
+const COMPOSITION_KEYS: [&str; 2] = ["imports", "extends"];
+
+/// JSON Schema (draft-07) for manifest YAML files
+pub fn manifest_json_schema(catalog: &ModelCatalog) -> Value {
+    let root = schemars::schema_for!(Manifest);
+    let mut schema = serde_json::to_value(root).expect("JSON Schema serializes");
+
+    schema["title"] = json!("Research Manifest");
+    let properties = &mut schema["properties"];
+    properties["imports"] = json!({
+        "description": "Files whose schemas, phases and quality gates are pulled in \
+                        (paths relative to this file)",
+        "type": "array",
+        "items": { "type": "string" }
+    });
+    properties["extends"] = json!({
+        "description": "Base manifest this file starts from; its own entries override \
+                        or extend the base (path relative to this file)",
+        "type": "string"
+    });
+
+    // Only standalone manifests need every section and complete phases; libraries and
+    // files using imports/extends may set any subset (phases then override by id)
+    let phase = &mut schema["definitions"]["Phase"];
+    let phase_required = std::mem::replace(&mut phase["required"], json!(["id"]));
+    phase["properties"]["before"] = json!({
+        "description": "Place this new phase before the inherited phase with this id",
+        "type": "string"
+    });
+    phase["properties"]["after"] = json!({
+        "description": "Place this new phase after the inherited phase with this id",
+        "type": "string"
+    });
+    let required = schema
+        .as_object_mut()
+        .and_then(|root| root.remove("required"))
+        .unwrap_or_else(|| json!([]));
+    let composed: Vec<Value> = COMPOSITION_KEYS
+        .iter()
+        .map(|key| json!({ "required": [key] }))
+        .collect();
+    schema["if"] = json!({
+        "required": ["manifest"],
+        "not": { "anyOf": composed }
+    });
+    schema["then"] = json!({
+        "required": required,
+        "properties": { "phases": { "items": { "required": phase_required } } }
+    });
+
+    let models: Vec<&str> = catalog
+        .models
+        .iter()
+        .filter(|m| !m.deprecated)
+        .map(|m| m.id.as_str())
+        .collect();
+    suggest(
+        &mut schema["definitions"]["Phase"]["properties"]["model"],
+        &models,
+    );
+    suggest(
+        &mut schema["definitions"]["QualityGate"]["properties"]["fail_action"],
+        KNOWN_FAIL_ACTIONS,
+    );
+
+    schema
+}
+
+/// Offer `values` as completions for a free-text property (other values stay valid)
+fn suggest(property: &mut Value, values: &[&str]) {
+    let Some(object) = property.as_object_mut() else {
+        return;
+    };
+    let description = object.remove("description");
+    let free_text = Value::Object(std::mem::take(object));
+    object.insert("anyOf".to_string(), json!([{ "enum": values }, free_text]));
+    if let Some(description) = description {
+        object.insert("description".to_string(), description);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_describes_manifest_format() {
+        let schema = manifest_json_schema(&ModelCatalog::builtin());
+        let definitions = &schema["definitions"];
+
+        assert!(definitions["Phase"]["properties"]["instructions"]["description"].is_string());
+        assert_eq!(definitions["Phase"]["required"], json!(["id"]));
+        assert_eq!(
+            schema["then"]["required"],
+            json!(["manifest", "phases", "quality_gates", "schemas"])
+        );
+        assert_eq!(
+            schema["then"]["properties"]["phases"]["items"]["required"],
+            json!(["id", "instructions", "name"])
+        );
+
+        let fail_action = &definitions["QualityGate"]["properties"]["fail_action"];
+        assert_eq!(fail_action["anyOf"][0]["enum"][0], "RETRY");
+        assert_eq!(fail_action["anyOf"][1]["type"], "string");
+        assert!(fail_action["description"].is_string());
+
+        let input_types = serde_json::to_string(&definitions["InputType"]).unwrap();
+        for value in ["string", "url", "enum", "number", "date", "file"] {
+            assert!(input_types.contains(&format!("\"{}\"", value)));
+        }
+    }
+
+    #[test]
+    fn test_checked_in_schema_is_current() {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../docs/manifest.schema.json");
+        let generated =
+            serde_json::to_string_pretty(&manifest_json_schema(&ModelCatalog::builtin())).unwrap();
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert_eq!(
+            checked_in.trim_end(),
+            generated,
+            "docs/manifest.schema.json is stale; regenerate it with \
+             `cargo run -- manifest-schema ../docs/manifest.schema.json`"
+        );
+    }
+}
@@ -0,0 +1,862 @@
+//! Semantic Manifest Linter
+//!
+//! Deserializing a manifest only proves its shape. `lint` also checks what the agent
+//! relies on at run time and reports each problem as a `Diagnostic` with a position
+//! in the YAML source, so the editor can point at it while the author types:
+//! - `invalid-yaml`: the document does not parse into a `Manifest`
+//! - `duplicate-phase-id`, `unknown-dependency`, `dependency-order`
+//! - `undefined-schema`: `output_schema` names no entry in `schemas`
+//! - `unknown-gate-phase`: a quality gate references no phase
+//! - `input-order`: a phase `input` is only produced by a later phase
+//! - `unproduced-input`: an `input` no phase produces (it receives the initial input)
+//! - `invalid-foreach`: a `foreach` list is not produced by an earlier phase, or its
+//!   concurrency is 0
+//! - `invalid-ensemble`: an ensemble has fewer than two models, votes without an
+//!   `output_schema`, or is combined with `foreach`
+//! - `unknown-model`, `deprecated-model`: checked against the model catalog (phase,
+//!   ensemble and judge models)
+//! - `unsupported-reasoning`: a phase sets `reasoning` for a model the catalog says
+//!   cannot reason (the setting is not sent)
+//! - `invalid-input`: a declared input is duplicated, an enum has no options, or its
+//!   default does not match its type
+//!
+//! - `invalid-report`: the report includes or excludes an unknown phase, or a template
+//!   placeholder names nothing the run produces
+//!
+//! - `invalid-composition`: an `imports`/`extends` file is missing, cyclic or conflicting
+//!
+//! serde_yaml does not keep value positions, so spans are recovered from the source
+//! text by `Locator` (block and flow style lists as written in our manifests). Phases a
+//! composed manifest inherits from other files have no span.
+
+use super::{EnsembleStrategy, InputType, Manifest, ALL_PHASES};
+use crate::agent::FALLBACK_INPUT_KEYS;
+use crate::model_catalog::ModelCatalog;
+use crate::report;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Position in the manifest source (1-based line and column, length in characters)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String, // Stable identifier, e.g. "unknown-dependency"
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, code: &str, message: String, span: Option<Span>) -> Self {
+        Self {
+            severity,
+            code: code.to_string(),
+            message,
+            span,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Lint manifest source saved (or to be saved) at `path`, which locates its
+/// `imports`/`extends`; diagnostics are ordered by position (unlocated ones last)
+pub fn lint(content: &str, path: Option<&Path>, catalog: &ModelCatalog) -> Vec<Diagnostic> {
+    let invalid_yaml = |e: serde_yaml::Error| {
+        let span = e.location().map(|location| Span {
+            line: location.line(),
+            column: location.column(),
+            length: 1,
+        });
+        vec![Diagnostic::new(
+            Severity::Error,
+            "invalid-yaml",
+            format!("Invalid YAML: {}", e),
+            span,
+        )]
+    };
+    let doc: Value = match serde_yaml::from_str(content) {
+        Ok(doc) => doc,
+        Err(e) => return invalid_yaml(e),
+    };
+
+    let composed = doc.get("extends").is_some() || doc.get("imports").is_some();
+    let manifest = if composed {
+        match Manifest::parse(content, path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                return vec![Diagnostic::new(
+                    Severity::Error,
+                    "invalid-composition",
+                    format!("{:#}", e),
+                    None,
+                )]
+            }
+        }
+    } else {
+        // Parsed directly so shape errors keep their position
+        match serde_yaml::from_str::<Manifest>(content) {
+            Ok(manifest) => manifest,
+            Err(e) => return invalid_yaml(e),
+        }
+    };
+
+    let mut diagnostics = lint_manifest(&manifest, &Locator::new(content), catalog);
+    diagnostics.sort_by_key(|d| d.span.map_or((usize::MAX, 0), |s| (s.line, s.column)));
+    diagnostics
+}
+
+fn lint_manifest(
+    manifest: &Manifest,
+    locator: &Locator,
+    catalog: &ModelCatalog,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Phases are matched to source items by id (an extending file lists only some)
+    let phase_span = |index: usize, key: &str, value: &str| {
+        let id = &manifest.phases[index].id;
+        let nth = manifest.phases[..index]
+            .iter()
+            .filter(|p| &p.id == id)
+            .count();
+        locator
+            .item_where("phases", "id", id, nth)
+            .and_then(|item| locator.find(item, key, value))
+    };
+
+    // Keys each phase leaves in the context (see Agent::run_phases)
+    let outputs: Vec<Option<&String>> = manifest
+        .phases
+        .iter()
+        .map(|p| p.output_target.as_ref().or(p.output_schema.as_ref()))
+        .collect();
+    let phase_ids: Vec<&str> = manifest.phases.iter().map(|p| p.id.as_str()).collect();
+
+    let input_items = locator.items("inputs");
+    let mut input_names = HashSet::new();
+    for (index, field) in manifest.inputs.iter().enumerate() {
+        let span = || {
+            input_items
+                .get(index)
+                .and_then(|item| locator.find(item.clone(), "name", &field.name))
+        };
+        let mut problems = Vec::new();
+        if !input_names.insert(field.name.as_str()) {
+            problems.push(format!("Duplicate input '{}'", field.name));
+        }
+        if field.r#type == InputType::Enum && field.options.is_empty() {
+            problems.push(format!("Enum input '{}' has no options", field.name));
+        }
+        // File defaults are checked when the research starts (the path may not exist yet)
+        if let Some(default) = field
+            .default_value()
+            .filter(|_| field.r#type != InputType::File)
+        {
+            if let Err(e) = field.check(&default) {
+                problems.push(format!("Default of input '{}': {}", field.name, e));
+            }
+        }
+        for message in problems {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "invalid-input",
+                message,
+                span(),
+            ));
+        }
+    }
+
+    let mut seen_ids = HashSet::new();
+    for (index, phase) in manifest.phases.iter().enumerate() {
+        if !seen_ids.insert(phase.id.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "duplicate-phase-id",
+                format!("Duplicate phase id '{}'", phase.id),
+                phase_span(index, "id", &phase.id),
+            ));
+        }
+
+        for dependency in &phase.dependencies {
+            if dependency == ALL_PHASES {
+                continue;
+            }
+            let span = phase_span(index, "dependencies", dependency);
+            match phase_ids.iter().position(|id| id == dependency) {
+                None => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "unknown-dependency",
+                    format!("Dependency '{}' is not a phase in this manifest", dependency),
+                    span,
+                )),
+                Some(position) if position >= index => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "dependency-order",
+                    format!(
+                        "Dependency '{}' does not run before phase '{}' (phases run in manifest order)",
+                        dependency, phase.id
+                    ),
+                    span,
+                )),
+                Some(_) => {}
+            }
+        }
+
+        if let Some(schema) = &phase.output_schema {
+            if !manifest.schemas.contains_key(schema) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "undefined-schema",
+                    format!("Output schema '{}' is not defined in schemas", schema),
+                    phase_span(index, "output_schema", schema),
+                ));
+            }
+        }
+
+        if let Some(input) = &phase.input {
+            let produced_before = outputs[..index].contains(&Some(input));
+            let produced_after = outputs[index..].contains(&Some(input));
+            let span = phase_span(index, "input", input);
+            if !produced_before && produced_after {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "input-order",
+                    format!(
+                        "Input '{}' is only produced by a later phase; this phase would receive the initial input",
+                        input
+                    ),
+                    span,
+                ));
+            } else if !produced_before
+                && !FALLBACK_INPUT_KEYS.contains(&input.as_str())
+                && !input_names.contains(input.as_str())
+            {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Info,
+                    "unproduced-input",
+                    format!(
+                        "Input '{}' is not produced by any earlier phase; it receives the initial input",
+                        input
+                    ),
+                    span,
+                ));
+            }
+        }
+
+        if let Some(foreach) = &phase.foreach {
+            let key = foreach.source_key().to_string();
+            let span = phase_span(index, "items", &foreach.items)
+                .or_else(|| phase_span(index, "foreach", &foreach.items));
+            if !outputs[..index].contains(&Some(&key)) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "invalid-foreach",
+                    format!(
+                        "Foreach list '{}' is not the output of an earlier phase",
+                        key
+                    ),
+                    span,
+                ));
+            }
+            if foreach.concurrency == Some(0) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "invalid-foreach",
+                    "Foreach concurrency must be at least 1".to_string(),
+                    phase_span(index, "concurrency", "0"),
+                ));
+            }
+        }
+
+        let mut models: Vec<(&str, &String)> = Vec::new();
+        if let Some(model) = &phase.model {
+            models.push(("model", model));
+        }
+        if let Some(ensemble) = &phase.ensemble {
+            let span = ensemble
+                .models
+                .first()
+                .and_then(|model| phase_span(index, "models", model));
+            if ensemble.models.len() < 2 {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "invalid-ensemble",
+                    "An ensemble needs at least two models".to_string(),
+                    span,
+                ));
+            }
+            if ensemble.strategy == EnsembleStrategy::Vote && phase.output_schema.is_none() {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "invalid-ensemble",
+                    "Voting needs a structured output: set output_schema, or use strategy select or merge"
+                        .to_string(),
+                    phase_span(index, "strategy", "vote"),
+                ));
+            }
+            if phase.foreach.is_some() {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "invalid-ensemble",
+                    "A phase cannot have both foreach and ensemble".to_string(),
+                    span,
+                ));
+            }
+            models.extend(ensemble.models.iter().map(|model| ("models", model)));
+            models.extend(
+                ensemble
+                    .judge_model
+                    .iter()
+                    .map(|model| ("judge_model", model)),
+            );
+        }
+        for (key, model) in models {
+            if let Some(warning) = catalog.model_warning(model) {
+                let code = if catalog.lookup(model).is_some() {
+                    "deprecated-model"
+                } else {
+                    "unknown-model"
+                };
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    code,
+                    warning,
+                    phase_span(index, key, model),
+                ));
+            }
+            let reasons = catalog
+                .lookup(model)
+                .is_none_or(|info| info.capabilities.reasoning);
+            if phase.reasoning.is_some() && key != "judge_model" && !reasons {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    "unsupported-reasoning",
+                    format!(
+                        "Model '{}' cannot reason; the phase's reasoning settings are ignored",
+                        model
+                    ),
+                    phase_span(index, key, model),
+                ));
+            }
+        }
+    }
+
+    let mut unknown_gates: Vec<&str> = Vec::new();
+    for gate in &manifest.quality_gates {
+        if !phase_ids.contains(&gate.phase.as_str()) {
+            let nth = unknown_gates.iter().filter(|p| **p == gate.phase).count();
+            unknown_gates.push(&gate.phase);
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "unknown-gate-phase",
+                format!("Quality gate references unknown phase '{}'", gate.phase),
+                locator
+                    .item_where("quality_gates", "phase", &gate.phase, nth)
+                    .and_then(|item| locator.find(item, "phase", &gate.phase)),
+            ));
+        }
+    }
+
+    if let Some(template) = &manifest.report {
+        let section = locator.section("report");
+        let span = |key: &str, value: &str| {
+            section
+                .clone()
+                .and_then(|lines| locator.find(lines, key, value))
+        };
+        for (key, ids) in [
+            ("include", &template.include),
+            ("exclude", &template.exclude),
+        ] {
+            for id in ids {
+                if id != ALL_PHASES && !phase_ids.contains(&id.as_str()) {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Error,
+                        "invalid-report",
+                        format!("Report {} names unknown phase '{}'", key, id),
+                        span(key, id),
+                    ));
+                }
+            }
+        }
+
+        // Roots a placeholder can name: phases, their outputs, inputs and the date
+        let known = |root: &str| {
+            root == report::DATE_PLACEHOLDER
+                || phase_ids.contains(&root)
+                || outputs.contains(&Some(&root.to_string()))
+                || input_names.contains(root)
+                || FALLBACK_INPUT_KEYS.contains(&root)
+        };
+        for (key, text) in [("cover", &template.cover), ("template", &template.template)] {
+            let Some(text) = text else {
+                continue;
+            };
+            for name in report::placeholders(text) {
+                let root = name.split('.').next().unwrap_or_default();
+                if !known(name) && !known(root) {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        "invalid-report",
+                        format!(
+                            "Report {} placeholder '{{{{{}}}}}' names no phase, output or input; it renders empty",
+                            key, name
+                        ),
+                        section
+                            .clone()
+                            .and_then(|lines| locator.find_text(lines, name)),
+                    ));
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+// ------------------------------------------------------------------
+// Source Positions
+// ------------------------------------------------------------------
+
+/// Finds where values are written in the manifest source (line based)
+struct Locator<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            lines: content.lines().collect(),
+        }
+    }
+
+    /// Lines of a top-level key, from the key to the next one
+    fn section(&self, section: &str) -> Option<Range<usize>> {
+        let header = format!("{}:", section);
+        let start = self.lines.iter().position(|l| l.starts_with(&header))?;
+        let end = (start + 1..self.lines.len())
+            .find(|&i| is_top_level(self.lines[i]))
+            .unwrap_or(self.lines.len());
+        Some(start..end)
+    }
+
+    /// Line ranges of the list items under a top-level key (e.g. each phase)
+    fn items(&self, section: &str) -> Vec<Range<usize>> {
+        let Some(Range { start, end }) = self.section(section) else {
+            return Vec::new();
+        };
+
+        let mut item_indent = None;
+        let mut starts = Vec::new();
+        for i in start + 1..end {
+            let line = self.lines[i];
+            let indent = indentation(line);
+            if !line[indent..].starts_with("- ") {
+                continue;
+            }
+            match item_indent {
+                None => {
+                    item_indent = Some(indent);
+                    starts.push(i);
+                }
+                Some(expected) if expected == indent => starts.push(i),
+                Some(_) => {}
+            }
+        }
+
+        let ends = starts.iter().skip(1).copied().chain(std::iter::once(end));
+        starts.iter().zip(ends).map(|(&s, e)| s..e).collect()
+    }
+
+    /// The `nth` item under `section` whose `key` is `value` (e.g. the phase with an id)
+    fn item_where(
+        &self,
+        section: &str,
+        key: &str,
+        value: &str,
+        nth: usize,
+    ) -> Option<Range<usize>> {
+        self.items(section)
+            .into_iter()
+            .filter(|item| {
+                self.find(item.clone(), key, value).is_some_and(|span| {
+                    let text: String = self.lines[span.line - 1]
+                        .chars()
+                        .skip(span.column - 1)
+                        .take(span.length)
+                        .collect();
+                    text == value
+                })
+            })
+            .nth(nth)
+    }
+
+    /// First occurrence of `text` anywhere in the given lines
+    fn find_text(&self, lines: Range<usize>, text: &str) -> Option<Span> {
+        lines.into_iter().find_map(|i| {
+            let byte = self.lines.get(i)?.find(text)?;
+            Some(Span {
+                line: i + 1,
+                column: self.lines[i][..byte].chars().count() + 1,
+                length: text.chars().count(),
+            })
+        })
+    }
+
+    /// Span of `value` under `key` within the given lines: on the key's own line
+    /// (scalar or flow list) or in the block list below it
+    fn find(&self, lines: Range<usize>, key: &str, value: &str) -> Option<Span> {
+        let prefix = format!("{}:", key);
+        let mut i = lines.start;
+        while i < lines.end {
+            let line = self.lines[i];
+            let indent = indentation(line);
+            let mut offset = indent;
+            if line[offset..].starts_with("- ") {
+                offset += 2;
+            }
+            if !line[offset..].starts_with(&prefix) {
+                i += 1;
+                continue;
+            }
+
+            let after_key = offset + prefix.len();
+            if let Some(span) = find_token(line, after_key, value, i) {
+                return Some(span);
+            }
+            // Block list: "- value" lines indented below the key
+            for (j, item) in self.lines.iter().enumerate().take(lines.end).skip(i + 1) {
+                if item.trim().is_empty() {
+                    continue;
+                }
+                let item_indent = indentation(item);
+                if item_indent < indent || !item[item_indent..].starts_with('-') {
+                    break;
+                }
+                if let Some(span) = find_token(item, item_indent + 1, value, j) {
+                    return Some(span);
+                }
+            }
+            // Key found but value not located: point at the key
+            return Some(Span {
+                line: i + 1,
+                column: line[..offset].chars().count() + 1,
+                length: key.chars().count(),
+            });
+        }
+        None
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_top_level(line: &str) -> bool {
+    !line.is_empty() && indentation(line) == 0 && !line.starts_with('#') && !line.starts_with('-')
+}
+
+/// `value` as a whole token (not part of a longer name) in `line` from byte `from`
+fn find_token(line: &str, from: usize, value: &str, line_index: usize) -> Option<Span> {
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | '.');
+    let mut search = from;
+    while let Some(found) = line[search..].find(value) {
+        let start = search + found;
+        let end = start + value.len();
+        let before = line[..start].chars().next_back();
+        let after = line[end..].chars().next();
+        if !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char) {
+            return Some(Span {
+                line: line_index + 1,
+                column: line[..start].chars().count() + 1,
+                length: value.chars().count(),
+            });
+        }
+        search = end;
+    }
+    None
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"manifest:
+  id: "LINT-TEST"
+  version: "1.0.0"
+  name: "Lint Test"
+  description: "Linter fixture."
+
+inputs:
+  - name: sector
+  - name: region
+    type: enum
+
+schemas:
+  Profile:
+    fields:
+      - name: company_name
+
+phases:
+  - id: "PHASE-01"
+    name: "Profile"
+    dependencies: ["PHASE-02"]
+    input: "target_company"
+    instructions: "Research."
+    output_schema: "Profile"
+
+  - id: "PHASE-02"
+    name: "Brief"
+    dependencies: ["PHASE-01", "PHASE-09"]
+    input: "brief_notes"
+    instructions: "Write."
+    output_schema: "Brief"
+    model: "gpt-99"
+
+  - id: "PHASE-01"
+    name: "Notes"
+    dependencies:
+      - PHASE-08
+    input: "industry"
+    instructions: "Note."
+    output_target: "brief_notes"
+
+quality_gates:
+  - phase: "PHASE-01"
+    check: "Complete?"
+    fail_action: "RETRY"
+  - phase: "PHASE-07"
+    check: "Complete?"
+    fail_action: "RETRY"
+"#;
+
+    fn find<'a>(diagnostics: &'a [Diagnostic], code: &str) -> &'a Diagnostic {
+        diagnostics
+            .iter()
+            .find(|d| d.code == code)
+            .unwrap_or_else(|| panic!("no {} diagnostic in {:?}", code, diagnostics))
+    }
+
+    fn span(line: usize, column: usize, length: usize) -> Option<Span> {
+        Some(Span {
+            line,
+            column,
+            length,
+        })
+    }
+
+    #[test]
+    fn test_reports_semantic_errors_with_spans() {
+        let diagnostics = lint(MANIFEST, None, &ModelCatalog::builtin());
+
+        let unknown = find(&diagnostics, "unknown-dependency");
+        assert!(unknown.message.contains("PHASE-09"));
+        assert_eq!(unknown.span, span(27, 33, 8));
+
+        assert_eq!(find(&diagnostics, "undefined-schema").span, span(30, 21, 5));
+        assert_eq!(find(&diagnostics, "unknown-model").span, span(31, 13, 6));
+        assert_eq!(find(&diagnostics, "input-order").span, span(28, 13, 11));
+        assert_eq!(
+            find(&diagnostics, "duplicate-phase-id").span,
+            span(33, 10, 8)
+        );
+        assert_eq!(find(&diagnostics, "dependency-order").span, span(20, 21, 8));
+        // Block list dependency
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.code == "unknown-dependency" && d.span == span(36, 9, 8)));
+        assert_eq!(
+            find(&diagnostics, "unproduced-input").severity,
+            Severity::Info
+        );
+        assert_eq!(
+            find(&diagnostics, "unknown-gate-phase").span,
+            span(45, 13, 8)
+        );
+
+        // Ordered by position
+        let lines: Vec<usize> = diagnostics.iter().map(|d| d.span.unwrap().line).collect();
+        assert!(lines.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_yaml_error_has_location() {
+        let diagnostics = lint(
+            "manifest:\n  id: [unclosed\n",
+            None,
+            &ModelCatalog::builtin(),
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "invalid-yaml");
+        assert!(diagnostics[0].is_error());
+        assert!(diagnostics[0].span.is_some());
+    }
+
+    #[test]
+    fn test_composition_errors_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("derived.yaml");
+
+        let diagnostics = lint(
+            "extends: missing-base.yaml\n",
+            Some(&path),
+            &ModelCatalog::builtin(),
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "invalid-composition");
+        assert!(diagnostics[0].message.contains("missing-base.yaml"));
+    }
+
+    #[test]
+    fn test_foreach_list_must_come_from_earlier_phase() {
+        let content = r#"manifest:
+  id: "FOREACH-LINT"
+  version: "1.0.0"
+  name: "Foreach Lint"
+  description: "Fan-out fixture."
+schemas:
+  Segments:
+    fields:
+      - name: segments
+phases:
+  - id: "PHASE-01"
+    name: "Per Segment"
+    instructions: "Size the segment."
+    foreach:
+      items: "Segments.segments"
+      concurrency: 0
+  - id: "PHASE-02"
+    name: "Segments"
+    instructions: "List the segments."
+    output_schema: "Segments"
+quality_gates: []
+"#;
+        let diagnostics = lint(content, None, &ModelCatalog::builtin());
+        let problems: Vec<&Diagnostic> = diagnostics
+            .iter()
+            .filter(|d| d.code == "invalid-foreach")
+            .collect();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].message.contains("'Segments'"));
+        assert_eq!(problems[0].span, span(15, 15, 17));
+        assert_eq!(problems[1].span, span(16, 20, 1));
+    }
+
+    #[test]
+    fn test_ensemble_checks() {
+        let content = r#"manifest:
+  id: "ENSEMBLE-LINT"
+  version: "1.0.0"
+  name: "Ensemble Lint"
+  description: "Ensemble fixture."
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Situation"
+    instructions: "Classify the situation."
+    ensemble:
+      models: ["gpt-4o", "no-such-model"]
+      strategy: vote
+    reasoning:
+      reasoning_effort: high
+quality_gates: []
+"#;
+        let diagnostics = lint(content, None, &ModelCatalog::builtin());
+        let problem = find(&diagnostics, "invalid-ensemble");
+        assert!(problem.message.contains("output_schema"));
+        assert_eq!(problem.span, span(13, 17, 4));
+        assert_eq!(find(&diagnostics, "unknown-model").span, span(12, 27, 13));
+        let reasoning = find(&diagnostics, "unsupported-reasoning");
+        assert!(reasoning.message.contains("'gpt-4o' cannot reason"));
+        assert_eq!(reasoning.span, span(12, 17, 6));
+    }
+
+    #[test]
+    fn test_report_checks() {
+        let content = r##"manifest:
+  id: "REPORT-LINT"
+  version: "1.0.0"
+  name: "Report Lint"
+  description: "Report fixture."
+schemas: {}
+phases:
+  - id: "PHASE-01"
+    name: "Brief"
+    instructions: "Write the brief."
+    output_target: "markdown_file"
+quality_gates: []
+report:
+  cover: "# {{target_company}} ({{date}})"
+  template: "{{markdown_file}}\n\n{{Situaton.situation}}"
+  include: ["ALL"]
+  exclude: ["PHASE-09"]
+"##;
+        let diagnostics = lint(content, None, &ModelCatalog::builtin());
+        let problems: Vec<&Diagnostic> = diagnostics
+            .iter()
+            .filter(|d| d.code == "invalid-report")
+            .collect();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].message.contains("'{{Situaton.situation}}'"));
+        assert_eq!(problems[0].span, span(15, 37, 18));
+        assert!(problems[1].message.contains("'PHASE-09'"));
+        assert_eq!(problems[1].span, span(17, 14, 8));
+    }
+
+    #[test]
+    fn test_token_match_ignores_longer_names() {
+        let span = find_token(
+            r#"    dependencies: ["PHASE-01-A", "PHASE-01"]"#,
+            17,
+            "PHASE-01",
+            0,
+        );
+        assert_eq!(
+            span,
+            Some(Span {
+                line: 1,
+                column: 35,
+                length: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_bundled_manifests_have_no_errors() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../manifests");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            let errors: Vec<Diagnostic> = lint(&content, Some(&path), &ModelCatalog::builtin())
+                .into_iter()
+                .filter(Diagnostic::is_error)
+                .collect();
+            assert!(errors.is_empty(), "{:?}: {:?}", path, errors);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Phase Timing Metrics and Latency Percentiles
+//!
+//! - `PhaseMetrics` is recorded for each phase run (stored on its `phase_outputs` row):
+//!   started/finished timestamps, time to first token, total duration, retries,
+//!   output tokens/sec and the model actually used
+//! - `summarize` aggregates recorded phases into per provider/model latency
+//!   percentiles, used to pick faster models for each phase
+
+use crate::llm::Generation;
+use crate::model_catalog;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Timing for one phase run (all provider calls, including continuations)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PhaseMetrics {
+    pub provider: Option<String>,
+    pub model: String, // Model actually used (as reported by the provider when known)
+    pub started_at: String, // RFC 3339, UTC
+    pub finished_at: String, // RFC 3339, UTC
+    pub ttft_ms: Option<u64>, // Time to first streamed token (None when not streamed)
+    pub duration_ms: u64,
+    pub retries: u32, // Provider requests beyond the first (stream fallback, continuations)
+    pub output_tokens: Option<u64>,
+    pub tokens_per_sec: Option<f64>,
+}
+
+impl PhaseMetrics {
+    /// Metrics for a phase that started at `started_at` and finished now
+    pub fn from_generation(
+        generation: &Generation,
+        started_at: DateTime<Utc>,
+        ttft_ms: Option<u64>,
+        retries: u32,
+    ) -> Self {
+        let finished_at = Utc::now();
+        let duration_ms = (finished_at - started_at).num_milliseconds().max(0) as u64;
+        let output_tokens = generation
+            .usage
+            .as_ref()
+            .map(|u| u.output_tokens)
+            .filter(|&tokens| tokens > 0);
+        let tokens_per_sec = output_tokens
+            .filter(|_| duration_ms > 0)
+            .map(|tokens| tokens as f64 * 1000.0 / duration_ms as f64);
+
+        Self {
+            provider: model_catalog::provider_for(&generation.model).map(|p| p.to_string()),
+            model: generation.model.clone(),
+            started_at: timestamp(started_at),
+            finished_at: timestamp(finished_at),
+            ttft_ms,
+            duration_ms,
+            retries,
+            output_tokens,
+            tokens_per_sec,
+        }
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// ------------------------------------------------------------------
+// Aggregation
+// ------------------------------------------------------------------
+
+/// Latency percentiles for one provider/model over the recorded phases
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencyStats {
+    pub provider: Option<String>,
+    pub model: String,
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub mean_ms: u64,
+    pub p50_ttft_ms: Option<u64>,
+    pub mean_tokens_per_sec: Option<f64>,
+}
+
+/// Group phase metrics by provider/model (sorted by model) and compute percentiles
+pub fn summarize(samples: &[PhaseMetrics]) -> Vec<LatencyStats> {
+    let mut groups: BTreeMap<(String, Option<String>), Vec<&PhaseMetrics>> = BTreeMap::new();
+    for sample in samples {
+        groups
+            .entry((sample.model.clone(), sample.provider.clone()))
+            .or_default()
+            .push(sample);
+    }
+
+    groups
+        .into_iter()
+        .map(|((model, provider), group)| {
+            let mut durations: Vec<u64> = group.iter().map(|m| m.duration_ms).collect();
+            durations.sort_unstable();
+            let mut ttfts: Vec<u64> = group.iter().filter_map(|m| m.ttft_ms).collect();
+            ttfts.sort_unstable();
+            let rates: Vec<f64> = group.iter().filter_map(|m| m.tokens_per_sec).collect();
+
+            LatencyStats {
+                provider,
+                model,
+                samples: group.len(),
+                p50_ms: percentile(&durations, 50.0).unwrap_or(0),
+                p90_ms: percentile(&durations, 90.0).unwrap_or(0),
+                p99_ms: percentile(&durations, 99.0).unwrap_or(0),
+                mean_ms: durations.iter().sum::<u64>() / durations.len() as u64,
+                p50_ttft_ms: percentile(&ttfts, 50.0),
+                mean_tokens_per_sec: (!rates.is_empty())
+                    .then(|| rates.iter().sum::<f64>() / rates.len() as f64),
+            }
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], pct: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::TokenUsage;
+
+    fn sample(model: &str, duration_ms: u64, ttft_ms: Option<u64>) -> PhaseMetrics {
+        PhaseMetrics {
+            provider: model_catalog::provider_for(model).map(|p| p.to_string()),
+            model: model.to_string(),
+            duration_ms,
+            ttft_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let values: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&values, 50.0), Some(5));
+        assert_eq!(percentile(&values, 90.0), Some(9));
+        assert_eq!(percentile(&values, 99.0), Some(10));
+        assert_eq!(percentile(&[42], 50.0), Some(42));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_summarize_groups_by_model() {
+        let samples = vec![
+            sample("gpt-4o", 3000, Some(400)),
+            sample("claude-sonnet-4-5", 1000, Some(200)),
+            sample("gpt-4o", 1000, None),
+            sample("claude-sonnet-4-5", 2000, Some(300)),
+        ];
+
+        let stats = summarize(&samples);
+
+        assert_eq!(stats.len(), 2);
+        let claude = &stats[0];
+        assert_eq!(claude.model, "claude-sonnet-4-5");
+        assert_eq!(claude.provider.as_deref(), Some("anthropic"));
+        assert_eq!(claude.samples, 2);
+        assert_eq!(
+            (claude.p50_ms, claude.p99_ms, claude.mean_ms),
+            (1000, 2000, 1500)
+        );
+        assert_eq!(claude.p50_ttft_ms, Some(200));
+        assert_eq!(stats[1].p50_ttft_ms, Some(400));
+        assert!(claude.mean_tokens_per_sec.is_none());
+    }
+
+    #[test]
+    fn test_from_generation_computes_throughput() {
+        let generation = Generation {
+            text: "done".to_string(),
+            model: "claude-sonnet-4-5".to_string(),
+            usage: Some(TokenUsage {
+                output_tokens: 500,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let started_at = Utc::now() - chrono::Duration::seconds(2);
+
+        let metrics = PhaseMetrics::from_generation(&generation, started_at, Some(150), 1);
+
+        assert_eq!(metrics.provider.as_deref(), Some("anthropic"));
+        assert!(metrics.duration_ms >= 2000);
+        assert!(metrics.tokens_per_sec.unwrap() <= 250.0);
+        assert!(metrics.finished_at >= metrics.started_at);
+        assert_eq!((metrics.ttft_ms, metrics.retries), (Some(150), 1));
+    }
+}
@@ -0,0 +1,422 @@
+//! Built-in Mock Provider (Offline Runs)
+//!
+//! Serves `mock*` models without network access or API keys, so the app can be
+//! demoed and manifests can be dry-run through the full phase pipeline at zero cost:
+//! - Scripted responses (cycled in order) or template-generated text
+//! - Schema-conforming JSON when the request carries a `response_schema`
+//! - Simulated latency and chunked streaming
+//! - Injected failures (by call number, mid-stream, or via failure model names)
+//! - Simulated `max_tokens` truncation (by call number)
+//!
+//! Model name variants:
+//! - `mock` / `mock-*`  : default behaviour from `MockConfig`
+//! - `mock-fast`        : no simulated latency
+//! - `mock-slow`        : 5x the configured latency
+//! - `mock-fail`        : every call fails
+//! - `mock-flaky`       : every second call fails
+
+use crate::llm::LLMError;
+use anyhow::{Context, Result};
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// ------------------------------------------------------------------
+// Configuration
+// ------------------------------------------------------------------
+
+/// Mock provider behaviour (loadable from YAML or JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockConfig {
+    /// Delay before the response (or first streamed chunk)
+    pub latency_ms: u64,
+    /// Delay between streamed chunks
+    pub chunk_delay_ms: u64,
+    /// Characters per streamed chunk
+    pub chunk_chars: usize,
+    /// Scripted responses, used in order and cycled.
+    /// Supports `{{model}}`, `{{input}}` and `{{call}}` placeholders.
+    pub responses: Vec<String>,
+    /// 1-based call numbers that return an injected provider error
+    pub fail_on_calls: Vec<usize>,
+    /// Cut every stream short with an error after this many chunks
+    pub fail_stream_after_chunks: Option<usize>,
+    /// 1-based call numbers that report a `max_tokens` stop (output truncated)
+    pub truncate_on_calls: Vec<usize>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            latency_ms: 400,
+            chunk_delay_ms: 30,
+            chunk_chars: 24,
+            responses: Vec::new(),
+            fail_on_calls: Vec::new(),
+            fail_stream_after_chunks: None,
+            truncate_on_calls: Vec::new(),
+        }
+    }
+}
+
+impl MockConfig {
+    /// Load a mock script from disk (YAML; JSON is accepted as a YAML subset)
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read mock config: {:?}", path.as_ref()))?;
+
+        serde_yaml::from_str(&content).with_context(|| "Failed to parse mock config")
+    }
+}
+
+// ------------------------------------------------------------------
+// Provider
+// ------------------------------------------------------------------
+
+/// Streamed mock response chunks
+pub type MockStream = Pin<Box<dyn Stream<Item = Result<String, LLMError>> + Send>>;
+
+/// Offline provider backing the `mock*` model family
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    config: MockConfig,
+    calls: Arc<AtomicUsize>, // Shared so cloned clients keep one call sequence
+}
+
+impl MockProvider {
+    pub fn new(config: MockConfig) -> Self {
+        Self {
+            config,
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of calls served so far (including injected failures)
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Generate a complete response, with the stop reason it reports
+    pub async fn generate(
+        &self,
+        system: &str,
+        user: &str,
+        model: &str,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<(String, &'static str), LLMError> {
+        let call = self.next_call();
+        tokio::time::sleep(self.latency_for(model)).await;
+        self.check_failure(model, call)?;
+
+        Ok((
+            self.render(system, user, model, schema, call),
+            self.stop_reason(call),
+        ))
+    }
+
+    /// Generate a response as a stream of text chunks, with the stop reason it reports
+    pub async fn generate_stream(
+        &self,
+        system: &str,
+        user: &str,
+        model: &str,
+        schema: Option<&serde_json::Value>,
+    ) -> Result<(MockStream, &'static str), LLMError> {
+        let call = self.next_call();
+        self.check_failure(model, call)?;
+
+        let text = self.render(system, user, model, schema, call);
+        let chunks = split_chunks(&text, self.config.chunk_chars.max(1));
+        let latency = self.latency_for(model);
+        let chunk_delay = Duration::from_millis(self.config.chunk_delay_ms);
+        let fail_after = self.config.fail_stream_after_chunks;
+
+        let stream = async_stream::stream! {
+            tokio::time::sleep(latency).await;
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                if fail_after == Some(index) {
+                    yield Err(LLMError::StreamingError(format!(
+                        "Injected mock stream failure after {} chunks",
+                        index
+                    )));
+                    return;
+                }
+                if index > 0 {
+                    tokio::time::sleep(chunk_delay).await;
+                }
+                yield Ok(chunk);
+            }
+        };
+
+        Ok((Box::pin(stream), self.stop_reason(call)))
+    }
+
+    /// Render the response text for a call (scripted, schema JSON, or template)
+    pub fn render(
+        &self,
+        system: &str,
+        user: &str,
+        model: &str,
+        schema: Option<&serde_json::Value>,
+        call: usize,
+    ) -> String {
+        let input = excerpt(user, 200);
+
+        if !self.config.responses.is_empty() {
+            let script = &self.config.responses[(call - 1) % self.config.responses.len()];
+            return script
+                .replace("{{model}}", model)
+                .replace("{{input}}", &input)
+                .replace("{{call}}", &call.to_string());
+        }
+
+        if let Some(schema) = schema {
+            return schema_response(schema, &excerpt(user, 60), call);
+        }
+
+        let task = system
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or("(no system prompt)");
+
+        format!(
+            "## Mock Response\n\n\
+             *Generated offline by `{}` (call #{}) - no provider was contacted.*\n\n\
+             **Task:** {}\n\n\
+             **Input:** {}\n\n\
+             - Simulated finding one for the input above\n\
+             - Simulated finding two with supporting detail\n\
+             - Simulated recommendation for the next phase\n",
+            model, call, task, input
+        )
+    }
+
+    fn next_call(&self) -> usize {
+        self.calls.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Stop reason for a call: `max_tokens` for the calls in `truncate_on_calls`
+    fn stop_reason(&self, call: usize) -> &'static str {
+        if self.config.truncate_on_calls.contains(&call) {
+            "max_tokens"
+        } else {
+            "end_turn"
+        }
+    }
+
+    fn latency_for(&self, model: &str) -> Duration {
+        let base = Duration::from_millis(self.config.latency_ms);
+        match model {
+            "mock-fast" => Duration::ZERO,
+            "mock-slow" => base * 5,
+            _ => base,
+        }
+    }
+
+    fn check_failure(&self, model: &str, call: usize) -> Result<(), LLMError> {
+        let injected = match model {
+            "mock-fail" => true,
+            "mock-flaky" => call.is_multiple_of(2),
+            _ => self.config.fail_on_calls.contains(&call),
+        };
+
+        if injected {
+            Err(LLMError::ProviderError(format!(
+                "Injected mock failure on call #{}",
+                call
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// ------------------------------------------------------------------
+// Helpers
+// ------------------------------------------------------------------
+
+/// Build a JSON object satisfying a `DataSchema::to_json_schema()` schema.
+/// Enum fields rotate through their allowed values by call number.
+fn schema_response(schema: &serde_json::Value, subject: &str, call: usize) -> String {
+    let mut object = serde_json::Map::new();
+
+    if let Some(properties) = schema["properties"].as_object() {
+        for (name, property) in properties {
+            let value = match property["enum"].as_array() {
+                Some(values) if !values.is_empty() => values[(call - 1) % values.len()].clone(),
+                _ => serde_json::json!(format!("Mock {} for {}", name, subject)),
+            };
+            object.insert(name.clone(), value);
+        }
+    }
+
+    serde_json::to_string_pretty(&object).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// First `max_chars` characters of the first non-empty line
+fn excerpt(text: &str, max_chars: usize) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    let mut out: String = line.chars().take(max_chars).collect();
+    if line.chars().count() > max_chars {
+        out.push_str("...");
+    }
+    out
+}
+
+/// Split text into chunks of at most `size` characters (char-boundary safe)
+fn split_chunks(text: &str, size: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(size).map(|c| c.iter().collect()).collect()
+}
+
+// ------------------------------------------------------------------
+// Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn fast_config() -> MockConfig {
+        MockConfig {
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            ..MockConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_template_response_mentions_input() {
+        let provider = MockProvider::new(fast_config());
+        let (text, _) = provider
+            .generate("Research the company.", "Acme Corp", "mock", None)
+            .await
+            .unwrap();
+
+        assert!(text.contains("Acme Corp"));
+        assert!(text.contains("Research the company."));
+        assert_eq!(provider.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_scripted_responses_cycle() {
+        let provider = MockProvider::new(MockConfig {
+            responses: vec![
+                "first {{input}}".to_string(),
+                "second #{{call}}".to_string(),
+            ],
+            ..fast_config()
+        });
+
+        let (a, _) = provider.generate("", "Acme", "mock", None).await.unwrap();
+        let (b, _) = provider.generate("", "Acme", "mock", None).await.unwrap();
+        let (c, _) = provider.generate("", "Acme", "mock", None).await.unwrap();
+
+        assert_eq!(a, "first Acme");
+        assert_eq!(b, "second #2");
+        assert_eq!(c, "first Acme");
+    }
+
+    #[tokio::test]
+    async fn test_mock_schema_response_is_valid_json() {
+        let provider = MockProvider::new(fast_config());
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "company_name": {"type": "string"},
+                "urgency_level": {"type": "string", "enum": ["HIGH", "MEDIUM", "LOW"]}
+            }
+        });
+
+        let (text, _) = provider
+            .generate("", "Acme", "mock", Some(&schema))
+            .await
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(parsed["urgency_level"], "HIGH");
+        assert!(parsed["company_name"].as_str().unwrap().contains("Acme"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_injected_failures() {
+        let provider = MockProvider::new(MockConfig {
+            fail_on_calls: vec![2],
+            ..fast_config()
+        });
+
+        assert!(provider.generate("", "x", "mock", None).await.is_ok());
+        assert!(provider.generate("", "x", "mock", None).await.is_err());
+        assert!(provider.generate("", "x", "mock-fail", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_truncate_on_calls_reports_max_tokens() {
+        let provider = MockProvider::new(MockConfig {
+            truncate_on_calls: vec![2],
+            ..fast_config()
+        });
+        let other = provider.clone();
+
+        let (_, first) = provider.generate("", "x", "mock", None).await.unwrap();
+        let (_, second) = other.generate_stream("", "x", "mock", None).await.unwrap();
+        // A later call on the shared sequence does not change what call #2 reported
+        let (_, third) = provider.generate("", "x", "mock", None).await.unwrap();
+
+        assert_eq!(first, "end_turn");
+        assert_eq!(second, "max_tokens");
+        assert_eq!(third, "end_turn");
+    }
+
+    #[tokio::test]
+    async fn test_mock_stream_reassembles_to_full_text() {
+        let provider = MockProvider::new(MockConfig {
+            chunk_chars: 5,
+            responses: vec!["The quick brown fox".to_string()],
+            ..fast_config()
+        });
+
+        let (mut stream, _) = provider
+            .generate_stream("", "x", "mock", None)
+            .await
+            .unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.unwrap());
+        }
+
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks.concat(), "The quick brown fox");
+    }
+
+    #[tokio::test]
+    async fn test_mock_stream_injected_failure() {
+        let provider = MockProvider::new(MockConfig {
+            chunk_chars: 2,
+            fail_stream_after_chunks: Some(1),
+            responses: vec!["abcdef".to_string()],
+            ..fast_config()
+        });
+
+        let (mut stream, _) = provider
+            .generate_stream("", "x", "mock", None)
+            .await
+            .unwrap();
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "ab");
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+}
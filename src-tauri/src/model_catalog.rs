@@ -0,0 +1,276 @@
+//! Model Catalog (Providers, Limits, Capabilities & Pricing)
+//!
+//! Single source of model metadata:
+//! - Provider routing by model id prefix (used by `LLMClient`)
+//! - Context window and max output per model
+//! - Capability flags (streaming, tools, vision, JSON mode, reasoning)
+//! - List prices per million tokens (used for dry-run cost estimates)
+//! - Deprecation flags, surfaced as manifest validation warnings
+//!
+//! The built-in catalog lives in `models.yaml` and is copied to the app data folder
+//! on first run, where it can be edited.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Model used when neither the UI override nor the phase specifies one
+pub const DEFAULT_MODEL: &str = "claude-sonnet-4-5-20250929";
+
+/// Built-in catalog (also the seed for the editable copy)
+const BUILTIN_CATALOG: &str = include_str!("../models.yaml");
+
+/// Provider for a model id, by prefix. Routing works for any model of a known family,
+/// even one missing from the catalog.
+pub fn provider_for(model: &str) -> Option<&'static str> {
+    if model.starts_with("claude") {
+        Some("anthropic")
+    } else if model.starts_with("gemini") {
+        Some("google")
+    } else if model.starts_with("deepseek") {
+        Some("deepseek")
+    } else if model.starts_with("gpt") || model.starts_with("o1") || model.starts_with("o3") {
+        Some("openai")
+    } else if model.starts_with("mock") {
+        Some("mock")
+    } else {
+        None
+    }
+}
+
+/// Whether a model accepts reasoning controls (extended thinking, reasoning effort),
+/// by family. Request bodies are built without a catalog at hand; the built-in
+/// catalog's `reasoning` flags agree with this.
+pub fn supports_reasoning(model: &str) -> bool {
+    const REASONING_FAMILIES: &[&str] = &[
+        "claude-opus-4",
+        "claude-sonnet-4",
+        "claude-haiku-4",
+        "claude-3-7",
+        "gemini-2.5",
+        "gemini-3",
+        "deepseek-reasoner",
+        "gpt-5",
+        "o1",
+        "o3",
+        "o4",
+    ];
+    REASONING_FAMILIES.iter().any(|f| model.starts_with(f))
+}
+
+// ------------------------------------------------------------------
+// Catalog Structures
+// ------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModelCapabilities {
+    pub streaming: bool,
+    pub tools: bool,
+    pub vision: bool, // Image/PDF attachments
+    pub json_mode: bool,
+    pub reasoning: bool, // Extended thinking / reasoning effort
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelInfo {
+    pub id: String, // Exact id, or a prefix that also covers dated snapshots
+    pub name: String,
+    pub provider: String,
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    #[serde(default)]
+    pub capabilities: ModelCapabilities,
+    pub input_cost_per_mtok: f64,  // USD per million input tokens
+    pub output_cost_per_mtok: f64, // USD per million output tokens
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>, // Suggested model for deprecated entries
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelCatalog {
+    pub models: Vec<ModelInfo>,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+// ------------------------------------------------------------------
+// Implementation
+// ------------------------------------------------------------------
+
+impl ModelCatalog {
+    /// The catalog shipped with the app
+    pub fn builtin() -> Self {
+        serde_yaml::from_str(BUILTIN_CATALOG).expect("built-in models.yaml is valid")
+    }
+
+    /// Load an edited catalog file
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read model catalog: {:?}", path.as_ref()))?;
+        serde_yaml::from_str(&content).with_context(|| "Failed to parse model catalog YAML")
+    }
+
+    /// Write the built-in catalog to `path` unless a file is already there
+    pub fn ensure_file<P: AsRef<Path>>(path: P) -> Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            fs::write(path, BUILTIN_CATALOG)
+                .with_context(|| format!("Failed to write model catalog: {:?}", path))?;
+        }
+        Ok(())
+    }
+
+    /// Catalog entry for a model (longest matching id prefix wins)
+    pub fn lookup(&self, model: &str) -> Option<&ModelInfo> {
+        self.models
+            .iter()
+            .filter(|m| model.starts_with(&m.id))
+            .max_by_key(|m| m.id.len())
+    }
+
+    /// Estimated cost in USD, or None if the model is not in the catalog
+    pub fn estimate_cost(
+        &self,
+        model: &str,
+        input_tokens: usize,
+        output_tokens: usize,
+    ) -> Option<f64> {
+        self.lookup(model).map(|m| {
+            (input_tokens as f64 * m.input_cost_per_mtok
+                + output_tokens as f64 * m.output_cost_per_mtok)
+                / 1_000_000.0
+        })
+    }
+
+    /// Warning for a model a manifest references: unknown to the catalog, or deprecated
+    pub fn model_warning(&self, model: &str) -> Option<String> {
+        match self.lookup(model) {
+            None if provider_for(model).is_none() => Some(format!(
+                "Unknown model '{}' (no provider serves this model)",
+                model
+            )),
+            None => Some(format!(
+                "Unknown model '{}' (not in the model catalog; no pricing or limits)",
+                model
+            )),
+            Some(info) if info.deprecated => Some(match &info.replacement {
+                Some(replacement) => format!(
+                    "Model '{}' is deprecated; use '{}' instead",
+                    model, replacement
+                ),
+                None => format!("Model '{}' is deprecated", model),
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog_is_consistent() {
+        let catalog = ModelCatalog::builtin();
+        assert!(!catalog.models.is_empty());
+        for model in &catalog.models {
+            assert_eq!(
+                provider_for(&model.id),
+                Some(model.provider.as_str()),
+                "provider mismatch for {}",
+                model.id
+            );
+            assert_eq!(
+                supports_reasoning(&model.id),
+                model.capabilities.reasoning,
+                "reasoning mismatch for {}",
+                model.id
+            );
+            if let Some(replacement) = &model.replacement {
+                assert!(catalog.model_warning(replacement).is_none());
+            }
+        }
+        assert!(catalog.model_warning(DEFAULT_MODEL).is_none());
+    }
+
+    #[test]
+    fn test_lookup_prefers_longest_prefix() {
+        let catalog = ModelCatalog::builtin();
+        assert_eq!(
+            catalog.lookup("gpt-4o-mini-2024-07-18").unwrap().id,
+            "gpt-4o-mini"
+        );
+        assert_eq!(catalog.lookup("gpt-4o").unwrap().id, "gpt-4o");
+        assert_eq!(catalog.lookup("o1-mini").unwrap().id, "o1-mini");
+        assert_eq!(
+            catalog.lookup("claude-sonnet-4-5-20250929").unwrap().id,
+            "claude-sonnet-4-5"
+        );
+        assert!(catalog.lookup("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let catalog = ModelCatalog::builtin();
+        // 1M input at $3 + 100k output at $15
+        let cost = catalog
+            .estimate_cost("claude-sonnet-4-5-20250929", 1_000_000, 100_000)
+            .unwrap();
+        assert!((cost - 4.5).abs() < 1e-9);
+        assert_eq!(catalog.estimate_cost("mock", 5000, 5000), Some(0.0));
+        assert_eq!(catalog.estimate_cost("llama-3", 10, 10), None);
+    }
+
+    #[test]
+    fn test_model_warnings() {
+        let catalog = ModelCatalog::builtin();
+        assert!(catalog
+            .model_warning("claude-3-5-sonnet")
+            .unwrap()
+            .contains("deprecated; use 'claude-sonnet-4-5-20250929'"));
+        assert!(catalog
+            .model_warning("gpt-9")
+            .unwrap()
+            .contains("not in the model catalog"));
+        assert!(catalog
+            .model_warning("llama-3")
+            .unwrap()
+            .contains("no provider"));
+        assert!(catalog.model_warning("gemini-2.5-flash").is_none());
+    }
+
+    #[test]
+    fn test_edited_catalog_file_is_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("models.yaml");
+
+        ModelCatalog::ensure_file(&path).unwrap();
+        assert_eq!(
+            ModelCatalog::load_from_file(&path).unwrap(),
+            ModelCatalog::builtin()
+        );
+
+        fs::write(
+            &path,
+            "models:\n  - id: gpt-4o\n    name: GPT-4o\n    provider: openai\n    context_window: 128000\n    max_output_tokens: 16384\n    input_cost_per_mtok: 2.0\n    output_cost_per_mtok: 8.0\n",
+        )
+        .unwrap();
+        ModelCatalog::ensure_file(&path).unwrap(); // Never overwrites edits
+        let catalog = ModelCatalog::load_from_file(&path).unwrap();
+        assert_eq!(catalog.models.len(), 1);
+        assert_eq!(catalog.lookup("gpt-4o").unwrap().input_cost_per_mtok, 2.0);
+        assert!(!catalog.lookup("gpt-4o").unwrap().capabilities.vision);
+    }
+}
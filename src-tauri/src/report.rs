@@ -0,0 +1,248 @@
+//! Report Assembly (`markdown_file`)
+//!
+//! At the end of a run the agent builds the final report from the phase outputs:
+//! - Without a manifest `report:` section, every phase output becomes a `## name`
+//!   section, separated by `---` (the original behaviour)
+//! - With one, the report is the optional cover page, then the rendered `template`,
+//!   then the `include`d phases as sections. Intermediate research only appears
+//!   where the protocol asks for it; by default the body is `{{markdown_file}}`, the
+//!   output of the drafting phase.
+//!
+//! Placeholders (`{{...}}`) name a phase id, a context key (phase output or input),
+//! or a field of a structured output (`SituationAnalysis.situation`, `List.items.0`);
+//! `{{date}}` is today's date. Unresolved placeholders render empty and are reported.
+
+use crate::manifest::{parse_json_output, ReportTemplate};
+use chrono::Local;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Placeholder for today's date
+pub const DATE_PLACEHOLDER: &str = "date";
+
+/// Context key the drafting phase writes the report to (and the assembled report replaces)
+pub const REPORT_KEY: &str = "markdown_file";
+
+const SECTION_SEPARATOR: &str = "\n\n---\n\n";
+
+/// A completed phase's contribution to the report
+#[derive(Debug, Clone)]
+pub struct ReportSection {
+    pub phase_id: String,
+    pub name: String,
+    /// The phase output, or its report rendering (fan-out phases)
+    pub content: String,
+}
+
+impl ReportSection {
+    fn render(&self) -> String {
+        format!("## {}\n\n{}", self.name, self.content)
+    }
+}
+
+/// The assembled report and the placeholders that could not be resolved
+#[derive(Debug, Clone, Default)]
+pub struct AssembledReport {
+    pub text: String,
+    pub warnings: Vec<String>,
+}
+
+/// Every phase as a section, in run order (no `report:` section)
+pub fn concatenate(sections: &[ReportSection]) -> String {
+    sections
+        .iter()
+        .map(ReportSection::render)
+        .collect::<Vec<_>>()
+        .join(SECTION_SEPARATOR)
+}
+
+/// Assemble the report as the manifest's `report:` section describes
+pub fn assemble(
+    report: &ReportTemplate,
+    sections: &[ReportSection],
+    context: &HashMap<String, String>,
+) -> AssembledReport {
+    let mut warnings = Vec::new();
+    let included: Vec<&ReportSection> = sections
+        .iter()
+        .filter(|s| report.includes(&s.phase_id))
+        .collect();
+
+    let mut parts = Vec::new();
+    if let Some(cover) = &report.cover {
+        parts.push(render(cover, sections, context, &mut warnings));
+    }
+    let default_template = format!("{{{{{}}}}}", REPORT_KEY);
+    let template = match &report.template {
+        Some(template) => Some(template.as_str()),
+        None if included.is_empty() => Some(default_template.as_str()),
+        None => None,
+    };
+    if let Some(template) = template {
+        parts.push(render(template, sections, context, &mut warnings));
+    }
+    parts.extend(included.iter().map(|s| s.render()));
+
+    AssembledReport {
+        text: parts
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(SECTION_SEPARATOR),
+        warnings,
+    }
+}
+
+/// Names of the placeholders in a template, in order
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    render_with(template, |name| {
+        names.push(name);
+        String::new()
+    });
+    names
+}
+
+fn render(
+    template: &str,
+    sections: &[ReportSection],
+    context: &HashMap<String, String>,
+    warnings: &mut Vec<String>,
+) -> String {
+    render_with(template, |name| {
+        resolve(name, sections, context).unwrap_or_else(|| {
+            warnings.push(format!("Unresolved placeholder {{{{{}}}}}", name));
+            String::new()
+        })
+    })
+}
+
+/// Replace each `{{ name }}` with `value(name)`
+fn render_with<'a>(template: &'a str, mut value: impl FnMut(&'a str) -> String) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        text.push_str(&value(rest[start + 2..start + end].trim()));
+        rest = &rest[start + end + 2..];
+    }
+    text.push_str(rest);
+    text
+}
+
+fn resolve(
+    name: &str,
+    sections: &[ReportSection],
+    context: &HashMap<String, String>,
+) -> Option<String> {
+    if name == DATE_PLACEHOLDER {
+        return Some(Local::now().format("%B %d, %Y").to_string());
+    }
+    if let Some(section) = sections.iter().find(|s| s.phase_id == name) {
+        return Some(section.content.clone());
+    }
+    if let Some(value) = context.get(name) {
+        return Some(value.clone());
+    }
+
+    // A field of a structured output: the key, then the path within its JSON
+    let (key, path) = name.split_once('.')?;
+    let mut value = parse_json_output(context.get(key)?)?;
+    for field in path.split('.') {
+        value = match value {
+            Value::Array(mut items) => {
+                let index: usize = field.parse().ok()?;
+                (index < items.len()).then(|| items.swap_remove(index))?
+            }
+            Value::Object(mut fields) => fields.remove(field)?,
+            _ => return None,
+        };
+    }
+    Some(format_value(&value))
+}
+
+/// Markdown for a field value: text as is, lists of scalars as bullets, else JSON
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) if items.iter().all(|i| !i.is_array() && !i.is_object()) => items
+            .iter()
+            .map(|item| format!("- {}", format_value(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Array(_) | Value::Object(_) => format!(
+            "```json\n{}\n```",
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        ),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(phase_id: &str, name: &str, content: &str) -> ReportSection {
+        ReportSection {
+            phase_id: phase_id.to_string(),
+            name: name.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_template_renders_outputs_fields_and_sections() {
+        let sections = [
+            section("PHASE-01", "Research", "Raw research notes"),
+            section(
+                "PHASE-02",
+                "Situation",
+                r#"{"situation": "GROWTH", "signals": ["Hiring", "New plant"]}"#,
+            ),
+            section("PHASE-03", "Appendix", "Sources"),
+            section("PHASE-04", "Brief", "The brief"),
+        ];
+        let context = HashMap::from([
+            ("target_company".to_string(), "Acme Corp".to_string()),
+            ("Situation".to_string(), sections[1].content.clone()),
+            ("markdown_file".to_string(), "The brief".to_string()),
+        ]);
+        let report: ReportTemplate = serde_yaml::from_str(
+            r##"
+cover: "# {{target_company}}\nPrepared {{date}}"
+template: "Situation: {{Situation.situation}}\n\n{{Situation.signals}}\n\n{{PHASE-04}}{{missing}}"
+include: ["ALL"]
+exclude: ["PHASE-01", "PHASE-02", "PHASE-04"]
+"##,
+        )
+        .unwrap();
+
+        let assembled = assemble(&report, &sections, &context);
+        assert!(assembled.text.starts_with("# Acme Corp\nPrepared "));
+        assert!(assembled.text.contains(
+            "---\n\nSituation: GROWTH\n\n- Hiring\n- New plant\n\nThe brief\n\n---\n\n## Appendix\n\nSources"
+        ));
+        assert!(!assembled.text.contains("Raw research notes"));
+        assert_eq!(assembled.warnings, ["Unresolved placeholder {{missing}}"]);
+    }
+
+    #[test]
+    fn test_default_report_is_the_drafted_brief() {
+        let sections = [
+            section("PHASE-01", "Research", "Raw research notes"),
+            section("PHASE-02", "Brief", "The brief"),
+        ];
+        let context = HashMap::from([("markdown_file".to_string(), "The brief".to_string())]);
+
+        let assembled = assemble(&ReportTemplate::default(), &sections, &context);
+        assert_eq!(assembled.text, "The brief");
+        assert_eq!(
+            concatenate(&sections),
+            "## Research\n\nRaw research notes\n\n---\n\n## Brief\n\nThe brief"
+        );
+    }
+}
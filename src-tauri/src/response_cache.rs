@@ -0,0 +1,324 @@
+//! Response Cache (Avoid Paying Twice for Identical Calls)
+//!
+//! Opt-in SQLite-backed cache for single-turn `LLMRequest`s. Entries are keyed by a
+//! SHA-256 hash of the serialized request (model, system prompt, user input and any
+//! generation params, including Gemini settings for Gemini models), so changing any of
+//! them is a miss. Today's date in the system prompt is left out, so entries stay
+//! usable for their whole TTL. The provider's stop reason is stored with the text, so
+//! a hit reports how the original call ended.
+//!
+//! Distinct from Anthropic prompt caching (`CacheConfig` in llm.rs), which only
+//! discounts repeated prompt prefixes on the provider side.
+
+use crate::llm::{GeminiConfig, LLMRequest};
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Bump when the key derivation changes so stale entries are never matched
+const KEY_VERSION: &str = "v2";
+
+/// Default time-to-live for cached responses (7 days)
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A cached response and the provider stop reason it ended with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub text: String,
+    pub stop_reason: Option<String>, // As the provider reported it ("end_turn", "STOP", ...)
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    conn: Arc<Mutex<Connection>>, // Shared so cloned clients hit the same store
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    /// Open (or create) a cache database on disk
+    pub fn open<P: AsRef<Path>>(path: P, ttl: Duration) -> Result<Self> {
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open response cache: {:?}", path.as_ref()))?;
+        Self::init(conn, ttl)
+    }
+
+    /// In-memory cache (lives as long as the client)
+    #[cfg(test)]
+    pub fn in_memory(ttl: Duration) -> Result<Self> {
+        Self::init(Connection::open_in_memory()?, ttl)
+    }
+
+    fn init(conn: Connection, ttl: Duration) -> Result<Self> {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS response_cache (
+                key TEXT PRIMARY KEY,
+                model TEXT NOT NULL,
+                response TEXT NOT NULL,
+                stop_reason TEXT,
+                created_at INTEGER NOT NULL,
+                hits INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_response_cache_created_at
+            ON response_cache(created_at);
+            "#,
+        )?;
+
+        // Caches created before stop reasons were stored (an empty table also errs
+        // here; the ALTER then fails harmlessly because the column exists)
+        let has_stop_reason = conn.query_row(
+            "SELECT stop_reason FROM response_cache LIMIT 1",
+            [],
+            |_row| Ok(()),
+        );
+        if has_stop_reason.is_err() {
+            let _ = conn.execute("ALTER TABLE response_cache ADD COLUMN stop_reason TEXT", []);
+        }
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            ttl,
+        })
+    }
+
+    /// Cache key for a request: SHA-256 over the serialized request (without its
+    /// `volatile_system` text) and the Gemini settings it is sent with, if any
+    pub fn key_for(req: &LLMRequest, gemini: Option<&GeminiConfig>) -> String {
+        let serialized = match req.volatile_system.as_deref().filter(|v| !v.is_empty()) {
+            Some(volatile) => {
+                let stable = LLMRequest {
+                    system: req.system.replace(volatile, ""),
+                    ..req.clone()
+                };
+                serde_json::to_string(&stable)
+            }
+            None => serde_json::to_string(req),
+        }
+        .unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_VERSION.as_bytes());
+        hasher.update(serialized.as_bytes());
+        // Default settings send nothing extra, so they key like no settings
+        if let Some(gemini) = gemini.filter(|g| **g != GeminiConfig::default()) {
+            hasher.update(serde_json::to_string(gemini).unwrap_or_default().as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Look up a fresh entry (expired entries are treated as misses)
+    pub fn get(&self, key: &str) -> Result<Option<CachedResponse>> {
+        let conn = self.lock()?;
+        let response = conn
+            .query_row(
+                "SELECT response, stop_reason FROM response_cache WHERE key = ?1 AND created_at >= ?2",
+                params![key, self.cutoff()],
+                |row| {
+                    Ok(CachedResponse {
+                        text: row.get(0)?,
+                        stop_reason: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        if response.is_some() {
+            conn.execute(
+                "UPDATE response_cache SET hits = hits + 1 WHERE key = ?1",
+                params![key],
+            )?;
+        }
+
+        Ok(response)
+    }
+
+    /// Store (or refresh) an entry
+    pub fn put(
+        &self,
+        key: &str,
+        model: &str,
+        response: &str,
+        stop_reason: Option<&str>,
+    ) -> Result<()> {
+        self.lock()?.execute(
+            r#"
+            INSERT INTO response_cache (key, model, response, stop_reason, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(key) DO UPDATE SET
+                response = excluded.response,
+                stop_reason = excluded.stop_reason,
+                created_at = excluded.created_at,
+                hits = 0
+            "#,
+            params![
+                key,
+                model,
+                response,
+                stop_reason,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete expired entries, returning how many were removed
+    pub fn purge_expired(&self) -> Result<usize> {
+        Ok(self.lock()?.execute(
+            "DELETE FROM response_cache WHERE created_at < ?1",
+            params![self.cutoff()],
+        )?)
+    }
+
+    /// Delete every entry, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        Ok(self.lock()?.execute("DELETE FROM response_cache", [])?)
+    }
+
+    /// Number of stored entries (including expired ones not yet purged)
+    #[cfg(test)]
+    pub fn len(&self) -> Result<usize> {
+        let count: i64 =
+            self.lock()?
+                .query_row("SELECT COUNT(*) FROM response_cache", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    fn cutoff(&self) -> i64 {
+        chrono::Utc::now().timestamp() - self.ttl.as_secs() as i64
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock response cache"))
+    }
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(user: &str) -> LLMRequest {
+        LLMRequest {
+            system: "system".to_string(),
+            user: user.to_string(),
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_key_changes_with_any_request_field() {
+        let base = ResponseCache::key_for(&request("Acme"), None);
+
+        assert_eq!(base, ResponseCache::key_for(&request("Acme"), None));
+        assert_ne!(base, ResponseCache::key_for(&request("Globex"), None));
+
+        let mut other_model = request("Acme");
+        other_model.model = "gpt-4o".to_string();
+        assert_ne!(base, ResponseCache::key_for(&other_model, None));
+
+        let mut with_schema = request("Acme");
+        with_schema.response_schema = Some(serde_json::json!({"type": "object"}));
+        assert_ne!(base, ResponseCache::key_for(&with_schema, None));
+    }
+
+    #[test]
+    fn test_key_ignores_date_but_not_gemini_settings() {
+        let dated = |date: &str| LLMRequest {
+            system: format!("Researcher.\nToday's date is {}.\nInstructions", date),
+            volatile_system: Some(format!("Today's date is {}.", date)),
+            ..request("Acme")
+        };
+        let base = ResponseCache::key_for(&dated("May 1, 2026"), None);
+        assert_eq!(base, ResponseCache::key_for(&dated("May 2, 2026"), None));
+
+        let tuned = GeminiConfig {
+            temperature: Some(0.2),
+            ..GeminiConfig::default()
+        };
+        assert_eq!(
+            base,
+            ResponseCache::key_for(&dated("May 1, 2026"), Some(&GeminiConfig::default()))
+        );
+        assert_ne!(
+            base,
+            ResponseCache::key_for(&dated("May 1, 2026"), Some(&tuned))
+        );
+    }
+
+    #[test]
+    fn test_put_and_get_roundtrip() {
+        let cache = ResponseCache::in_memory(DEFAULT_TTL).unwrap();
+        let key = ResponseCache::key_for(&request("Acme"), None);
+
+        assert!(cache.get(&key).unwrap().is_none());
+        cache
+            .put(&key, "claude", "cached answer", Some("end_turn"))
+            .unwrap();
+
+        assert_eq!(
+            cache.get(&key).unwrap(),
+            Some(CachedResponse {
+                text: "cached answer".to_string(),
+                stop_reason: Some("end_turn".to_string()),
+            })
+        );
+        assert_eq!(cache.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses() {
+        let cache = ResponseCache::in_memory(Duration::ZERO).unwrap();
+        let key = ResponseCache::key_for(&request("Acme"), None);
+
+        // Backdate the entry so it is strictly older than the zero TTL
+        cache.put(&key, "claude", "stale", None).unwrap();
+        cache
+            .lock()
+            .unwrap()
+            .execute("UPDATE response_cache SET created_at = created_at - 10", [])
+            .unwrap();
+
+        assert!(cache.get(&key).unwrap().is_none());
+        assert_eq!(cache.purge_expired().unwrap(), 1);
+        assert!(cache.is_empty().unwrap());
+    }
+
+    #[test]
+    fn test_cache_without_stop_reasons_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response_cache.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE response_cache (key TEXT PRIMARY KEY, model TEXT NOT NULL, \
+                 response TEXT NOT NULL, created_at INTEGER NOT NULL, hits INTEGER NOT NULL DEFAULT 0);",
+            )
+            .unwrap();
+
+        let cache = ResponseCache::open(&path, DEFAULT_TTL).unwrap();
+        cache.put("key", "gemini", "answer", Some("STOP")).unwrap();
+        let hit = cache.get("key").unwrap().unwrap();
+        assert_eq!(hit.stop_reason.as_deref(), Some("STOP"));
+        drop(cache);
+        assert!(ResponseCache::open(&path, DEFAULT_TTL).is_ok()); // Migrates once
+    }
+}
@@ -0,0 +1,747 @@
+//! Server-Sent Events Decoder (Provider Streaming)
+//!
+//! One incremental decoder shared by every provider's streaming endpoint:
+//! - `SseDecoder` frames raw response bytes into events. Events and UTF-8 sequences split
+//!   across chunk boundaries, multi-line `data:` fields, comments and CR/CRLF line endings
+//!   are all handled per the SSE spec
+//! - `StreamFormat` interprets event payloads (Anthropic, Gemini, OpenAI-compatible) into
+//!   typed `StreamEvent`s: text, reasoning, usage, stop reason and end of stream.
+//!   Provider error events become `LLMError`s instead of being skipped
+//! - `decode` wires both onto a response `bytes_stream()`
+
+use crate::llm::{LLMError, TokenUsage};
+use futures::stream::{Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+// ------------------------------------------------------------------
+// SSE Framing
+// ------------------------------------------------------------------
+
+/// One dispatched SSE event
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>, // `event:` field (Anthropic names every event)
+    pub data: String,          // `data:` lines joined with '\n'
+    pub id: Option<String>,    // Last `id:` seen on the stream
+}
+
+/// Incremental SSE parser: feed it chunks as they arrive, collect completed events
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>, // Bytes of the current, unterminated line
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+    skip_lf: bool, // Previous line ended in '\r'; a leading '\n' completes that CRLF
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one chunk; returns the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        // '\r' and '\n' never occur inside multi-byte UTF-8 sequences, so splitting on
+        // them is safe; a sequence cut by the chunk boundary stays in the buffer
+        let mut events = Vec::new();
+        let mut line_start = 0;
+        for i in 0..self.buffer.len() {
+            let byte = self.buffer[i];
+            if self.skip_lf {
+                self.skip_lf = false;
+                if byte == b'\n' {
+                    line_start = i + 1;
+                    continue;
+                }
+            }
+            if byte == b'\n' || byte == b'\r' {
+                let line = String::from_utf8_lossy(&self.buffer[line_start..i]).into_owned();
+                self.skip_lf = byte == b'\r';
+                events.extend(self.process_line(&line));
+                line_start = i + 1;
+            }
+        }
+        self.buffer.drain(..line_start);
+        events
+    }
+
+    /// Flush the event left unterminated when the stream ended
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            self.process_line(&line);
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None; // Comment / keep-alive
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => {} // `retry` and unknown fields
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        })
+    }
+}
+
+// ------------------------------------------------------------------
+// Typed Provider Events
+// ------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Text(String),
+    Reasoning(String), // Thinking / reasoning_content chunk
+    Usage(TokenUsage), // Running totals reported so far
+    Stop(String),      // Provider stop reason ("end_turn", "max_tokens", "length", "STOP", ...)
+    Model(String),     // Model the provider reports serving the request
+    Done,              // Explicit end of stream (`[DONE]`, `message_stop`)
+}
+
+/// Payload dialect of a provider's stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamFormat {
+    Anthropic,
+    Gemini,
+    OpenAI, // Also DeepSeek
+}
+
+impl StreamFormat {
+    /// Interpret one SSE event. Error events and malformed payloads are errors.
+    pub fn parse(self, event: &SseEvent) -> Result<Vec<StreamEvent>, LLMError> {
+        if event.data == "[DONE]" {
+            return Ok(vec![StreamEvent::Done]);
+        }
+
+        let value: serde_json::Value = serde_json::from_str(&event.data).map_err(|e| {
+            LLMError::StreamingError(format!(
+                "Malformed {:?} stream event ({}): {}",
+                self,
+                e,
+                event.data.chars().take(200).collect::<String>()
+            ))
+        })?;
+        if let Some(error) = value.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(LLMError::ProviderError(message));
+        }
+
+        let events = match self {
+            Self::Anthropic => parse_anthropic(value),
+            Self::Gemini => parse_gemini(value),
+            Self::OpenAI => parse_openai(value),
+        };
+        events.map_err(|e| LLMError::StreamingError(format!("Unexpected {:?} event: {}", self, e)))
+    }
+}
+
+// Anthropic
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    delta: Option<AnthropicDelta>,
+    #[serde(default)]
+    message: Option<AnthropicStreamMessage>, // message_start: input/cache usage
+    #[serde(default)]
+    usage: Option<TokenUsage>, // message_delta: output usage
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamMessage {
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicDelta {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    thinking: Option<String>, // thinking_delta (extended thinking)
+    #[serde(default)]
+    stop_reason: Option<String>, // message_delta
+}
+
+fn parse_anthropic(value: serde_json::Value) -> serde_json::Result<Vec<StreamEvent>> {
+    let event: AnthropicStreamEvent = serde_json::from_value(value)?;
+    let mut events = Vec::new();
+    match event.event_type.as_str() {
+        "message_start" => {
+            if let Some(message) = event.message {
+                events.extend(message.model.map(StreamEvent::Model));
+                events.extend(message.usage.map(StreamEvent::Usage));
+            }
+        }
+        "content_block_delta" => {
+            if let Some(delta) = event.delta {
+                events.extend(delta.thinking.map(StreamEvent::Reasoning));
+                events.extend(delta.text.map(StreamEvent::Text));
+            }
+        }
+        "message_delta" => {
+            events.extend(
+                event
+                    .delta
+                    .and_then(|d| d.stop_reason)
+                    .map(StreamEvent::Stop),
+            );
+            events.extend(event.usage.map(StreamEvent::Usage));
+        }
+        "message_stop" => events.push(StreamEvent::Done),
+        _ => {} // ping, content_block_start/stop
+    }
+    Ok(events)
+}
+
+// Gemini
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiStreamChunk {
+    #[serde(default)]
+    candidates: Vec<GeminiStreamCandidate>,
+    #[serde(default)]
+    usage_metadata: Option<GeminiUsage>,
+    #[serde(default)]
+    model_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiStreamCandidate {
+    #[serde(default)]
+    content: Option<GeminiStreamContent>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiStreamContent {
+    #[serde(default)]
+    parts: Vec<GeminiStreamPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiStreamPart {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    thought: bool, // Thought summary part
+}
+
+/// Gemini `usageMetadata` (also used by non-streaming responses)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GeminiUsage {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+    #[serde(default)]
+    cached_content_token_count: u64,
+}
+
+impl From<&GeminiUsage> for TokenUsage {
+    fn from(usage: &GeminiUsage) -> Self {
+        TokenUsage {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: usage.cached_content_token_count,
+        }
+    }
+}
+
+fn parse_gemini(value: serde_json::Value) -> serde_json::Result<Vec<StreamEvent>> {
+    let chunk: GeminiStreamChunk = serde_json::from_value(value)?;
+    let mut events = Vec::new();
+    if let Some(candidate) = chunk.candidates.into_iter().next() {
+        let parts = candidate.content.map(|c| c.parts).unwrap_or_default();
+        for part in parts {
+            if let Some(text) = part.text {
+                events.push(if part.thought {
+                    StreamEvent::Reasoning(text)
+                } else {
+                    StreamEvent::Text(text)
+                });
+            }
+        }
+        events.extend(candidate.finish_reason.map(StreamEvent::Stop));
+    }
+    if let Some(usage) = &chunk.usage_metadata {
+        events.push(StreamEvent::Usage(usage.into()));
+    }
+    events.extend(chunk.model_version.map(StreamEvent::Model));
+    Ok(events)
+}
+
+// OpenAI-compatible (OpenAI, DeepSeek)
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    #[serde(default)]
+    usage: Option<OpenAIUsage>, // Final chunk (OpenAI: stream_options.include_usage)
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChoice {
+    #[serde(default)]
+    delta: Option<OpenAIStreamDelta>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+    /// Reasoning chunk (DeepSeek R1 and other OpenAI-compatible reasoning models)
+    #[serde(default, alias = "reasoning")]
+    reasoning_content: Option<String>,
+}
+
+/// OpenAI-compatible `usage` (also used by non-streaming responses)
+#[derive(Debug, Deserialize)]
+pub(crate) struct OpenAIUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    #[serde(default)]
+    prompt_tokens_details: Option<OpenAIPromptTokensDetails>,
+    #[serde(default)]
+    prompt_cache_hit_tokens: u64, // DeepSeek
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIPromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
+}
+
+impl From<&OpenAIUsage> for TokenUsage {
+    fn from(usage: &OpenAIUsage) -> Self {
+        let cached = usage
+            .prompt_tokens_details
+            .as_ref()
+            .map(|d| d.cached_tokens)
+            .unwrap_or_default();
+        TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: cached.max(usage.prompt_cache_hit_tokens),
+        }
+    }
+}
+
+fn parse_openai(value: serde_json::Value) -> serde_json::Result<Vec<StreamEvent>> {
+    let chunk: OpenAIStreamChunk = serde_json::from_value(value)?;
+    let mut events = Vec::new();
+    if let Some(choice) = chunk.choices.into_iter().next() {
+        if let Some(delta) = choice.delta {
+            events.extend(
+                delta
+                    .reasoning_content
+                    .filter(|r| !r.is_empty())
+                    .map(StreamEvent::Reasoning),
+            );
+            events.extend(
+                delta
+                    .content
+                    .filter(|c| !c.is_empty())
+                    .map(StreamEvent::Text),
+            );
+        }
+        events.extend(choice.finish_reason.map(StreamEvent::Stop));
+    }
+    if let Some(usage) = &chunk.usage {
+        events.push(StreamEvent::Usage(usage.into()));
+    }
+    // Every chunk names the model; only report it with the closing chunks
+    if chunk.usage.is_some() || events.iter().any(|e| matches!(e, StreamEvent::Stop(_))) {
+        events.extend(chunk.model.map(StreamEvent::Model));
+    }
+    Ok(events)
+}
+
+// ------------------------------------------------------------------
+// Byte Stream → Event Stream
+// ------------------------------------------------------------------
+
+struct DecodeState<S> {
+    bytes: std::pin::Pin<Box<S>>,
+    decoder: SseDecoder,
+    pending: VecDeque<Result<StreamEvent, LLMError>>,
+    finished: bool,
+}
+
+impl<S> DecodeState<S> {
+    fn queue(&mut self, format: StreamFormat, events: Vec<SseEvent>) {
+        for event in events {
+            match format.parse(&event) {
+                Ok(parsed) => {
+                    for parsed in parsed {
+                        let done = parsed == StreamEvent::Done;
+                        self.pending.push_back(Ok(parsed));
+                        if done {
+                            self.finished = true;
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.pending.push_back(Err(e));
+                    self.finished = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Decode a provider response body into typed events. The stream ends after `Done`,
+/// the first error, or the end of the body.
+pub fn decode<S, B, E>(
+    bytes: S,
+    format: StreamFormat,
+) -> impl Stream<Item = Result<StreamEvent, LLMError>> + Send
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send,
+    E: Display + Send,
+{
+    let state = DecodeState {
+        bytes: Box::pin(bytes),
+        decoder: SseDecoder::new(),
+        pending: VecDeque::new(),
+        finished: false,
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.finished {
+                return None;
+            }
+            match state.bytes.next().await {
+                Some(Ok(chunk)) => {
+                    let events = state.decoder.feed(chunk.as_ref());
+                    state.queue(format, events);
+                }
+                Some(Err(e)) => {
+                    state
+                        .pending
+                        .push_back(Err(LLMError::NetworkError(e.to_string())));
+                    state.finished = true;
+                }
+                None => {
+                    let events = state.decoder.finish().into_iter().collect();
+                    state.queue(format, events);
+                    state.finished = true;
+                }
+            }
+        }
+    })
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `body` in `chunk_size`-byte chunks and collect the framed events
+    fn decode_chunked(body: &str, chunk_size: usize) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events = Vec::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            events.extend(decoder.feed(chunk));
+        }
+        events.extend(decoder.finish());
+        events
+    }
+
+    fn collect(
+        body: &str,
+        chunk_size: usize,
+        format: StreamFormat,
+    ) -> Vec<Result<StreamEvent, String>> {
+        let chunks: Vec<Result<Vec<u8>, std::io::Error>> = body
+            .as_bytes()
+            .chunks(chunk_size)
+            .map(|c| Ok(c.to_vec()))
+            .collect();
+        let events = decode(futures::stream::iter(chunks), format);
+        futures::executor::block_on(events.collect::<Vec<_>>())
+            .into_iter()
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    fn text(events: &[Result<StreamEvent, String>]) -> String {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                Ok(StreamEvent::Text(t)) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    const ANTHROPIC_FIXTURE: &str = concat!(
+        "event: message_start\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4-5-20250929\",\"usage\":{\"input_tokens\":12,\"cache_creation_input_tokens\":2048,\"cache_read_input_tokens\":0,\"output_tokens\":1}}}\n\n",
+        "event: ping\n",
+        "data: {\"type\":\"ping\"}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Step 1\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Acme \"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"is growing \u{1F4C8}\"}}\n\n",
+        "event: message_delta\n",
+        "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"},\"usage\":{\"output_tokens\":350}}\n\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}\n\n",
+    );
+
+    const OPENAI_FIXTURE: &str = concat!(
+        ": keep-alive\n\n",
+        "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"Consider margins\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\" world\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+        "data: {\"model\":\"gpt-4o-2024-08-06\",\"choices\":[],\"usage\":{\"prompt_tokens\":40,\"completion_tokens\":2,\"prompt_tokens_details\":{\"cached_tokens\":32}}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    const GEMINI_FIXTURE: &str = concat!(
+        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Thinking...\",\"thought\":true},{\"text\":\"Part one, \"}]}}]}\r\n\r\n",
+        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"part two.\"}]},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":20,\"candidatesTokenCount\":5,\"cachedContentTokenCount\":8}}\r\n\r\n",
+    );
+
+    #[test]
+    fn test_decoder_frames_fields_comments_and_multiline_data() {
+        let events = decode_chunked(
+            ": comment\nevent: update\nid: 7\ndata: line one\ndata:line two\nretry: 1000\n\ndata\n\n",
+            usize::MAX,
+        );
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("update".into()),
+                    data: "line one\nline two".into(),
+                    id: Some("7".into()),
+                },
+                SseEvent {
+                    event: None,
+                    data: "".into(),
+                    id: Some("7".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decoder_handles_every_line_ending() {
+        let lf = decode_chunked("data: a\n\ndata: b\n\n", usize::MAX);
+        let crlf = decode_chunked("data: a\r\n\r\ndata: b\r\n\r\n", usize::MAX);
+        let cr = decode_chunked("data: a\r\rdata: b\r\r", usize::MAX);
+        assert_eq!(lf.len(), 2);
+        assert_eq!(lf, crlf);
+        assert_eq!(lf, cr);
+    }
+
+    #[test]
+    fn test_decoder_is_independent_of_chunk_boundaries() {
+        // Includes a multi-byte UTF-8 character that small chunks cut in half
+        let whole = decode_chunked(ANTHROPIC_FIXTURE, usize::MAX);
+        assert_eq!(whole.len(), 7);
+        for size in 1..=17 {
+            assert_eq!(
+                decode_chunked(ANTHROPIC_FIXTURE, size),
+                whole,
+                "chunk size {}",
+                size
+            );
+        }
+        // CRLF pairs cut between chunks
+        let gemini = decode_chunked(GEMINI_FIXTURE, usize::MAX);
+        for size in 1..=7 {
+            assert_eq!(
+                decode_chunked(GEMINI_FIXTURE, size),
+                gemini,
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_decoder_flushes_unterminated_event_and_ignores_empty_events() {
+        let events = decode_chunked("event: noop\n\ndata: tail", usize::MAX);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "tail");
+        assert_eq!(events[0].event, None);
+    }
+
+    #[test]
+    fn test_anthropic_stream_yields_typed_events() {
+        for size in [1, 5, 64, usize::MAX] {
+            let events = collect(ANTHROPIC_FIXTURE, size, StreamFormat::Anthropic);
+            assert_eq!(text(&events), "Acme is growing \u{1F4C8}");
+            assert!(events.contains(&Ok(StreamEvent::Reasoning("Step 1".into()))));
+            assert!(events.contains(&Ok(StreamEvent::Stop("max_tokens".into()))));
+            assert!(events.contains(&Ok(StreamEvent::Model("claude-sonnet-4-5-20250929".into()))));
+            assert!(events.contains(&Ok(StreamEvent::Usage(TokenUsage {
+                input_tokens: 12,
+                output_tokens: 1,
+                cache_creation_input_tokens: 2048,
+                cache_read_input_tokens: 0,
+            }))));
+            assert!(events.contains(&Ok(StreamEvent::Usage(TokenUsage {
+                output_tokens: 350,
+                ..Default::default()
+            }))));
+            assert_eq!(events.last(), Some(&Ok(StreamEvent::Done)));
+        }
+    }
+
+    #[test]
+    fn test_openai_stream_yields_typed_events() {
+        for size in [1, 3, usize::MAX] {
+            let events = collect(OPENAI_FIXTURE, size, StreamFormat::OpenAI);
+            assert_eq!(text(&events), "Hello world");
+            assert_eq!(
+                events[0],
+                Ok(StreamEvent::Reasoning("Consider margins".into()))
+            );
+            assert!(events.contains(&Ok(StreamEvent::Stop("length".into()))));
+            assert!(events.contains(&Ok(StreamEvent::Model("gpt-4o-2024-08-06".into()))));
+            assert!(events.contains(&Ok(StreamEvent::Usage(TokenUsage {
+                input_tokens: 40,
+                output_tokens: 2,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 32,
+            }))));
+            assert_eq!(events.last(), Some(&Ok(StreamEvent::Done)));
+        }
+    }
+
+    #[test]
+    fn test_gemini_stream_yields_every_part() {
+        let events = collect(GEMINI_FIXTURE, 4, StreamFormat::Gemini);
+        assert_eq!(text(&events), "Part one, part two.");
+        assert_eq!(events[0], Ok(StreamEvent::Reasoning("Thinking...".into())));
+        assert!(events.contains(&Ok(StreamEvent::Stop("STOP".into()))));
+        assert!(events.contains(&Ok(StreamEvent::Usage(TokenUsage {
+            input_tokens: 20,
+            output_tokens: 5,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 8,
+        }))));
+    }
+
+    #[test]
+    fn test_stream_ends_after_done() {
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\ndata: [DONE]\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"b\"}}]}\n\n";
+        let events = collect(body, 8, StreamFormat::OpenAI);
+        assert_eq!(text(&events), "a");
+        assert_eq!(events.last(), Some(&Ok(StreamEvent::Done)));
+    }
+
+    #[test]
+    fn test_error_events_end_the_stream() {
+        let anthropic = concat!(
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\"partial\"}}\n\n",
+            "event: error\n",
+            "data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\"ignored\"}}\n\n",
+        );
+        let events = collect(anthropic, 10, StreamFormat::Anthropic);
+        assert_eq!(text(&events), "partial");
+        assert_eq!(
+            events.last(),
+            Some(&Err("Provider API error: Overloaded".to_string()))
+        );
+
+        let gemini = "data: {\"error\":{\"code\":429,\"message\":\"Resource exhausted\"}}\n\n";
+        let events = collect(gemini, usize::MAX, StreamFormat::Gemini);
+        assert_eq!(
+            events,
+            vec![Err("Provider API error: Resource exhausted".to_string())]
+        );
+
+        let malformed = "data: {\"choices\": [\n\n";
+        let events = collect(malformed, usize::MAX, StreamFormat::OpenAI);
+        assert!(events[0]
+            .as_ref()
+            .unwrap_err()
+            .contains("Malformed OpenAI stream event"));
+    }
+
+    #[test]
+    fn test_network_errors_are_surfaced() {
+        let chunks: Vec<Result<Vec<u8>, std::io::Error>> = vec![
+            Ok(b"data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\n".to_vec()),
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "reset",
+            )),
+        ];
+        let events: Vec<_> = futures::executor::block_on(
+            decode(futures::stream::iter(chunks), StreamFormat::OpenAI).collect(),
+        );
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[1], Err(LLMError::NetworkError(msg)) if msg == "reset"));
+    }
+}
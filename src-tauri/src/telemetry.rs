@@ -0,0 +1,125 @@
+//! Structured Logging (tracing) and Session Run Logs
+//!
+//! - `init` installs the global `tracing` subscriber: human-readable output on
+//!   stdout plus a daily rolling log file in `<app_data>/logs`
+//! - Spans: `workflow` (session_id, manifest) > `phase` (phase_id, model) >
+//!   `provider_request` (provider, model, latency_ms)
+//! - `RunLogEntry` is one line of a session's run log: the agent keeps them in
+//!   `AgentState.logs` and they are stored with the session (`session_run_logs`)
+//!
+//! Filtering follows `RUST_LOG` when set (default: `info`).
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::Level;
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Subdirectory of the app data directory holding the rolling log files
+pub const LOG_DIR: &str = "logs";
+
+/// Rolling log files are named `fullintel-agent.<date>.log`
+const LOG_FILE_PREFIX: &str = "fullintel-agent";
+
+/// Daily log files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 14;
+
+/// Install the global subscriber (stdout + rolling file in `log_dir`).
+/// The returned guard flushes the file writer on drop: keep it for the app's lifetime.
+/// If the log file cannot be opened, stdout logging is still installed and the error
+/// is returned (log it with `tracing`).
+pub fn init(log_dir: &Path) -> Result<WorkerGuard, String> {
+    let (file_writer, guard) = match open_log_file(log_dir) {
+        Ok((writer, guard)) => (Some(writer), Ok(guard)),
+        Err(e) => (None, Err(e)),
+    };
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_target(false))
+        .with(file_writer.map(|writer| fmt::layer().with_ansi(false).with_writer(writer)))
+        .try_init()
+        .map_err(|e| format!("Failed to install tracing subscriber: {}", e))?;
+
+    guard
+}
+
+/// Non-blocking writer for the daily rolling log file in `log_dir`
+fn open_log_file(log_dir: &Path) -> Result<(NonBlocking, WorkerGuard), String> {
+    std::fs::create_dir_all(log_dir)
+        .map_err(|e| format!("Failed to create log directory {:?}: {}", log_dir, e))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+// ------------------------------------------------------------------
+// Session Run Log
+// ------------------------------------------------------------------
+
+/// One line of a session's run log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunLogEntry {
+    pub timestamp: String, // RFC 3339, local time
+    pub level: String,     // "info" | "warn" | "error"
+    pub phase_id: Option<String>,
+    pub message: String,
+}
+
+impl RunLogEntry {
+    pub fn new(level: Level, phase_id: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: level.as_str().to_ascii_lowercase(),
+            phase_id: phase_id.map(|s| s.to_string()),
+            message: message.into(),
+        }
+    }
+}
+
+/// Level for an agent log line, from its status emoji (❌ error; ⚠️/✂️ warning)
+pub fn level_for(message: &str) -> Level {
+    if message.starts_with('❌') {
+        Level::ERROR
+    } else if message.starts_with('⚠') || message.starts_with('✂') {
+        Level::WARN
+    } else {
+        Level::INFO
+    }
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_follows_status_emoji() {
+        assert_eq!(level_for("❌ ERROR after 1.2s: timeout"), Level::ERROR);
+        assert_eq!(level_for("⚠️ Stream error: reset"), Level::WARN);
+        assert_eq!(level_for("✂️ TRUNCATED at max_tokens"), Level::WARN);
+        assert_eq!(level_for("📥 COMPLETE: 12 tokens"), Level::INFO);
+    }
+
+    #[test]
+    fn test_run_log_entry_serializes_lowercase_level() {
+        let entry = RunLogEntry::new(Level::WARN, Some("PHASE-01"), "careful");
+        assert_eq!(entry.level, "warn");
+        assert_eq!(entry.phase_id.as_deref(), Some("PHASE-01"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&entry.timestamp).is_ok());
+    }
+}
@@ -0,0 +1,244 @@
+//! Stream Token Batching
+//!
+//! Providers stream many small chunks; emitting one `stream-token` event per chunk
+//! floods IPC on fast models. This module coalesces streamed text:
+//! - `TokenBatcher` buffers text until `max_chars` is reached or the oldest buffered
+//!   text is `window_ms` old (whichever comes first); `flush` returns the remainder
+//! - `BatchSender` hands batches to a background task, so a slow event consumer never
+//!   stalls the provider read loop. While the task is behind, new batches are merged
+//!   into one pending batch instead of queueing; `finish` delivers everything.
+//!   Each batch carries a stamp taken when it was produced (the event scope, whose
+//!   `seq` must follow emission order); a merged batch keeps the earliest stamp.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+
+/// Batches waiting for the delivery task before new text is merged instead
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Coalescing settings for streamed text (both 0 = one event per chunk)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TokenBatchConfig {
+    /// Longest buffered text waits before it is emitted (0 = no time limit)
+    pub window_ms: u64,
+    /// Emit once this many characters are buffered (0 = no size limit)
+    pub max_chars: usize,
+}
+
+impl Default for TokenBatchConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 50,
+            max_chars: 512,
+        }
+    }
+}
+
+impl TokenBatchConfig {
+    /// One event per provider chunk
+    #[cfg(test)]
+    pub fn unbatched() -> Self {
+        Self {
+            window_ms: 0,
+            max_chars: 0,
+        }
+    }
+
+    fn is_unbatched(&self) -> bool {
+        self.window_ms == 0 && self.max_chars == 0
+    }
+}
+
+/// Buffers streamed text into batches
+pub struct TokenBatcher {
+    config: TokenBatchConfig,
+    buffer: String,
+    buffered_chars: usize,
+    first_buffered_at: Option<Instant>,
+}
+
+impl TokenBatcher {
+    pub fn new(config: TokenBatchConfig) -> Self {
+        Self {
+            config,
+            buffer: String::new(),
+            buffered_chars: 0,
+            first_buffered_at: None,
+        }
+    }
+
+    /// Add streamed text; returns a batch once the size limit is reached
+    /// (or immediately when batching is off)
+    pub fn push(&mut self, text: &str) -> Option<String> {
+        if text.is_empty() {
+            return None;
+        }
+        self.buffer.push_str(text);
+        self.buffered_chars += text.chars().count();
+        self.first_buffered_at.get_or_insert_with(Instant::now);
+
+        let full = self.config.max_chars > 0 && self.buffered_chars >= self.config.max_chars;
+        if full || self.config.is_unbatched() {
+            return self.flush();
+        }
+        None
+    }
+
+    /// When the buffered text is due (None while empty or without a time window)
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.config.window_ms == 0 {
+            return None;
+        }
+        self.first_buffered_at
+            .map(|at| at + Duration::from_millis(self.config.window_ms))
+    }
+
+    /// Take the buffered text if its time window has elapsed at `now`
+    pub fn poll_due(&mut self, now: Instant) -> Option<String> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => self.flush(),
+            _ => None,
+        }
+    }
+
+    /// Take whatever is buffered (end of stream)
+    pub fn flush(&mut self) -> Option<String> {
+        self.first_buffered_at = None;
+        self.buffered_chars = 0;
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.buffer))
+        }
+    }
+}
+
+/// Delivers stamped batches from a background task (see module docs)
+pub struct BatchSender<S> {
+    tx: mpsc::Sender<(S, String)>,
+    pending: Option<(S, String)>,
+    task: JoinHandle<()>,
+}
+
+impl<S: Send + 'static> BatchSender<S> {
+    /// Start the delivery task; `deliver` is called with each stamp and batch, in order
+    pub fn spawn<F>(mut deliver: F) -> Self
+    where
+        F: FnMut(S, String) + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<(S, String)>(CHANNEL_CAPACITY);
+        let task = tokio::spawn(async move {
+            while let Some((stamp, batch)) = rx.recv().await {
+                deliver(stamp, batch);
+            }
+        });
+        Self {
+            tx,
+            pending: None,
+            task,
+        }
+    }
+
+    /// Queue a batch without waiting; merged into the pending batch while the task is behind
+    pub fn send(&mut self, stamp: S, batch: String) {
+        let batch = match self.pending.take() {
+            Some((pending_stamp, mut pending)) => {
+                pending.push_str(&batch);
+                (pending_stamp, pending)
+            }
+            None => (stamp, batch),
+        };
+        match self.tx.try_send(batch) {
+            Ok(()) => {}
+            Err(TrySendError::Full(batch)) => self.pending = Some(batch),
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// Deliver any held-back text and wait until every batch has been delivered
+    pub async fn finish(mut self) {
+        if let Some(pending) = self.pending.take() {
+            let _ = self.tx.send(pending).await;
+        }
+        drop(self.tx);
+        let _ = self.task.await;
+    }
+}
+
+// ------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_batches_by_char_count() {
+        let mut batcher = TokenBatcher::new(TokenBatchConfig {
+            window_ms: 0,
+            max_chars: 5,
+        });
+
+        assert_eq!(batcher.push("ab"), None);
+        assert_eq!(batcher.push("cdé"), Some("abcdé".to_string()));
+        assert_eq!(batcher.push("f"), None);
+        assert_eq!(batcher.deadline(), None);
+        assert_eq!(batcher.flush(), Some("f".to_string()));
+        assert_eq!(batcher.flush(), None);
+    }
+
+    #[test]
+    fn test_batches_by_time_window() {
+        let mut batcher = TokenBatcher::new(TokenBatchConfig {
+            window_ms: 20,
+            max_chars: 0,
+        });
+        assert_eq!(batcher.deadline(), None);
+
+        assert_eq!(batcher.push("Hello"), None);
+        let deadline = batcher.deadline().unwrap();
+        assert_eq!(batcher.poll_due(deadline - Duration::from_millis(1)), None);
+        assert_eq!(batcher.push(", world"), None);
+        assert_eq!(batcher.poll_due(deadline), Some("Hello, world".to_string()));
+        assert_eq!(batcher.deadline(), None);
+    }
+
+    #[test]
+    fn test_unbatched_emits_every_chunk() {
+        let mut batcher = TokenBatcher::new(TokenBatchConfig::unbatched());
+        assert_eq!(batcher.push("a"), Some("a".to_string()));
+        assert_eq!(batcher.push(""), None);
+        assert_eq!(batcher.push("b"), Some("b".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_slow_consumer_gets_all_text_in_order() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = delivered.clone();
+        let mut sender = BatchSender::spawn(move |stamp, batch| {
+            std::thread::sleep(Duration::from_millis(2));
+            sink.lock().unwrap().push((stamp, batch));
+        });
+
+        let sent: Vec<String> = (0..200).map(|i| format!("{} ", i)).collect();
+        for (i, batch) in sent.iter().enumerate() {
+            sender.send(i, batch.clone());
+        }
+        sender.finish().await;
+
+        let delivered = delivered.lock().unwrap();
+        let text: Vec<&str> = delivered.iter().map(|(_, batch)| batch.as_str()).collect();
+        assert_eq!(text.concat(), sent.concat());
+        // The consumer fell behind, so batches were merged rather than queued
+        assert!(delivered.len() < sent.len());
+        // Each batch keeps the stamp of its first text
+        for (stamp, batch) in delivered.iter() {
+            assert!(batch.starts_with(&sent[*stamp]));
+        }
+    }
+}
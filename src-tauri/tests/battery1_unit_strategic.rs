@@ -44,6 +44,7 @@ fn test_llmclient_multi_provider_property() {
             system: "You are a helpful assistant.".to_string(),
             user: "Hello, world!".to_string(),
             model: model.to_string(),
+            ..Default::default()
         };
 
         // Component 4: Request struct fields accessible
@@ -645,6 +646,7 @@ fn test_llmrequest_empty_system_prompt() {
         system: String::new(),
         user: "Hello, world!".to_string(),
         model: "claude-3-sonnet".to_string(),
+        ..Default::default()
     };
 
     // 2. Verify fields are accessible
@@ -669,6 +671,7 @@ fn test_llmrequest_empty_user_message() {
         system: "You are a helpful assistant".to_string(),
         user: String::new(),
         model: "gpt-4".to_string(),
+        ..Default::default()
     };
 
     // 2. Verify empty user message is handled
@@ -693,6 +696,7 @@ fn test_llmrequest_model_validation() {
         system: "test".to_string(),
         user: "test".to_string(),
         model: "claude-3-sonnet-20240229".to_string(),
+        ..Default::default()
     };
     assert!(claude.model.starts_with("claude-"));
 
@@ -701,6 +705,7 @@ fn test_llmrequest_model_validation() {
         system: "test".to_string(),
         user: "test".to_string(),
         model: "gpt-4-turbo".to_string(),
+        ..Default::default()
     };
     assert!(gpt.model.starts_with("gpt-"));
 
@@ -709,6 +714,7 @@ fn test_llmrequest_model_validation() {
         system: "test".to_string(),
         user: "test".to_string(),
         model: "gemini-pro".to_string(),
+        ..Default::default()
     };
     assert!(gemini.model.starts_with("gemini-"));
 
@@ -717,6 +723,7 @@ fn test_llmrequest_model_validation() {
         system: "test".to_string(),
         user: "test".to_string(),
         model: "qwen-max".to_string(),
+        ..Default::default()
     };
     assert!(qwen.model.starts_with("qwen-"));
 
@@ -737,6 +744,7 @@ fn test_llmrequest_serialization() {
         system: "You are helpful".to_string(),
         user: "Hello".to_string(),
         model: "claude-3-sonnet".to_string(),
+        ..Default::default()
     };
 
     // 2. Serialize to JSON
@@ -767,6 +775,7 @@ fn test_llmrequest_cloning() {
         system: "Original system".to_string(),
         user: "Original user".to_string(),
         model: "claude-3-sonnet".to_string(),
+        ..Default::default()
     };
 
     // 2. Clone the request
@@ -7,12 +7,14 @@ import "./App.css";
 import { AuthScreen } from "./components/AuthScreen";
 import { SettingsPanel } from "./components/SettingsPanel";
 import { ManifestEditor } from "./components/ManifestEditor";
+import { newRunId, subscribeAgentEvents, ApprovalDecision, PhaseMetrics, PhaseOutputPayload, PhaseProgress } from "./agentEvents";
 import fullintelLogoWide from "./assets/fullintel_logo_wide.jpg";
 
 type Phase = {
   id: string;
   name: string;
-  status: "pending" | "running" | "completed" | "failed";
+  status: "pending" | "running" | "awaiting_approval" | "completed" | "failed";
+  progress?: PhaseProgress | null;  // Foreach phases: items finished so far
 };
 
 type PhaseInfo = {
@@ -20,11 +22,41 @@ type PhaseInfo = {
   name: string;
 };
 
+// Result of validate_manifest (matches Rust ManifestValidation); only the fields used here
+type ManifestValidation = {
+  phases: PhaseInfo[];
+  diagnostics: { severity: "error" | "warning" | "info"; message: string; span: { line: number } | null }[];
+};
+
 type SavedManifest = {
   name: string;
   path: string;
 };
 
+// Research input declared in the manifest's `inputs:` block (matches Rust InputField)
+type InputField = {
+  name: string;
+  type: "string" | "url" | "enum" | "number" | "date" | "file";
+  label: string | null;
+  required: boolean;
+  default: string | number | boolean | null;
+  options: string[];  // enum values
+  help: string | null;
+};
+
+// Initial form values from the declared defaults
+const inputDefaults = (fields: InputField[]): Record<string, string> =>
+  Object.fromEntries(
+    fields.map((field) => [field.name, field.default === null ? "" : String(field.default)])
+  );
+
+// Research subject shown for sessions and briefs: the filled-in values, in manifest order
+const summarizeInputs = (fields: InputField[], values: Record<string, string>): string =>
+  fields
+    .map((field) => values[field.name]?.trim())
+    .filter(Boolean)
+    .join(" · ");
+
 type UserInfo = {
   id: number;
   username: string;
@@ -65,22 +97,7 @@ type CustomProviderSummary = {
   has_key: boolean;
 };
 
-type LogPayload = { message: string };
-type PhasePayload = { phase_id: string; status: string };
-type StreamPayload = { token: string; phase_id: string };
-
-// Phase output payload for session persistence (matches Rust PhaseOutputPayload)
-// IM-5001, IM-5002: Extended with system_prompt and user_input for user data accessibility
-type PhaseOutputPayload = {
-  session_id: number | null;
-  phase_id: string;
-  phase_name: string;
-  status: string;  // "running", "completed", "failed"
-  system_prompt: string | null;  // IM-5001: System prompt sent to LLM
-  user_input: string | null;     // IM-5002: User input/manifest data sent to LLM
-  output: string | null;
-  error: string | null;
-};
+type FollowupTokenPayload = { token: string; session_id: number | null };
 
 // Research session types (matches Rust structs)
 type ResearchSessionSummary = {
@@ -110,18 +127,31 @@ type PhaseOutputRecord = {
   error: string | null;
   created_at: string;
   updated_at: string;
+  cache_hit: boolean;
+  reasoning: string | null;
+  stop_reason: string | null;
+  truncated: boolean;
+  metrics: PhaseMetrics | null;
 };
 
 // IM-5030: Session-level conversation message (separate from brief-level)
-// Reserved for future conversation tracking feature - uncomment when implementing
-// type SessionMessage = {
-//   id: number;
-//   session_id: number;
-//   phase_id: string | null;
-//   role: string;  // "user", "assistant", "system"
-//   content: string;
-//   created_at: string;
-// };
+// Follow-up chat turns are stored with phase_id "followup"
+type SessionMessage = {
+  id: number;
+  session_id: number;
+  phase_id: string | null;
+  role: string;  // "user", "assistant", "system"
+  content: string;
+  created_at: string;
+};
+
+// One line of a session's stored run log (agent progress, warnings and errors)
+type RunLogEntry = {
+  timestamp: string;
+  level: string;  // "info", "warn", "error"
+  phase_id: string | null;
+  message: string;
+};
 
 // IM-5020: Session resume types for reconstructing context
 type SessionHistoryMessage = {
@@ -146,14 +176,34 @@ type ResearchSession = {
   manifest_name: string | null;
   status: string;
   current_phase_id: string | null;
+  manifest_version_id: number | null;  // Manifest library version the session ran
   created_at: string;
   updated_at: string;
 };
 
+// Saved manifest version (matches Rust auth::ManifestVersion; content omitted)
+type ManifestVersion = {
+  id: number;
+  manifest_key: string;
+  revision: number;
+  version: string;
+  content_hash: string;
+  author: string;
+  created_at: string;
+};
+
+// Phase held for review when its run ended (matches Rust PendingReview)
+type PendingReview = {
+  phase_id: string;
+  phase_name: string;
+  output: string;
+};
+
 type ResumeSessionResult = {
   session: ResearchSession;
   next_phase_id: string;
   context: SessionContext;
+  pending_review: PendingReview | null;
 };
 
 // Project types for organizing sessions into groups
@@ -182,6 +232,7 @@ const getProviderInfo = (model: string): { name: string; provider: string } => {
   if (model.startsWith("deepseek")) return { name: "DeepSeek", provider: "deepseek" };
   if (model.startsWith("gemini")) return { name: "Google", provider: "google" };
   if (model.startsWith("gpt")) return { name: "OpenAI", provider: "openai" };
+  if (model.startsWith("mock")) return { name: "Mock (Offline)", provider: "mock" };
   return { name: "OpenAI", provider: "openai" };
 };
 
@@ -243,6 +294,25 @@ const getPhaseDisplayName = (name: string): string => {
   return words.slice(0, 2).join(' ');
 };
 
+// Timeline rows for phases with recorded timing: offset and width as % of the run
+const buildPhaseTimeline = (outputs: PhaseOutputRecord[]) => {
+  const timed = outputs.filter((o) => o.metrics && o.metrics.started_at);
+  if (timed.length === 0) return [];
+  const start = Math.min(...timed.map((o) => Date.parse(o.metrics!.started_at)));
+  const end = Math.max(...timed.map((o) => Date.parse(o.metrics!.finished_at)));
+  const span = Math.max(end - start, 1);
+  return timed.map((o) => {
+    const m = o.metrics!;
+    return {
+      phaseId: o.phase_id,
+      name: getPhaseDisplayName(o.phase_name),
+      metrics: m,
+      offsetPct: ((Date.parse(m.started_at) - start) / span) * 100,
+      widthPct: Math.max((m.duration_ms / span) * 100, 0.5),
+    };
+  });
+};
+
 function App() {
   // Authentication state
   const [currentUser, setCurrentUser] = useState<UserInfo | null>(null);
@@ -272,6 +342,9 @@ function App() {
   const [manifestName, setManifestName] = useState("Default (Fullintel)");
   const [manifestDescription, setManifestDescription] = useState<string | null>(null); // Description shown in main window
   const [manifestInputLabel, setManifestInputLabel] = useState<string | null>(null); // Dynamic placeholder from manifest
+  const [manifestInputs, setManifestInputs] = useState<InputField[]>([]); // Declared inputs (empty = single subject)
+  const [inputValues, setInputValues] = useState<Record<string, string>>({});
+  const [attachedFiles, setAttachedFiles] = useState<string[]>([]); // Local files sent with the next research run
   const [savedManifests, setSavedManifests] = useState<SavedManifest[]>([]);
   const [showManifestDropdown, setShowManifestDropdown] = useState(false);
   const [showManifestEditor, setShowManifestEditor] = useState(false);
@@ -281,6 +354,8 @@ function App() {
   const [followupInput, setFollowupInput] = useState("");
   const [conversation, setConversation] = useState<ConversationMessage[]>([]);
   const [isFollowupRunning, setIsFollowupRunning] = useState(false);
+  // Research session the follow-up chat belongs to (history is stored server-side)
+  const [followupSessionId, setFollowupSessionId] = useState<number | null>(null);
 
   // Saved briefs state
   const [savedBriefs, setSavedBriefs] = useState<BriefSummary[]>([]);
@@ -315,6 +390,17 @@ function App() {
     userInput: string | null;
   } | null>(null);
   const [showLivePrompt, setShowLivePrompt] = useState(false);
+  // Phase of the current run waiting at an approval checkpoint (approval: required).
+  // runId is null for a review re-opened on resume (decided against the stored session)
+  const [pendingApproval, setPendingApproval] = useState<{
+    runId: string | null;
+    sessionId: number | null;
+    phaseId: string;
+    phaseName: string;
+    output: string;
+  } | null>(null);
+  const [approvalEdit, setApprovalEdit] = useState("");
+  const [approvalFeedback, setApprovalFeedback] = useState("");
   // Track which session's action menu is open (null = none open)
   const [sessionMenuOpen, setSessionMenuOpen] = useState<number | null>(null);
   // Track dropdown position for fixed positioning (escapes overflow:hidden)
@@ -475,11 +561,12 @@ function App() {
     return () => document.removeEventListener('click', handleClickOutside);
   }, [projectMenuOpen, projectSessionMenuOpen, archivedMenuOpen]);
 
-  // Load manifest input_label when manifest path changes
+  // Load manifest input_label and declared inputs when manifest path changes
   useEffect(() => {
     const loadInputLabel = async () => {
       if (!manifestPath) {
         setManifestInputLabel(null);
+        setManifestInputs([]);
         return;
       }
       try {
@@ -489,10 +576,36 @@ function App() {
         console.error("Failed to load manifest input label:", error);
         setManifestInputLabel(null);
       }
+      try {
+        const info = await invoke<{ inputs: InputField[] }>("get_manifest_info", { manifestPath });
+        const defaults = inputDefaults(info.inputs);
+        setManifestInputs(info.inputs);
+        setInputValues(defaults);
+        if (info.inputs.length > 0) {
+          setCompany(summarizeInputs(info.inputs, defaults));
+        }
+      } catch (error) {
+        console.error("Failed to load manifest inputs:", error);
+        setManifestInputs([]);
+      }
     };
     loadInputLabel();
   }, [manifestPath]);
 
+  // Update one declared input; the research subject follows the filled-in values
+  const updateInputValue = (name: string, value: string) => {
+    const next = { ...inputValues, [name]: value };
+    setInputValues(next);
+    setCompany(summarizeInputs(manifestInputs, next));
+  };
+
+  const browseInputFile = async (name: string) => {
+    const selected = await open({ multiple: false });
+    if (selected && typeof selected === "string") {
+      updateInputValue(name, selected);
+    }
+  };
+
   // Load API key for current provider when model changes or user logs in
   useEffect(() => {
     const loadProviderKey = async () => {
@@ -502,6 +615,13 @@ function App() {
         return;
       }
 
+      // Built-in mock models run offline and need no key
+      if (model.startsWith("mock")) {
+        setApiKey("offline");
+        setApiKeyConfigured(true);
+        return;
+      }
+
       // Check if this is a custom provider (model value starts with "custom_")
       if (model.startsWith("custom_")) {
         try {
@@ -557,48 +677,12 @@ function App() {
   useEffect(() => {
     console.log("[DEBUG] Setting up Tauri event listeners...");
 
-    const unlistenLogs = listen<LogPayload>("agent-log", (event) => {
-      console.log("[DEBUG] agent-log received:", event.payload.message);
-      setLogs((prev) => [...prev, event.payload.message]);
-    });
-
-    const unlistenPhases = listen<PhasePayload>("phase-update", (event) => {
-      console.log("[DEBUG] phase-update received:", event.payload);
-      setPhases((prev) =>
-        prev.map((p) =>
-          p.id === event.payload.phase_id
-            ? { ...p, status: event.payload.status as Phase["status"] }
-            : p
-        )
-      );
-      // Clear streaming output when phase changes
-      if (event.payload.status === "running") {
-        setStreamingOutput("");
-      }
-    });
-
-    const unlistenStream = listen<StreamPayload>("stream-token", (event) => {
-      setStreamingOutput((prev) => prev + event.payload.token);
-    });
-
-    // Listen for phase-output events to persist to SQLite
+    // Listen for phase-output events to persist to SQLite (every run, including ones
+    // started elsewhere; the live display only follows the current run - see startResearch)
     // IM-5003: Extended to pass system_prompt and user_input for user data accessibility
     const unlistenPhaseOutput = listen<PhaseOutputPayload>("phase-output", async (event) => {
-      const { session_id, phase_id, phase_name, status, system_prompt, user_input, output, error } = event.payload;
-      console.log("[DEBUG] phase-output received:", { session_id, phase_id, status, hasPrompt: !!system_prompt });
-
-      // IM-5045: Update live prompt display during active research
-      if (status === "running" && (system_prompt || user_input)) {
-        setLivePhasePrompt({
-          phaseId: phase_id,
-          phaseName: phase_name,
-          systemPrompt: system_prompt,
-          userInput: user_input,
-        });
-      } else if (status === "completed" || status === "failed") {
-        // Clear live prompt when phase completes
-        setLivePhasePrompt(null);
-      }
+      const { session_id, phase_id, phase_name, status, system_prompt, user_input, output, error, cache_hit, reasoning, stop_reason, truncated, metrics } = event.payload;
+      console.log("[DEBUG] phase-output received:", { run_id: event.payload.run_id, session_id, phase_id, status, hasPrompt: !!system_prompt });
 
       // Only persist if we have a valid session_id
       if (session_id !== null) {
@@ -612,6 +696,7 @@ function App() {
             userInput: user_input,         // IM-5002: Pass user input
             output,
             error,
+            meta: status === "completed" ? { cache_hit, reasoning, stop_reason, truncated, metrics } : null,
           });
           console.log("[DEBUG] Phase output persisted:", phase_id, status);
         } catch (err) {
@@ -624,9 +709,6 @@ function App() {
 
     return () => {
       console.log("[DEBUG] Cleaning up event listeners");
-      unlistenLogs.then((f) => f());
-      unlistenPhases.then((f) => f());
-      unlistenStream.then((f) => f());
       unlistenPhaseOutput.then((f) => f());
     };
   }, []);
@@ -767,7 +849,7 @@ function App() {
 
   // Reset phases to pending status
   const resetPhases = () => {
-    setPhases(prev => prev.map(p => ({ ...p, status: "pending" as const })));
+    setPhases(prev => prev.map(p => ({ ...p, status: "pending" as const, progress: null })));
   };
 
   // Refresh saved briefs list
@@ -796,8 +878,29 @@ function App() {
       const outputs = await invoke<PhaseOutputRecord[]>("get_phase_outputs", { sessionId: session.id });
       setSelectedSession(session);
       setSessionPhaseOutputs(outputs);
+      setFollowupSessionId(session.id);
+      const followups = await invoke<SessionMessage[]>("get_session_conversation", {
+        sessionId: session.id,
+        phaseId: "followup",
+      });
+      setConversation(
+        followups
+          .filter((m) => m.role === "user" || m.role === "assistant")
+          .map((m) => ({ role: m.role as ConversationMessage["role"], content: m.content }))
+      );
+      const runLog = await invoke<RunLogEntry[]>("get_session_run_log", { sessionId: session.id });
+      const detail = await invoke<ResearchSession | null>("get_research_session", { sessionId: session.id });
+      const manifestVersion = detail?.manifest_version_id
+        ? await invoke<ManifestVersion | null>("get_manifest_version", { versionId: detail.manifest_version_id })
+        : null;
       setShowSessionsList(false);
-      setLogs([`📂 Viewing session: ${session.company} (${new Date(session.created_at).toLocaleString()})`]);
+      setLogs([
+        `📂 Viewing session: ${session.company} (${new Date(session.created_at).toLocaleString()})`,
+        ...(manifestVersion
+          ? [`📋 Manifest: ${manifestVersion.manifest_key} v${manifestVersion.version} (rev ${manifestVersion.revision}, sha256 ${manifestVersion.content_hash.slice(0, 12)})`]
+          : []),
+        ...runLog.map((entry) => `${new Date(entry.timestamp).toLocaleTimeString()} ${entry.message}`),
+      ]);
     } catch (error) {
       console.error("Failed to load session:", error);
       setLogs(prev => [...prev, `❌ Failed to load session: ${error}`]);
@@ -809,6 +912,8 @@ function App() {
     setSelectedSession(null);
     setSessionPhaseOutputs([]);
     setExpandedPrompts(new Set()); // Reset expanded prompts when closing
+    setFollowupSessionId(null);
+    setConversation([]);
   };
 
   // IM-5041: Toggle prompt visibility for a phase
@@ -934,7 +1039,7 @@ function App() {
       if (result.session.manifest_path) {
         try {
           const content = await invoke<string>("load_manifest_file", { path: result.session.manifest_path });
-          const phaseInfos = await invoke<PhaseInfo[]>("validate_manifest", { content });
+          const { phases: phaseInfos } = await invoke<ManifestValidation>("validate_manifest", { content, path: result.session.manifest_path });
           setPhases(phaseInfos.map(p => ({ ...p, status: "pending" as const })));
           setManifestPath(result.session.manifest_path);
           setManifestName(result.session.manifest_name || "Loaded Manifest");
@@ -945,6 +1050,20 @@ function App() {
         }
       }
 
+      // The run ended while waiting for review: re-open it
+      if (result.pending_review) {
+        setPendingApproval({
+          runId: null,
+          sessionId: result.session.id,
+          phaseId: result.pending_review.phase_id,
+          phaseName: result.pending_review.phase_name,
+          output: result.pending_review.output,
+        });
+        setApprovalEdit(result.pending_review.output);
+        setApprovalFeedback("");
+        setLogs(prev => [...prev, `⏸️ ${result.pending_review!.phase_name} is waiting for review`]);
+      }
+
       // Note: Session ID tracking happens via the phase-output events
       // TODO: In future, use result.context.history with multi-turn API for seamless resume
       setLogs(prev => [...prev, `✅ Session ready to resume. Click "Generate Brief" to continue.`]);
@@ -1809,13 +1928,21 @@ ${processLog}${reportContent}
 
     try {
       // Validate the YAML first
-      const phases = await invoke<PhaseInfo[]>("validate_manifest", { content: yamlContent });
+      const { phases, diagnostics } = await invoke<ManifestValidation>("validate_manifest", { content: yamlContent });
 
       if (phases.length === 0) {
         alert("The YAML doesn't appear to be a valid manifest (no phases found).");
         return;
       }
 
+      const errors = diagnostics.filter((d) => d.severity === "error");
+      if (errors.length > 0) {
+        const problems = errors.map((d) => (d.span ? `Line ${d.span.line}: ${d.message}` : d.message)).join("\n");
+        if (!confirm(`The manifest has problems that will fail at run time:\n\n${problems}\n\nSave it anyway?`)) {
+          return;
+        }
+      }
+
       // Save the manifest
       const defaultFilename = `new_manifest_${new Date().toISOString().split("T")[0]}.yaml`;
 
@@ -1930,6 +2057,22 @@ ${processLog}${reportContent}
     }
   };
 
+  // Attach local files (images, PDFs, CSV/text) to the next research run
+  const handleAttachFiles = async () => {
+    try {
+      const selected = await open({
+        multiple: true,
+        filters: [{ name: "Attachments", extensions: ["pdf", "png", "jpg", "jpeg", "gif", "webp", "csv", "tsv", "txt", "md", "json"] }]
+      });
+      if (!selected) return;
+      const paths = Array.isArray(selected) ? selected : [selected];
+      setAttachedFiles((prev) => [...prev, ...paths.filter((p) => !prev.includes(p))]);
+    } catch (error) {
+      console.error("Failed to attach files:", error);
+      setLogs((prev) => [...prev, `❌ Failed to attach files: ${error}`]);
+    }
+  };
+
   // Open manifest editor with existing manifest
   const handleEditManifest = (path: string | null) => {
     setManifestEditorPath(path);
@@ -2020,17 +2163,12 @@ ${processLog}${reportContent}
       // Ensure API key is configured
       await invoke("set_api_key", { key: apiKey });
 
-      // Build context from report (if available) and conversation history
-      let context = "";
-      if (report) {
-        context = `Generated Report:\n${report}\n\n`;
-      }
-      if (conversation.length > 0) {
-        context += "Conversation History:\n" + conversation.map(m =>
-          `${m.role === "user" ? "User" : "Assistant"}: ${m.content}`
-        ).join("\n\n");
-      }
-      if (!context) {
+      // Ground the chat in the report: the live run's, or the viewed session's final output
+      const sessionReport = selectedSession
+        ? [...sessionPhaseOutputs].reverse().find((o) => o.status === "completed" && o.output)?.output
+        : null;
+      let context = report || sessionReport || "";
+      if (!context && followupSessionId === null) {
         // Standalone chat mode - provide helpful context about the app
         context = `This is a new conversation. The user is using the Fullintel Agent, a research and intelligence platform.
 You can help with:
@@ -2042,13 +2180,30 @@ You can help with:
 If the user wants to create a new research manifest/template, help them design the phases and structure.`;
       }
 
-      const response = await invoke<string>("send_followup", {
-        question,
-        context,
-        model,
+      // Stream the answer into a placeholder assistant message
+      setConversation((prev) => [...prev, { role: "assistant", content: "" }]);
+      const unlistenTokens = await listen<FollowupTokenPayload>("followup-token", (event) => {
+        setConversation((prev) => {
+          const last = prev[prev.length - 1];
+          return [...prev.slice(0, -1), { ...last, content: last.content + event.payload.token }];
+        });
       });
 
-      setConversation((prev) => [...prev, { role: "assistant", content: response }]);
+      let response: string;
+      try {
+        // With a session, prior turns are loaded and both turns persisted by the backend
+        response = await invoke<string>("send_followup", {
+          sessionId: followupSessionId,
+          question,
+          context,
+          model,
+          history: followupSessionId === null ? conversation : null,
+        });
+      } finally {
+        unlistenTokens();
+      }
+
+      setConversation((prev) => [...prev.slice(0, -1), { role: "assistant", content: response }]);
 
       // Persist conversation to database if we have a current brief
       if (currentBriefId) {
@@ -2069,7 +2224,11 @@ If the user wants to create a new research manifest/template, help them design t
       }
     } catch (error) {
       console.error("Chat failed:", error);
-      setConversation((prev) => [...prev, { role: "assistant", content: `Error: ${error}` }]);
+      setConversation((prev) => {
+        // Replace the streaming placeholder (if any) with the error
+        const rest = prev[prev.length - 1]?.role === "assistant" ? prev.slice(0, -1) : prev;
+        return [...rest, { role: "assistant", content: `Error: ${error}` }];
+      });
     } finally {
       setIsFollowupRunning(false);
     }
@@ -2085,12 +2244,20 @@ If the user wants to create a new research manifest/template, help them design t
       alert("Please enter a target company.");
       return;
     }
+    const missingInputs = manifestInputs
+      .filter((field) => field.required && !inputValues[field.name]?.trim())
+      .map((field) => field.label || field.name);
+    if (missingInputs.length > 0) {
+      alert(`Please fill in: ${missingInputs.join(", ")}`);
+      return;
+    }
 
     setIsRunning(true);
     setStreamingOutput("");
     setReport("");
     resetPhases(); // Reset phases to pending status
     setConversation([]); // Clear previous conversation
+    setFollowupSessionId(null); // Set again from the new run's phase-output events
     setCurrentBriefId(null); // Clear current brief since this is a new research
 
     // Immediate feedback - these show right away
@@ -2102,6 +2269,55 @@ If the user wants to create a new research manifest/template, help them design t
     ];
     setLogs(startLogs);
 
+    // Follow only this run's events, so concurrent runs don't mix tokens and logs
+    const runId = newRunId();
+    const unsubscribe = await subscribeAgentEvents({ runId }, {
+      onLog: (payload) => setLogs((prev) => [...prev, payload.message]),
+      onPhaseUpdate: (payload) => {
+        setPhases((prev) =>
+          prev.map((p) =>
+            p.id === payload.phase_id
+              ? { ...p, status: payload.status as Phase["status"], progress: payload.progress ?? p.progress }
+              : p
+          )
+        );
+        // Clear streaming output when phase changes
+        if (payload.status === "running") {
+          setStreamingOutput("");
+        }
+      },
+      onStreamToken: (payload) => setStreamingOutput((prev) => prev + payload.token),
+      onPhaseOutput: (payload) => {
+        if (payload.session_id !== null) {
+          setFollowupSessionId(payload.session_id);
+        }
+        // IM-5045: Update live prompt display during active research
+        if (payload.status === "running" && (payload.system_prompt || payload.user_input)) {
+          setLivePhasePrompt({
+            phaseId: payload.phase_id,
+            phaseName: payload.phase_name,
+            systemPrompt: payload.system_prompt,
+            userInput: payload.user_input,
+          });
+        } else if (payload.status === "completed" || payload.status === "failed") {
+          // Clear live prompt when phase completes
+          setLivePhasePrompt(null);
+        } else if (payload.status === "awaiting_approval") {
+          // The run waits until the output is approved, edited or rejected
+          setPendingApproval({
+            runId,
+            sessionId: payload.session_id,
+            phaseId: payload.phase_id,
+            phaseName: payload.phase_name,
+            output: payload.output ?? "",
+          });
+          setApprovalEdit(payload.output ?? "");
+          setApprovalFeedback("");
+          setStreamingOutput("");
+        }
+      },
+    });
+
     try {
       await invoke("set_api_key", { key: apiKey });
       setLogs((prev) => [...prev, "✅ API key configured"]);
@@ -2113,6 +2329,9 @@ If the user wants to create a new research manifest/template, help them design t
         company,
         model,
         manifestPathOverride: manifestPath,
+        attachments: attachedFiles.length > 0 ? attachedFiles : null,
+        runId,
+        inputs: manifestInputs.length > 0 ? inputValues : null,
       });
 
       // Give the backend a moment to create the session, then refresh the list
@@ -2148,10 +2367,30 @@ If the user wants to create a new research manifest/template, help them design t
       setLogs((prev) => [...prev, `❌ Error: ${error}`]);
       await refreshSessionsList(); // Also refresh on error to show failed session
     } finally {
+      unsubscribe();
       setIsRunning(false);
+      setPendingApproval(null);
     }
   }
 
+  // Send the reviewer's decision; the run continues (or re-runs the phase) in the backend
+  const submitReview = async (decision: ApprovalDecision) => {
+    if (!pendingApproval) return;
+    try {
+      if (pendingApproval.runId !== null) {
+        await invoke<string>("review_phase", { runId: pendingApproval.runId, decision });
+      } else {
+        // The run ended while waiting: record the decision on the stored session
+        await invoke<string>("review_session_phase", { sessionId: pendingApproval.sessionId, decision });
+        await refreshSessionsList();
+      }
+      setPendingApproval(null);
+    } catch (error) {
+      console.error("Failed to submit review:", error);
+      alert(`Failed to submit review: ${error}`);
+    }
+  };
+
   const providerInfo = getProviderInfoWithCustom(model, customProviders);
 
   // Handle logout
@@ -2195,6 +2434,7 @@ If the user wants to create a new research manifest/template, help them design t
         isOpen={showManifestEditor}
         onClose={() => setShowManifestEditor(false)}
         currentManifestPath={manifestEditorPath}
+        model={model}
         onManifestLoaded={handleManifestLoaded}
         onManifestSaved={handleManifestSaved}
         onRemoveManifest={async (path) => {
@@ -2325,6 +2565,9 @@ If the user wants to create a new research manifest/template, help them design t
               <option value="gemini-3-pro-preview">Gemini 3 Pro</option>
               <option value="gemini-2.0-flash">Gemini 2.0</option>
             </optgroup>
+            <optgroup label="Offline">
+              <option value="mock">Mock (no API calls)</option>
+            </optgroup>
             {customProviders.length > 0 && (
               <optgroup label="Custom">
                 {customProviders.map((provider) => (
@@ -2382,6 +2625,9 @@ If the user wants to create a new research manifest/template, help them design t
                         <option value="gemini-3-pro-preview">Gemini 3 Pro</option>
                         <option value="gemini-2.0-flash">Gemini 2.0</option>
                       </optgroup>
+                      <optgroup label="Offline">
+                        <option value="mock">Mock (no API calls)</option>
+                      </optgroup>
                     </select>
                   </div>
                   <div className="current-session-row">
@@ -3362,6 +3608,29 @@ If the user wants to create a new research manifest/template, help them design t
                   </div>
                 </div>
 
+                {/* Phase Timeline (phases with recorded timing) */}
+                {buildPhaseTimeline(sessionPhaseOutputs).length > 0 && (
+                  <div style={{ marginBottom: "12px", fontSize: "9px" }}>
+                    {buildPhaseTimeline(sessionPhaseOutputs).map((row) => (
+                      <div key={row.phaseId} style={{ display: "flex", alignItems: "center", gap: "8px", marginBottom: "2px" }}>
+                        <span style={{ width: "90px", color: "var(--text-secondary)" }}>{row.name}</span>
+                        <div style={{ flex: 1, position: "relative", height: "8px", background: "var(--blue-25)" }}>
+                          <div
+                            title={`${row.metrics.model} · ${(row.metrics.duration_ms / 1000).toFixed(1)}s`
+                              + (row.metrics.ttft_ms !== null ? ` · first token ${(row.metrics.ttft_ms / 1000).toFixed(1)}s` : "")
+                              + (row.metrics.retries > 0 ? ` · ${row.metrics.retries} retries` : "")}
+                            style={{ position: "absolute", left: `${row.offsetPct}%`, width: `${row.widthPct}%`, height: "100%", background: "var(--blue-400)", borderRadius: "2px" }}
+                          />
+                        </div>
+                        <span style={{ width: "110px", textAlign: "right", color: "var(--text-muted)" }}>
+                          {(row.metrics.duration_ms / 1000).toFixed(1)}s
+                          {row.metrics.tokens_per_sec !== null && ` · ${row.metrics.tokens_per_sec.toFixed(0)} tok/s`}
+                        </span>
+                      </div>
+                    ))}
+                  </div>
+                )}
+
                 {/* Phase Outputs */}
                 {sessionPhaseOutputs.map((output) => (
                   <div key={output.id} style={{ marginTop: "16px", paddingTop: "12px", borderTop: "1px solid var(--border-subtle)" }}>
@@ -3480,6 +3749,17 @@ If the user wants to create a new research manifest/template, help them design t
                       </div>
                     )}
 
+                    {output.truncated && (
+                      <div style={{ marginBottom: "8px", fontSize: "9px", color: "var(--status-invalid)" }}>
+                        ⚠️ truncated at the output token limit - this output is incomplete
+                      </div>
+                    )}
+                    {output.reasoning && (
+                      <details style={{ marginBottom: "8px", fontSize: "9px" }}>
+                        <summary className="text-btn" style={{ fontSize: "8px", cursor: "pointer" }}>view reasoning</summary>
+                        <pre style={{ whiteSpace: "pre-wrap", margin: "4px 0", background: "var(--blue-50)", padding: "6px", borderRadius: "2px", color: "var(--text-secondary)" }}>{output.reasoning}</pre>
+                      </details>
+                    )}
                     {output.output && (
                       <div style={{ fontSize: "10px", lineHeight: 1.6, whiteSpace: "pre-wrap" }}>
                         {output.output}
@@ -3509,6 +3789,9 @@ If the user wants to create a new research manifest/template, help them design t
                     >
                       <span className="header-phase-dot"></span>
                       {getPhaseDisplayName(phase.name)}
+                      {phase.status === 'running' && phase.progress && (
+                        <span className="header-phase-progress">{phase.progress.completed}/{phase.progress.total}</span>
+                      )}
                       {/* Phase dropdown menu */}
                       {phaseMenuOpen === phase.id && (
                         <div
@@ -3578,6 +3861,9 @@ If the user wants to create a new research manifest/template, help them design t
                       >
                         <span className="header-phase-dot"></span>
                         {getPhaseDisplayName(phase.name)}
+                        {phase.status === 'running' && phase.progress && (
+                          <span className="header-phase-progress">{phase.progress.completed}/{phase.progress.total}</span>
+                        )}
                         {/* Phase dropdown menu */}
                         {phaseMenuOpen === phase.id && (
                           <div
@@ -3621,18 +3907,61 @@ If the user wants to create a new research manifest/template, help them design t
                         Configure API Keys
                       </button>
                     )}
-                    {/* Research subject input (manifest-specific label) */}
+                    {/* Research subject input (manifest-specific label), or the manifest's declared inputs */}
                     <div style={{ maxWidth: "300px", margin: "24px auto 0" }}>
-                      <input
-                        type="text"
-                        className="chat-input"
-                        value={company}
-                        onChange={(e) => setCompany(e.target.value)}
-                        placeholder={manifestInputLabel || "Enter research subject..."}
-                        disabled={isRunning}
-                        onKeyDown={(e) => e.key === "Enter" && !isRunning && apiKeyConfigured && company.trim() && startResearch()}
-                        style={{ marginBottom: "8px" }}
-                      />
+                      {manifestInputs.length === 0 ? (
+                        <input
+                          type="text"
+                          className="chat-input"
+                          value={company}
+                          onChange={(e) => setCompany(e.target.value)}
+                          placeholder={manifestInputLabel || "Enter research subject..."}
+                          disabled={isRunning}
+                          onKeyDown={(e) => e.key === "Enter" && !isRunning && apiKeyConfigured && company.trim() && startResearch()}
+                          style={{ marginBottom: "8px" }}
+                        />
+                      ) : (
+                        manifestInputs.map((field) => (
+                          <div key={field.name} style={{ marginBottom: "8px", textAlign: "left" }}>
+                            <label style={{ display: "block", fontSize: "12px", color: "var(--text-secondary)", marginBottom: "2px" }}>
+                              {field.label || field.name}
+                              {field.required && <span style={{ color: "var(--status-invalid)" }}> *</span>}
+                            </label>
+                            {field.type === "enum" ? (
+                              <select
+                                className="chat-input"
+                                value={inputValues[field.name] ?? ""}
+                                onChange={(e) => updateInputValue(field.name, e.target.value)}
+                                disabled={isRunning}
+                              >
+                                <option value="">—</option>
+                                {field.options.map((option) => (
+                                  <option key={option} value={option}>{option}</option>
+                                ))}
+                              </select>
+                            ) : (
+                              <div style={{ display: "flex", gap: "4px" }}>
+                                <input
+                                  type={field.type === "string" || field.type === "file" ? "text" : field.type}
+                                  className="chat-input"
+                                  value={inputValues[field.name] ?? ""}
+                                  onChange={(e) => updateInputValue(field.name, e.target.value)}
+                                  placeholder={field.type === "file" ? "Path to file..." : undefined}
+                                  disabled={isRunning}
+                                />
+                                {field.type === "file" && (
+                                  <button className="refire-btn" onClick={() => browseInputFile(field.name)} disabled={isRunning}>
+                                    Browse
+                                  </button>
+                                )}
+                              </div>
+                            )}
+                            {field.help && (
+                              <div style={{ fontSize: "11px", color: "var(--text-muted)", marginTop: "2px" }}>{field.help}</div>
+                            )}
+                          </div>
+                        ))
+                      )}
                       <button
                         className="refire-btn"
                         onClick={startResearch}
@@ -3645,6 +3974,51 @@ If the user wants to create a new research manifest/template, help them design t
                   </div>
                 ) : (
                   <div>
+                    {/* Approval checkpoint: the run waits for the reviewer's decision */}
+                    {pendingApproval && (
+                      <div style={{ marginBottom: "16px", padding: "12px", border: "1px solid var(--blue-300)", borderRadius: "4px", background: "var(--blue-50)" }}>
+                        <div style={{ fontSize: "10px", fontWeight: 500, color: "var(--blue-700)", marginBottom: "8px" }}>
+                          ⏸ Review required: {pendingApproval.phaseName}
+                        </div>
+                        <textarea
+                          value={approvalEdit}
+                          onChange={(e) => setApprovalEdit(e.target.value)}
+                          rows={10}
+                          style={{ width: "100%", fontSize: "11px", fontFamily: "monospace", padding: "6px", boxSizing: "border-box", background: "var(--bg-card)", border: "1px solid var(--border-default)", borderRadius: "2px" }}
+                        />
+                        <input
+                          type="text"
+                          value={approvalFeedback}
+                          onChange={(e) => setApprovalFeedback(e.target.value)}
+                          placeholder="Feedback for a re-run (e.g. wrong company: use the US parent)"
+                          style={{ width: "100%", fontSize: "11px", padding: "6px", margin: "8px 0", boxSizing: "border-box", border: "1px solid var(--border-default)", borderRadius: "2px" }}
+                        />
+                        <div style={{ display: "flex", gap: "8px" }}>
+                          <button
+                            className="refire-btn"
+                            onClick={() =>
+                              submitReview(
+                                approvalEdit === pendingApproval.output
+                                  ? { action: "approve" }
+                                  : { action: "edit", output: approvalEdit }
+                              )
+                            }
+                          >
+                            {approvalEdit === pendingApproval.output ? "✅ Approve" : "✅ Approve with Edits"}
+                          </button>
+                          <button
+                            className="refire-btn"
+                            onClick={() => submitReview({ action: "reject", feedback: approvalFeedback.trim() })}
+                            disabled={!approvalFeedback.trim()}
+                          >
+                            ↩ Reject & Re-run
+                          </button>
+                          <button className="refire-btn" onClick={() => submitReview({ action: "cancel" })}>
+                            ⏹ Cancel Run
+                          </button>
+                        </div>
+                      </div>
+                    )}
                     {/* IM-5045: Live Prompt View during active research */}
                     {livePhasePrompt && (
                       <div style={{ marginBottom: "16px", border: "1px solid var(--blue-200)", borderRadius: "4px", overflow: "hidden" }}>
@@ -3866,6 +4240,28 @@ If the user wants to create a new research manifest/template, help them design t
             >
               Research
             </button>
+            {!chatMode && (
+              <button
+                className="mode-btn"
+                onClick={handleAttachFiles}
+                onContextMenu={(e) => { e.preventDefault(); setAttachedFiles([]); }}
+                disabled={isRunning}
+                style={{
+                  padding: "4px 12px",
+                  fontSize: "10px",
+                  border: "1px solid var(--border-color)",
+                  borderRadius: "4px",
+                  background: "transparent",
+                  color: attachedFiles.length > 0 ? "var(--blue-700)" : "var(--text-secondary)",
+                  cursor: "pointer"
+                }}
+                title={attachedFiles.length > 0
+                  ? `${attachedFiles.join("\n")}\n(right-click to clear)`
+                  : "Attach PDFs, images or CSV files as phase inputs"}
+              >
+                📎 {attachedFiles.length > 0 ? `${attachedFiles.length} attached` : "Attach"}
+              </button>
+            )}
           </div>
           <div className="chat-input-container">
             <textarea
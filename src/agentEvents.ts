@@ -0,0 +1,112 @@
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+// Every agent event carries the run it belongs to (matches Rust EventScope).
+// `seq` increases across all of a run's events.
+export type EventScope = {
+  run_id: string;
+  session_id: number | null;
+  seq: number;
+};
+
+export type LogPayload = EventScope & { message: string };
+// Items of a foreach (fan-out) phase finished out of the total (matches Rust PhaseProgress)
+export type PhaseProgress = { completed: number; total: number };
+
+export type PhasePayload = EventScope & { phase_id: string; status: string; progress: PhaseProgress | null };
+export type StreamPayload = EventScope & { token: string; phase_id: string };
+
+// Timing for one phase run (matches Rust PhaseMetrics)
+export type PhaseMetrics = {
+  provider: string | null;
+  model: string;  // Model actually used
+  started_at: string;
+  finished_at: string;
+  ttft_ms: number | null;  // Time to first streamed token
+  duration_ms: number;
+  retries: number;
+  output_tokens: number | null;
+  tokens_per_sec: number | null;
+};
+
+// Phase output payload for session persistence (matches Rust PhaseOutputPayload)
+// IM-5001, IM-5002: Extended with system_prompt and user_input for user data accessibility
+export type PhaseOutputPayload = EventScope & {
+  phase_id: string;
+  phase_name: string;
+  status: string;  // "running", "completed", "failed"
+  system_prompt: string | null;  // IM-5001: System prompt sent to LLM
+  user_input: string | null;     // IM-5002: User input/manifest data sent to LLM
+  output: string | null;
+  error: string | null;
+  cache_hit: boolean;  // Output was served from the response cache
+  reasoning: string | null;  // Thinking/reasoning trace (reasoning models only)
+  stop_reason: string | null;  // "end_turn", "max_tokens", ...
+  truncated: boolean;  // Output still ends at the token limit after continuations
+  metrics: PhaseMetrics | null;  // Completed phases only
+};
+
+// Token usage summed over a finished run's phases (matches Rust TokenUsagePayload)
+export type TokenUsagePayload = EventScope & {
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens: number;  // Prompt cache write
+  cache_read_input_tokens: number;      // Prompt cache read
+};
+
+// Reviewer decision for a phase at an approval checkpoint (matches Rust ApprovalDecision)
+export type ApprovalDecision =
+  | { action: "approve" }
+  | { action: "edit"; output: string }  // Approve with the output replaced
+  | { action: "reject"; feedback: string }  // Re-run the phase with this feedback
+  | { action: "cancel" };  // End the run
+
+// Which events a subscription receives: one run, one session, or both must match
+export type AgentEventFilter = {
+  runId?: string;
+  sessionId?: number;
+};
+
+export type AgentEventHandlers = {
+  onLog?: (payload: LogPayload) => void;
+  onPhaseUpdate?: (payload: PhasePayload) => void;
+  onStreamToken?: (payload: StreamPayload) => void;
+  onPhaseOutput?: (payload: PhaseOutputPayload) => void;
+  onTokenUsage?: (payload: TokenUsagePayload) => void;
+};
+
+// Run id passed to run_research so the UI can subscribe before the run starts
+export const newRunId = (): string => `run-${crypto.randomUUID()}`;
+
+const matches = (filter: AgentEventFilter, scope: EventScope): boolean =>
+  (filter.runId === undefined || scope.run_id === filter.runId) &&
+  (filter.sessionId === undefined || scope.session_id === filter.sessionId);
+
+// Listen to the agent events matching `filter`. Events of another run or session are
+// ignored, as are repeats of an event type already seen at a later seq.
+// Resolves once all listeners are registered, to a function removing them.
+export async function subscribeAgentEvents(
+  filter: AgentEventFilter,
+  handlers: AgentEventHandlers
+): Promise<UnlistenFn> {
+  const lastSeq = new Map<string, number>();
+
+  const route = <T extends EventScope>(eventName: string, handler?: (payload: T) => void) =>
+    listen<T>(eventName, (event) => {
+      const payload = event.payload;
+      if (!handler || !matches(filter, payload)) return;
+      const key = `${payload.run_id}:${eventName}`;
+      if ((lastSeq.get(key) ?? -1) >= payload.seq) return;
+      lastSeq.set(key, payload.seq);
+      handler(payload);
+    });
+
+  const unlisteners = await Promise.all([
+    route<LogPayload>("agent-log", handlers.onLog),
+    route<PhasePayload>("phase-update", handlers.onPhaseUpdate),
+    route<StreamPayload>("stream-token", handlers.onStreamToken),
+    route<PhaseOutputPayload>("phase-output", handlers.onPhaseOutput),
+    route<TokenUsagePayload>("token-usage", handlers.onTokenUsage),
+  ]);
+
+  return () => unlisteners.forEach((unlisten) => unlisten());
+}
@@ -1,4 +1,4 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { save, open } from "@tauri-apps/plugin-dialog";
 import "./ManifestEditor.css";
@@ -6,8 +6,75 @@ import "./ManifestEditor.css";
 type PhaseInfo = {
   id: string;
   name: string;
+  warnings?: string[];  // e.g. unknown or deprecated model
+  source?: { file: string; overridden_in: string[] } | null;  // Defining file (imports/extends)
 };
 
+const baseName = (path: string) => path.split(/[/\\]/).pop() || path;
+
+// Semantic lint result (matches Rust manifest::lint::Diagnostic)
+type Diagnostic = {
+  severity: "error" | "warning" | "info";
+  code: string;  // e.g. "unknown-dependency"
+  message: string;
+  span: { line: number; column: number; length: number } | null;  // 1-based
+};
+
+// Result of validate_manifest (matches Rust ManifestValidation)
+type ManifestValidation = {
+  phases: PhaseInfo[];
+  diagnostics: Diagnostic[];
+};
+
+// Delay after the last keystroke before linting
+const LINT_DEBOUNCE_MS = 400;
+
+// Saved manifest version (matches Rust auth::ManifestVersionSummary)
+type ManifestVersionSummary = {
+  id: number;
+  revision: number;
+  version: string;  // semantic version from the manifest header
+  content_hash: string;
+  author: string;
+  created_at: string;
+};
+
+// Description and suggested values of a manifest key (from the manifest JSON Schema)
+type KeyHint = { key: string; description: string; values: string[] };
+
+type JsonSchemaNode = {
+  description?: string;
+  enum?: string[];
+  anyOf?: JsonSchemaNode[];
+  properties?: Record<string, JsonSchemaNode>;
+};
+
+// Find `key` in the schema's top-level or definition properties
+const describeKey = (
+  schema: JsonSchemaNode & { definitions?: Record<string, JsonSchemaNode> },
+  key: string
+): KeyHint | null => {
+  const objects = [schema, ...Object.values(schema.definitions ?? {})];
+  for (const object of objects) {
+    const property = object.properties?.[key];
+    if (property?.description) {
+      const values = property.enum ?? property.anyOf?.find((option) => option.enum)?.enum ?? [];
+      return { key, description: property.description, values };
+    }
+  }
+  return null;
+};
+
+// Result of the manifest generator (matches Rust generator::GeneratedManifest)
+type GeneratedManifest = {
+  content: string;
+  rounds: number;  // LLM calls: first draft plus repairs
+  valid: boolean;  // false if lint errors remain
+  diagnostics: Diagnostic[];
+};
+
+const SEVERITY_ICON: Record<Diagnostic["severity"], string> = { error: "✗", warning: "⚠", info: "ℹ" };
+
 type ManifestEditorProps = {
   isOpen: boolean;
   onClose: () => void;
@@ -15,6 +82,7 @@ type ManifestEditorProps = {
   onManifestLoaded: (path: string, name: string, phases: PhaseInfo[]) => void;
   onManifestSaved: (path: string, name: string) => void;
   onRemoveManifest?: (path: string) => void;
+  model?: string;  // Model that drafts manifests in the generator
 };
 
 export function ManifestEditor({
@@ -24,6 +92,7 @@ export function ManifestEditor({
   onManifestLoaded,
   onManifestSaved,
   onRemoveManifest,
+  model,
 }: ManifestEditorProps) {
   const [content, setContent] = useState("");
   const [filePath, setFilePath] = useState<string | null>(null);
@@ -35,6 +104,16 @@ export function ManifestEditor({
   const [validatedPhases, setValidatedPhases] = useState<PhaseInfo[]>([]);
   const [isLoading, setIsLoading] = useState(false);
   const [isSaving, setIsSaving] = useState(false);
+  const [diagnostics, setDiagnostics] = useState<Diagnostic[]>([]);
+  const [history, setHistory] = useState<ManifestVersionSummary[]>([]);
+  const [historyDiff, setHistoryDiff] = useState<{ versionId: number; text: string } | null>(null);
+  const [manifestSchema, setManifestSchema] = useState<JsonSchemaNode | null>(null);
+  const [keyHint, setKeyHint] = useState<KeyHint | null>(null);
+  const [showGenerator, setShowGenerator] = useState(false);
+  const [briefDescription, setBriefDescription] = useState("");
+  const [briefExamples, setBriefExamples] = useState("");
+  const [isGenerating, setIsGenerating] = useState(false);
+  const editorRef = useRef<HTMLTextAreaElement>(null);
 
   // Load current manifest content when opening editor with existing manifest
   useEffect(() => {
@@ -46,6 +125,91 @@ export function ManifestEditor({
     }
   }, [isOpen, currentManifestPath]);
 
+  // Lint as the author types (debounced)
+  useEffect(() => {
+    if (!isOpen || !content.trim()) {
+      setDiagnostics([]);
+      return;
+    }
+    const timer = setTimeout(async () => {
+      try {
+        setDiagnostics(await invoke<Diagnostic[]>("lint_manifest", { content, path: filePath }));
+      } catch (error) {
+        console.error("Failed to lint manifest:", error);
+      }
+    }, LINT_DEBOUNCE_MS);
+    return () => clearTimeout(timer);
+  }, [isOpen, content, filePath]);
+
+  // Manifest JSON Schema, for key descriptions while editing
+  useEffect(() => {
+    if (!isOpen || manifestSchema) return;
+    invoke<JsonSchemaNode>("get_manifest_json_schema")
+      .then(setManifestSchema)
+      .catch((error) => console.error("Failed to load manifest schema:", error));
+  }, [isOpen, manifestSchema]);
+
+  // Describe the key on the caret's line
+  const updateKeyHint = () => {
+    const editor = editorRef.current;
+    if (!editor || !manifestSchema) return;
+    const text = editor.value;
+    const lineStart = text.lastIndexOf("\n", editor.selectionStart - 1) + 1;
+    const lineEnd = text.indexOf("\n", lineStart);
+    const line = text.slice(lineStart, lineEnd === -1 ? undefined : lineEnd);
+    const key = line.match(/^\s*(?:-\s+)?([A-Za-z_]+):/)?.[1];
+    setKeyHint(key ? describeKey(manifestSchema, key) : null);
+  };
+
+  // Version history of the open file (saved while logged in)
+  useEffect(() => {
+    if (isOpen && filePath) {
+      loadHistory(filePath);
+    } else {
+      setHistory([]);
+    }
+    setHistoryDiff(null);
+  }, [isOpen, filePath]);
+
+  const loadHistory = async (path: string) => {
+    try {
+      setHistory(await invoke<ManifestVersionSummary[]>("get_manifest_history", { path }));
+    } catch (error) {
+      console.error("Failed to load manifest history:", error);
+      setHistory([]);
+    }
+  };
+
+  // Toggle the diff between a version and the one before it
+  const toggleHistoryDiff = async (version: ManifestVersionSummary, previous: ManifestVersionSummary) => {
+    if (historyDiff?.versionId === version.id) {
+      setHistoryDiff(null);
+      return;
+    }
+    try {
+      const text = await invoke<string>("diff_manifest_versions", {
+        fromVersionId: previous.id,
+        toVersionId: version.id,
+      });
+      setHistoryDiff({ versionId: version.id, text: text || "No changes" });
+    } catch (error) {
+      console.error("Failed to diff manifest versions:", error);
+    }
+  };
+
+  // Select a diagnostic's span in the editor
+  const jumpToDiagnostic = (diagnostic: Diagnostic) => {
+    const editor = editorRef.current;
+    if (!editor || !diagnostic.span) return;
+    const lines = content.split("\n");
+    const lineStart = lines
+      .slice(0, diagnostic.span.line - 1)
+      .reduce((offset, line) => offset + line.length + 1, 0);
+    const start = lineStart + diagnostic.span.column - 1;
+    editor.focus();
+    editor.setSelectionRange(start, start + Math.max(diagnostic.span.length, 1));
+  };
+
   // Load manifest content from file
   const loadManifestContent = async (path: string) => {
     setIsLoading(true);
@@ -65,7 +229,7 @@ export function ManifestEditor({
         setDisplayName(nameWithoutExt);
       }
       setIsModified(false);
-      await validateContent(fileContent);
+      await validateContent(fileContent, path);
     } catch (error) {
       console.error("Failed to load manifest:", error);
       setValidationStatus("invalid");
@@ -76,7 +240,8 @@ export function ManifestEditor({
   };
 
   // Validate YAML content - returns true if valid, false otherwise
-  const validateContent = async (yamlContent: string): Promise<boolean> => {
+  // `path` locates imports/extends (defaults to the open file)
+  const validateContent = async (yamlContent: string, path: string | null = filePath): Promise<boolean> => {
     if (!yamlContent.trim()) {
       setValidationStatus("unchecked");
       setValidationMessage("Enter manifest content to validate");
@@ -85,9 +250,24 @@ export function ManifestEditor({
     }
 
     try {
-      const phases = await invoke<PhaseInfo[]>("validate_manifest", { content: yamlContent });
+      const { phases, diagnostics } = await invoke<ManifestValidation>("validate_manifest", { content: yamlContent, path });
+      setDiagnostics(diagnostics);
+
+      // Lint errors would fail (or silently misbehave) at run time, so they block saving
+      const errors = diagnostics.filter((d) => d.severity === "error");
+      if (errors.length > 0) {
+        setValidationStatus("invalid");
+        setValidationMessage(errors.map((d) => (d.span ? `Line ${d.span.line}: ${d.message}` : d.message)).join("\n"));
+        setValidatedPhases([]);
+        return false;
+      }
+
       setValidationStatus("valid");
-      setValidationMessage(`Valid manifest with ${phases.length} phases`);
+      const warningCount = phases.reduce((n, p) => n + (p.warnings?.length ?? 0), 0);
+      setValidationMessage(
+        `Valid manifest with ${phases.length} phases` +
+        (warningCount > 0 ? ` (${warningCount} warning${warningCount === 1 ? "" : "s"})` : "")
+      );
       setValidatedPhases(phases);
       return true;
     } catch (error) {
@@ -120,12 +300,42 @@ export function ManifestEditor({
       setFileName("New Manifest");
       setDisplayName("New Manifest");
       setIsModified(true);
-      await validateContent(template);
+      await validateContent(template, null);
     } catch (error) {
       console.error("Failed to get template:", error);
     }
   };
 
+  // Draft a manifest from the brief (examples are separated by lines of ---)
+  const handleGenerate = async () => {
+    if (!model || !briefDescription.trim()) return;
+    if (isModified && !confirm("Replace the current manifest with a generated draft?")) return;
+
+    setIsGenerating(true);
+    try {
+      const examples = briefExamples.split(/^---\s*$/m).map((e) => e.trim()).filter(Boolean);
+      const generated = await invoke<GeneratedManifest>("generate_manifest", {
+        brief: { description: briefDescription, examples },
+        model,
+      });
+      setContent(generated.content);
+      setFilePath(null);
+      setFileName("Generated Manifest");
+      setDisplayName(generated.content.match(/^\s+name:\s*"?([^"\n]+)"?/m)?.[1] ?? "Generated Manifest");
+      setIsModified(true);
+      setShowGenerator(false);
+      await validateContent(generated.content, null);
+      if (!generated.valid) {
+        alert(`The draft still has problems after ${generated.rounds} attempts. Fix them in the editor before saving.`);
+      }
+    } catch (error) {
+      console.error("Failed to generate manifest:", error);
+      alert(`Failed to generate manifest: ${error}`);
+    } finally {
+      setIsGenerating(false);
+    }
+  };
+
   // Handle open file
   const handleOpenFile = async () => {
     try {
@@ -187,6 +397,7 @@ export function ManifestEditor({
 
       // Add to saved manifests list with user's display name
       await invoke("save_manifest_to_list", { name: nameForList, path: savePath });
+      await loadHistory(savePath);
 
       // Notify parent
       onManifestSaved(savePath, nameForList);
@@ -237,6 +448,7 @@ export function ManifestEditor({
       }
 
       await invoke("save_manifest_to_list", { name: nameForList, path: selectedPath });
+      await loadHistory(selectedPath);
       onManifestSaved(selectedPath, nameForList);
 
     } catch (error) {
@@ -307,6 +519,15 @@ export function ManifestEditor({
             <button className="toolbar-btn" onClick={handleOpenFile} title="Open existing file">
               Open
             </button>
+            {model && (
+              <button
+                className="toolbar-btn"
+                onClick={() => setShowGenerator(!showGenerator)}
+                title="Draft a manifest from a description of the research"
+              >
+                Generate…
+              </button>
+            )}
           </div>
           <div className="toolbar-group">
             <button
@@ -361,6 +582,40 @@ export function ManifestEditor({
           )}
         </div>
 
+        {/* Generator brief */}
+        {showGenerator && (
+          <div className="manifest-generator">
+            <label>
+              What should the protocol research?
+              <textarea
+                value={briefDescription}
+                onChange={(e) => setBriefDescription(e.target.value)}
+                placeholder="e.g. Research a hospital system's communications challenges"
+                rows={2}
+              />
+            </label>
+            <label>
+              Example outputs (optional, separate examples with a line of ---)
+              <textarea
+                value={briefExamples}
+                onChange={(e) => setBriefExamples(e.target.value)}
+                placeholder="Paste a report excerpt or table the protocol should produce"
+                rows={3}
+              />
+            </label>
+            <div className="manifest-generator-actions">
+              <span className="manifest-generator-model">Model: {model}</span>
+              <button
+                className="toolbar-btn validate-btn"
+                onClick={handleGenerate}
+                disabled={isGenerating || !briefDescription.trim()}
+              >
+                {isGenerating ? "Generating…" : "Generate Draft"}
+              </button>
+            </div>
+          </div>
+        )}
+
         {/* File info bar */}
         <div className="manifest-editor-info">
           <div className="display-name-row">
@@ -407,9 +662,11 @@ export function ManifestEditor({
               <div className="loading-overlay">Loading manifest...</div>
             ) : (
               <textarea
+                ref={editorRef}
                 className="yaml-editor"
                 value={content}
                 onChange={(e) => handleContentChange(e.target.value)}
+                onSelect={updateKeyHint}
                 placeholder="Enter your manifest YAML here..."
                 spellCheck={false}
               />
@@ -425,6 +682,41 @@ export function ManifestEditor({
             </div>
             <div className="validation-message">{validationMessage}</div>
 
+            {keyHint && (
+              <div className="key-hint">
+                <code>{keyHint.key}</code> {keyHint.description}
+                {keyHint.values.length > 0 && (
+                  <div className="key-hint-values">Values: {keyHint.values.join(", ")}</div>
+                )}
+              </div>
+            )}
+
+            {diagnostics.length > 0 && (
+              <div className="diagnostics-list">
+                <h4>Problems ({diagnostics.length})</h4>
+                <ul>
+                  {diagnostics.map((diagnostic, idx) => (
+                    <li
+                      key={`${diagnostic.code}-${idx}`}
+                      className={`diagnostic diagnostic-${diagnostic.severity}`}
+                      onClick={() => jumpToDiagnostic(diagnostic)}
+                      title={diagnostic.code}
+                    >
+                      <span className="diagnostic-icon">{SEVERITY_ICON[diagnostic.severity]}</span>
+                      <span className="diagnostic-message">
+                        {diagnostic.span && (
+                          <span className="diagnostic-position">
+                            {diagnostic.span.line}:{diagnostic.span.column}
+                          </span>
+                        )}
+                        {diagnostic.message}
+                      </span>
+                    </li>
+                  ))}
+                </ul>
+              </div>
+            )}
+
             {validatedPhases.length > 0 && (
               <div className="phases-preview">
                 <h4>Phases ({validatedPhases.length})</h4>
@@ -433,11 +725,54 @@ export function ManifestEditor({
                     <li key={phase.id}>
                       <span className="phase-number">{idx + 1}.</span>
                       <span className="phase-name">{phase.name}</span>
+                      {phase.source && phase.source.file !== filePath && (
+                        <div className="phase-source" title={phase.source.file}>
+                          from {baseName(phase.source.file)}
+                        </div>
+                      )}
+                      {phase.source && phase.source.file !== filePath && phase.source.overridden_in.length > 0 && (
+                        <div className="phase-source">
+                          overridden in {phase.source.overridden_in.map(baseName).join(", ")}
+                        </div>
+                      )}
+                      {phase.warnings?.map((warning) => (
+                        <div key={warning} style={{ color: "var(--status-warning, #b45309)", fontSize: "10px" }}>
+                          ⚠ {warning}
+                        </div>
+                      ))}
                     </li>
                   ))}
                 </ul>
               </div>
             )}
+
+            {history.length > 0 && (
+              <div className="history-list">
+                <h4>History ({history.length})</h4>
+                <ul>
+                  {history.map((version, idx) => {
+                    const previous = history[idx + 1];
+                    return (
+                      <li key={version.id} title={`sha256 ${version.content_hash}`}>
+                        <span className="history-revision">r{version.revision}</span>
+                        <span className="history-version">v{version.version}</span>
+                        <div className="history-meta">
+                          {version.author} · {new Date(version.created_at).toLocaleString()}
+                          {previous && (
+                            <button className="history-diff-btn" onClick={() => toggleHistoryDiff(version, previous)}>
+                              {historyDiff?.versionId === version.id ? "hide diff" : `diff vs r${previous.revision}`}
+                            </button>
+                          )}
+                        </div>
+                        {historyDiff?.versionId === version.id && (
+                          <pre className="history-diff">{historyDiff.text}</pre>
+                        )}
+                      </li>
+                    );
+                  })}
+                </ul>
+              </div>
+            )}
           </div>
         </div>
       </div>